@@ -14,10 +14,12 @@ extern crate quote;
 use proc_macro::TokenStream as TokenStream1;
 use proc_macro2::TokenStream as TokenStream2;
 use syn::{
-    parse_macro_input, punctuated::Punctuated, token::Comma, BareFnArg, Error, FnArg, ItemFn,
-    LitStr, Result,
+    parse_macro_input, parse_quote, punctuated::Punctuated, token::Comma, BareFnArg, DeriveInput,
+    Error, FnArg, ItemFn, LitStr, Result, ReturnType, Type,
 };
 
+mod record;
+
 /// Declares a new event handler detour for any of the client events.
 ///
 /// This attribute takes a single string literal which names the event
@@ -34,9 +36,48 @@ use syn::{
 ///
 /// Within the handler function, a special `call_original` macro will
 /// be available for forwarding any arguments to the detoured function.
+/// Invoking it without arguments forwards the arguments the handler
+/// was called with.
+/// When the handler is run by the [`replay`] engine, it calls a stub
+/// instead.
+///
+/// Every call is counted and timed in the handler's [`metrics`] entry.
+///
+/// Panics in the handler are caught before they can unwind into C++
+/// code, see [`unwind`] for details. The original function is called
+/// in place of the handler unless `call_original` was already invoked,
+/// in which case a zeroed value is returned. Arguments to the handler
+/// must therefore be [`Copy`], and it must return `()`, a raw pointer
+/// or an integer.
+///
+/// Handlers taking exactly a `*mut` `this` pointer and either a
+/// `*mut dml::Record` or a `#[record]` argument are registered with the
+/// [`replay`] engine. Other handlers cannot be replayed.
+///
+/// An argument marked with `#[record]` receives a `*mut dml::Record`
+/// from C++ code and converts it to its declared type, which must
+/// implement `dml::FromRecord`, e.g. through
+/// [`DmlRecord`](derive@DmlRecord). When the conversion fails, the
+/// error is printed and the original function is called instead.
+/// While a [`schema`] recording is running, the layout of the record is
+/// observed beforehand:
+///
+/// ```ignore
+/// # use oleaf_hook_macros::event;
+/// #[event("HandleQuestDialog")]
+/// fn quest_dialog_handler(this: *mut c_void, #[record] dialog: QuestDialog) {
+///     // ...
+///     call_original!()
+/// }
+/// ```
 ///
 /// Note that the `detour`, `linkme` and `oleaf-hook` crates are required
 /// as direct dependencies of any crate this macro is used in.
+///
+/// [`metrics`]: ../oleaf_hook/metrics/index.html
+/// [`replay`]: ../oleaf_hook/replay/index.html
+/// [`schema`]: ../oleaf_hook/dml/schema/index.html
+/// [`unwind`]: ../oleaf_hook/unwind/index.html
 #[proc_macro_attribute]
 pub fn event(attr: TokenStream1, item: TokenStream1) -> TokenStream1 {
     let event = parse_macro_input!(attr as LitStr);
@@ -47,6 +88,44 @@ pub fn event(attr: TokenStream1, item: TokenStream1) -> TokenStream1 {
         .into()
 }
 
+/// Derives `dml::FromRecord` on a struct with named fields.
+///
+/// As reading a `dml::Record` is unsafe, [`TryFrom`] is only derived for
+/// `&dml::RecordBuf`, which owns its record. Records from C++ code are
+/// converted through the unsafe `FromRecord::from_record` instead.
+///
+/// Every struct field is read from the record field of the same name
+/// and must implement `dml::FromField`, whose type ID is checked
+/// against the record field. Conversion fails with a `dml::FieldError`
+/// when a field is missing or has a different type.
+///
+/// Fields of type [`Option`] may be missing from the record. The name
+/// of the record field can be changed with `#[dml(rename = "...")]`:
+///
+/// ```ignore
+/// # use oleaf_hook_macros::DmlRecord;
+/// #[derive(DmlRecord)]
+/// struct QuestDialog {
+///     #[dml(rename = "QuestID")]
+///     quest_id: u32,
+///     #[dml(rename = "Title")]
+///     title: String,
+///     #[dml(rename = "GoalID")]
+///     goal_id: Option<u32>,
+/// }
+/// ```
+///
+/// Note that the `oleaf-hook` crate is required as a direct dependency
+/// of any crate this macro is used in.
+#[proc_macro_derive(DmlRecord, attributes(dml))]
+pub fn derive_dml_record(item: TokenStream1) -> TokenStream1 {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    record::expand(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
 fn into_bare_args(args: &Punctuated<FnArg, Comma>) -> Punctuated<BareFnArg, Comma> {
     args.iter()
         .map(|arg| {
@@ -63,7 +142,127 @@ fn into_bare_args(args: &Punctuated<FnArg, Comma>) -> Punctuated<BareFnArg, Comm
         .collect()
 }
 
-fn expand(func: ItemFn, event: LitStr) -> Result<TokenStream2> {
+// Replaces the argument patterns of `sig` with the `__arg{i}` identifiers
+// and the types of `#[record]` arguments with DML record pointers. Returns
+// the statements that bind the original patterns inside the handler.
+fn rewrite_args(sig: &mut syn::Signature, event: &LitStr) -> Result<Vec<TokenStream2>> {
+    let mut bindings = Vec::with_capacity(sig.inputs.len());
+
+    for (i, arg) in sig.inputs.iter_mut().enumerate() {
+        let pat_type = match arg {
+            FnArg::Typed(pat_type) => pat_type,
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new_spanned(
+                    receiver,
+                    "event handlers cannot take `self`",
+                ))
+            }
+        };
+
+        let raw = format_ident!("__arg{}", i);
+        let pat = std::mem::replace(&mut *pat_type.pat, parse_quote!(#raw));
+        let ty = &pat_type.ty;
+
+        let len = pat_type.attrs.len();
+        pat_type.attrs.retain(|attr| !attr.path.is_ident("record"));
+        if pat_type.attrs.len() == len {
+            bindings.push(quote! { let #pat: #ty = #raw; });
+            continue;
+        }
+
+        bindings.push(quote! {
+            let #pat: #ty = match unsafe { #raw.as_ref() }.map(|record| {
+                unsafe { ::oleaf_hook::dml::schema::observe(#event, record) };
+                // SAFETY: C++ code keeps the record alive and unmodified
+                // while the handler runs.
+                unsafe { <#ty as ::oleaf_hook::dml::FromRecord>::from_record(record) }
+            }) {
+                ::core::option::Option::Some(::core::result::Result::Ok(value)) => value,
+                ::core::option::Option::Some(::core::result::Result::Err(e)) => {
+                    ::std::println!(::core::concat!("Failed to convert record for ", #event, ": {}"), e);
+                    return call_original!();
+                }
+                ::core::option::Option::None => {
+                    ::std::println!(::core::concat!("Got null record for ", #event, "!"));
+                    return call_original!();
+                }
+            };
+        });
+        *pat_type.ty = parse_quote!(*mut ::oleaf_hook::dml::Record);
+    }
+
+    Ok(bindings)
+}
+
+// The return value of a handler that panicked after calling the original
+// function is made up, so it must be valid when zeroed.
+fn check_output(output: &ReturnType) -> Result<()> {
+    let ty = match output {
+        ReturnType::Default => return Ok(()),
+        ReturnType::Type(_, ty) => ty,
+    };
+    let valid = match &**ty {
+        Type::Tuple(tuple) => tuple.elems.is_empty(),
+        Type::Ptr(_) => true,
+        Type::Path(path) => {
+            path.qself.is_none()
+                && path.path.segments.last().map_or(false, |s| {
+                    s.arguments.is_empty() && is_integer(&s.ident.to_string())
+                })
+        }
+        _ => false,
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::new_spanned(
+            ty,
+            "event handlers must return `()`, a raw pointer or an integer",
+        ))
+    }
+}
+
+fn is_integer(ident: &str) -> bool {
+    matches!(
+        ident,
+        "i8" | "u8" | "i16" | "u16" | "i32" | "u32" | "i64" | "u64" | "isize" | "usize"
+    ) || matches!(
+        ident,
+        "c_char" | "c_schar" | "c_uchar" | "c_short" | "c_ushort" | "c_int" | "c_uint"
+    ) || matches!(
+        ident,
+        "c_long" | "c_ulong" | "c_longlong" | "c_ulonglong" | "c_size_t"
+    )
+}
+
+// Checks if the handler takes a raw `this` pointer and a `*mut Record`,
+// which `#[record]` arguments have been rewritten to at this point.
+fn is_replayable(args: &Punctuated<FnArg, Comma>) -> bool {
+    let tys: Vec<_> = args
+        .iter()
+        .filter_map(|arg| match arg {
+            FnArg::Typed(pat_type) => Some(&*pat_type.ty),
+            FnArg::Receiver(_) => None,
+        })
+        .collect();
+
+    match tys[..] {
+        [Type::Ptr(this), Type::Ptr(record)] => {
+            this.mutability.is_some()
+                && record.mutability.is_some()
+                && matches!(
+                    &*record.elem,
+                    Type::Path(path) if path.qself.is_none()
+                        && path.path.segments.last().map_or(false, |s| s.ident == "Record")
+                )
+        }
+        _ => false,
+    }
+}
+
+fn expand(mut func: ItemFn, event: LitStr) -> Result<TokenStream2> {
+    let bindings = rewrite_args(&mut func.sig, &event)?;
     let attrs = &func.attrs;
     let vis = &func.vis;
     let sig = &func.sig;
@@ -86,45 +285,125 @@ fn expand(func: ItemFn, event: LitStr) -> Result<TokenStream2> {
             "function must not be declared as async fn",
         ));
     }
+    check_output(&sig.output)?;
 
+    // Handlers in the `(this, record)` shape can be invoked by the replay engine.
+    let replay = if is_replayable(&sig.inputs) {
+        quote! {
+            #[allow(unsafe_op_in_unsafe_fn, unused_unsafe)]
+            unsafe fn __replay(
+                this: *mut ::std::os::raw::c_void,
+                record: *mut ::oleaf_hook::dml::Record,
+            ) {
+                let _ = unsafe { #ident(this as _, record) };
+            }
+
+            #[::linkme::distributed_slice(::oleaf_hook::replay::REPLAY_HANDLERS)]
+            static __REPLAY_HANDLER: ::oleaf_hook::replay::Handler = ::oleaf_hook::replay::Handler {
+                name: #event,
+                invoke: __replay,
+            };
+        }
+    } else {
+        TokenStream2::new()
+    };
+
+    let arg_idents: Vec<_> = (0..sig.inputs.len())
+        .map(|i| format_ident!("__arg{}", i))
+        .collect();
+    let arg_tys: Vec<_> = into_bare_args(&sig.inputs)
+        .into_iter()
+        .map(|arg| arg.ty)
+        .collect();
+    let output_ty = &sig.output;
+
+    let detour_mod = format_ident!("__{}_oleaf_detour", ident);
     let detour_ident = format_ident!("__{}_OLEAF_ORIGINAL", ident);
+    let metrics_ident = format_ident!("__{}_OLEAF_METRICS", ident);
     Ok(quote! {
-        use ::detour::static_detour;
-        static_detour! {
-            static #detour_ident: #sig_ty;
+        // `static_detour` needs itself in scope, which would clash between
+        // several handlers in the same module.
+        #[allow(non_snake_case)]
+        mod #detour_mod {
+            use super::*;
+            use ::detour::static_detour;
+            static_detour! {
+                pub(super) static #detour_ident: #sig_ty;
+            }
         }
+        use self::#detour_mod::#detour_ident;
+
+        #[allow(non_upper_case_globals)]
+        #[::linkme::distributed_slice(::oleaf_hook::metrics::EVENT_METRICS)]
+        static #metrics_ident: ::oleaf_hook::metrics::EventMetrics =
+            ::oleaf_hook::metrics::EventMetrics::new(#event);
 
         const _: () = {
+            #[cfg(windows)]
             type __EventDetourFn = #sig_ty;
 
-            #[allow(unsafe_op_in_unsafe_fn)]
+            // Set when the hook was disabled after a panic so it is not reinstalled.
+            #[cfg(windows)]
+            static __POISONED: ::core::sync::atomic::AtomicBool =
+                ::core::sync::atomic::AtomicBool::new(false);
+
+            #[cfg(windows)]
+            #[allow(unsafe_op_in_unsafe_fn, unused_unsafe)]
             #[::linkme::distributed_slice(::oleaf_hook::event::INIT_EVENT_DETOURS)]
             unsafe fn __install_detour(dispatcher: *mut ::std::os::raw::c_void) {
                 use ::core::{option::Option, result::Result};
 
                 // Opt out if the event handler for this function is already installed.
-                if #detour_ident.is_enabled() {
+                if #detour_ident.is_enabled() || __POISONED.load(::core::sync::atomic::Ordering::SeqCst) {
                     return;
                 }
 
+                // Panics are contained here so they never unwind into C++ frames.
+                let detour = move |#(#arg_idents: #arg_tys),*| #output_ty {
+                    let _in_flight = ::oleaf_hook::unload::InFlight::enter();
+                    ::oleaf_hook::unwind::contain(
+                        #event,
+                        || unsafe { #ident(#(#arg_idents),*) },
+                        || unsafe { #detour_ident.call(#(#arg_idents),*) },
+                        || {
+                            __POISONED.store(true, ::core::sync::atomic::Ordering::SeqCst);
+                            let _ = #detour_ident.disable();
+                        },
+                    )
+                };
+
                 // See if we can query the handler for our event...
-                let mut event = ::oleaf_hook::cxx::String::new(#event)
-                    .expect(::core::concat!("Got invalid handler name: ", #event, "!"));
+                let mut event = match ::oleaf_hook::cxx::String::new(#event) {
+                    Result::Ok(event) => event,
+                    Result::Err(_) => {
+                        ::std::println!(::core::concat!("Got invalid handler name: ", #event, "!"));
+                        return;
+                    }
+                };
                 if let Option::Some(ptr) = ::oleaf_hook::event::find_event_by_name(dispatcher, &mut event) {
-                    ::oleaf_hook::paging::with_read_write_page(ptr, 0x100, || {
+                    let installed = ::oleaf_hook::paging::with_read_write_page(ptr, 0x100, || {
                         ::std::println!(::core::concat!("Found ", #event, " at {:#p}"), ptr);
-                        // ...and detour it.
-                        #detour_ident.initialize(
+                        // ...and detour it. A previously unhooked detour is only re-enabled.
+                        match #detour_ident.initialize(
                             ::core::mem::transmute::<_, __EventDetourFn>(ptr),
-                            #ident,
-                        )
-                        .unwrap_or_else(|e| {
-                            ::core::panic!(::core::concat!("Failed to install detour for ", #event, ": {}!"), e);
-                        });
-                        #detour_ident.enable()
-                            .expect(::core::concat!("Failed to enable detour for ", #event));
-                    })
-                    .expect("Failed to alter page table permissions to read/write!");
+                            detour,
+                        ) {
+                            Result::Ok(_) | Result::Err(::detour::Error::AlreadyInitialized) => {
+                                #detour_ident.enable()
+                            }
+                            Result::Err(e) => Result::Err(e),
+                        }
+                    });
+
+                    match installed {
+                        Result::Ok(Result::Ok(())) => (),
+                        Result::Ok(Result::Err(e)) => {
+                            ::std::println!(::core::concat!("Failed to install detour for ", #event, ": {}!"), e);
+                        }
+                        Result::Err(e) => {
+                            ::std::println!("Failed to alter page table permissions to read/write: {}!", e);
+                        }
+                    }
                 }
             }
 
@@ -133,18 +412,38 @@ fn expand(func: ItemFn, event: LitStr) -> Result<TokenStream2> {
             unsafe fn __uninstall_detour() {
                 let _ = #detour_ident.disable();
             }
+
+            #[::linkme::distributed_slice(::oleaf_hook::event::EVENT_HANDLER_NAMES)]
+            static __EVENT_NAME: &'static str = #event;
+
+            #replay
         };
 
-        #[allow(unused_braces, unused_macros)]
+        #[allow(unused_braces, unused_macros, unused_unsafe)]
         #(#attrs)*
         #vis #sig {
+            let __oleaf_timer = ::oleaf_hook::metrics::Timer::start(&#metrics_ident);
+
             // Injected into scope for use by the function author.
             macro_rules! call_original {
-                ($($tt:tt)*) => {
-                    #detour_ident.call($($tt)*)
+                () => {
+                    call_original!(#(#arg_idents),*)
                 };
+                ($($tt:tt)+) => {{
+                    let start = ::std::time::Instant::now();
+                    ::oleaf_hook::unwind::mark_original_called();
+                    let ret = if ::oleaf_hook::replay::is_active() {
+                        unsafe { ::oleaf_hook::replay::call_original_stub(($($tt)*)) }
+                    } else {
+                        #detour_ident.call($($tt)*)
+                    };
+                    __oleaf_timer.record_original(start);
+                    ret
+                }};
             }
 
+            #(#bindings)*
+
             {
                 #block
             }
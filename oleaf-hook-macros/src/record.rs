@@ -0,0 +1,131 @@
+use proc_macro2::TokenStream as TokenStream2;
+use syn::{
+    Data, DeriveInput, Error, Fields, GenericArgument, Lit, Meta, NestedMeta, PathArguments,
+    Result, Type,
+};
+
+// Gets `T` if `ty` is spelled as `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+
+    let segment = path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Gets the value of `#[dml(rename = "...")]` if present.
+fn rename(attrs: &[syn::Attribute]) -> Result<Option<String>> {
+    let mut name = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("dml")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new_spanned(meta, "expected `dml(...)`")),
+        };
+
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+                    match nv.lit {
+                        Lit::Str(lit) => name = Some(lit.value()),
+                        lit => return Err(Error::new_spanned(lit, "expected a string literal")),
+                    }
+                }
+                nested => return Err(Error::new_spanned(nested, "unknown dml attribute")),
+            }
+        }
+    }
+
+    Ok(name)
+}
+
+pub fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &data.fields,
+                    "DmlRecord can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                ident,
+                "DmlRecord can only be derived for structs",
+            ))
+        }
+    };
+
+    let inits = fields
+        .iter()
+        .map(|field| {
+            let field_ident = field.ident.as_ref().unwrap();
+            let name = rename(&field.attrs)?
+                .unwrap_or_else(|| field_ident.to_string().trim_start_matches("r#").to_owned());
+
+            // Missing optional fields are fine, but present ones are still type-checked.
+            Ok(match option_inner(&field.ty) {
+                Some(inner) => quote! {
+                    #field_ident: match record.get(#name) {
+                        ::core::option::Option::Some(field) => {
+                            ::core::option::Option::Some(field.value_as::<#inner>()?)
+                        }
+                        ::core::option::Option::None => ::core::option::Option::None,
+                    }
+                },
+                None => {
+                    let ty = &field.ty;
+                    quote! { #field_ident: record.get_as::<#ty>(#name)? }
+                }
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        impl #impl_generics ::oleaf_hook::dml::FromRecord for #ident #ty_generics #where_clause {
+            #[allow(unused_unsafe)]
+            unsafe fn from_record(
+                record: &::oleaf_hook::dml::Record,
+            ) -> ::core::result::Result<Self, ::oleaf_hook::dml::FieldError> {
+                // SAFETY: The caller guarantees that the record and the
+                // string storage of its fields are live and unmodified
+                // until all data is copied out of them.
+                unsafe {
+                    ::core::result::Result::Ok(Self {
+                        #(#inits),*
+                    })
+                }
+            }
+        }
+
+        impl #impl_generics ::core::convert::TryFrom<&::oleaf_hook::dml::RecordBuf>
+            for #ident #ty_generics #where_clause
+        {
+            type Error = ::oleaf_hook::dml::FieldError;
+
+            fn try_from(
+                buf: &::oleaf_hook::dml::RecordBuf,
+            ) -> ::core::result::Result<Self, Self::Error> {
+                // SAFETY: The buffer owns the record and its storage, and
+                // C++ code can only access them through a mutable borrow.
+                unsafe { <Self as ::oleaf_hook::dml::FromRecord>::from_record(buf.record()) }
+            }
+        }
+    })
+}
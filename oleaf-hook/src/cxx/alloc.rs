@@ -0,0 +1,262 @@
+//! Memory allocation for objects that are shared with C++ code.
+
+use std::{
+    alloc::Layout,
+    lazy::SyncOnceCell,
+    mem,
+    os::raw::{c_size_t, c_void},
+    ptr,
+};
+
+/// An allocator for memory owned by C++ objects.
+///
+/// Whenever Rust code grows or replaces storage of a C++-managed object,
+/// the new memory must come from the same allocator that C++ code will
+/// eventually use to free it. Mixing up allocators corrupts the heap.
+///
+/// # Safety
+///
+/// Implementations must return memory that is valid for `layout` or a
+/// null pointer, and [`Allocator::deallocate`] must accept every pointer
+/// previously returned by [`Allocator::allocate`] together with the
+/// same layout.
+pub unsafe trait Allocator {
+    /// Allocates a block of memory for `layout`.
+    ///
+    /// Returns a null pointer if the allocation failed.
+    fn allocate(&self, layout: Layout) -> *mut u8;
+
+    /// Frees a block of memory at `ptr` that was allocated for `layout`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by [`Allocator::allocate`] for the
+    /// same `layout` and must not be used afterwards.
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout);
+}
+
+// Allocates storage for `capacity` elements plus a null terminator,
+// aborting on failure like Rust collections do.
+pub(crate) fn allocate_str<T, A: Allocator>(alloc: &A, capacity: usize) -> *mut T {
+    let layout = Layout::array::<T>(capacity + 1).expect("capacity overflow");
+    let ptr = alloc.allocate(layout);
+    if ptr.is_null() {
+        std::alloc::handle_alloc_error(layout);
+    }
+    ptr as *mut T
+}
+
+// Gets the capacity MSVC grows a string to when it needs room for
+// `required` elements, rounding up to the `mask` of its SSO buffer and
+// growing by at least half of the old `capacity` to amortize writes.
+pub(crate) fn grow_str_capacity(required: usize, capacity: usize, mask: usize) -> usize {
+    (required | mask).max(capacity + capacity / 2)
+}
+
+// SAFETY: `ptr` must stem from `allocate_str` with the same `capacity`.
+pub(crate) unsafe fn deallocate_str<T, A: Allocator>(alloc: &A, ptr: *mut T, capacity: usize) {
+    let layout = Layout::array::<T>(capacity + 1).expect("capacity overflow");
+    unsafe { alloc.deallocate(ptr as *mut u8, layout) }
+}
+
+/// The global Rust allocator.
+///
+/// Memory obtained from it must never be freed or reallocated by C++
+/// code. Objects using it are thus only suitable for handing out views
+/// of data that remains owned by Rust.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Global;
+
+unsafe impl Allocator for Global {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        if layout.size() == 0 {
+            // The global allocator does not support zero-sized
+            // allocations, so we hand out a dangling pointer instead.
+            layout.align() as *mut u8
+        } else {
+            unsafe { std::alloc::alloc(layout) }
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        if layout.size() != 0 {
+            unsafe { std::alloc::dealloc(ptr, layout) }
+        }
+    }
+}
+
+/// The function signature of the client's `malloc` or `operator new`.
+pub type FnAllocate = unsafe extern "C" fn(c_size_t) -> *mut c_void;
+
+/// The function signature of the client's `free` or `operator delete`.
+pub type FnFree = unsafe extern "C" fn(*mut c_void);
+
+// MSVC's `std::allocator` manually aligns big allocations to 32 bytes
+// and stores the pointer it actually got from `operator new` right in
+// front of the user's block. `std::allocator::deallocate` relies on
+// this, so we have to replicate it for C++ code to free our storage.
+const BIG_ALLOCATION_THRESHOLD: usize = 4096;
+const BIG_ALLOCATION_ALIGNMENT: usize = 32;
+const NON_USER_SIZE: usize = 2 * mem::size_of::<usize>() + BIG_ALLOCATION_ALIGNMENT - 1;
+
+// The alignment guaranteed by `operator new` on x64. Stricter alignments
+// use a separate overload of it which we don't support.
+const DEFAULT_NEW_ALIGNMENT: usize = 16;
+
+/// An allocator on the heap of the game client.
+///
+/// This replicates the behavior of MSVC's `std::allocator` on top of the
+/// client's own `malloc` and `free`, so objects allocated from it can be
+/// grown and freed by C++ code just like its own. `operator new` and
+/// `operator delete` may be used as well, but note that `operator new`
+/// throws a C++ exception on failure, which must never unwind into Rust
+/// code.
+///
+/// Layouts with an alignment greater than 16 are not supported and fail
+/// to allocate.
+#[derive(Clone, Copy, Debug)]
+pub struct GameHeap {
+    allocate: FnAllocate,
+    free: FnFree,
+}
+
+impl GameHeap {
+    /// Creates an allocator that obtains memory from `allocate` and
+    /// returns it to `free`.
+    ///
+    /// # Safety
+    ///
+    /// The functions must be the client's `malloc` and `free`, or
+    /// `operator new` and `operator delete`, as used by its
+    /// `std::allocator`.
+    pub unsafe fn new(allocate: FnAllocate, free: FnFree) -> Self {
+        Self { allocate, free }
+    }
+}
+
+unsafe impl Allocator for GameHeap {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        if layout.align() > DEFAULT_NEW_ALIGNMENT {
+            return ptr::null_mut();
+        }
+
+        let size = layout.size();
+        if size == 0 {
+            // C++ containers never allocate zero bytes, so this block is
+            // never handed to the client either.
+            return layout.align() as *mut u8;
+        }
+
+        // SAFETY: The functions were promised to be the client's.
+        unsafe {
+            if size < BIG_ALLOCATION_THRESHOLD {
+                return (self.allocate)(size) as *mut u8;
+            }
+
+            let block = match size.checked_add(NON_USER_SIZE) {
+                Some(block) => block,
+                None => return ptr::null_mut(),
+            };
+            let container = (self.allocate)(block) as usize;
+            if container == 0 {
+                return ptr::null_mut();
+            }
+
+            let ptr = (container + NON_USER_SIZE) & !(BIG_ALLOCATION_ALIGNMENT - 1);
+            *(ptr as *mut usize).sub(1) = container;
+            ptr as *mut u8
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        let size = layout.size();
+        if size == 0 {
+            return;
+        }
+
+        unsafe {
+            let ptr = if size < BIG_ALLOCATION_THRESHOLD {
+                ptr as *mut c_void
+            } else {
+                *(ptr as *const usize).sub(1) as *mut c_void
+            };
+            (self.free)(ptr)
+        }
+    }
+}
+
+// The DLLs of the dynamically linked C runtimes MSVC has shipped with,
+// in lowercase. The UCRT is also reached through its API sets.
+#[cfg(windows)]
+const CRT_MODULES: &[&str] = &[
+    "ucrtbase.dll",
+    "ucrtbased.dll",
+    "api-ms-win-crt-heap-l1-1-0.dll",
+    "msvcrt.dll",
+    "msvcr120.dll",
+    "msvcr110.dll",
+    "msvcr100.dll",
+    "msvcr90.dll",
+    "msvcr80.dll",
+];
+
+#[cfg(windows)]
+impl GameHeap {
+    /// Creates an allocator from the `malloc` and `free` functions the
+    /// client imports from a dynamically linked C runtime.
+    ///
+    /// Imports of the same name from other DLLs are ignored, as they
+    /// need not be the CRT's functions.
+    pub fn from_imports(module: &crate::Module<'_>) -> std::io::Result<Self> {
+        let is_crt = |dll: &str| CRT_MODULES.contains(&dll.to_ascii_lowercase().as_str());
+
+        // SAFETY: The imports resolve to the CRT functions of the same name.
+        unsafe {
+            Ok(Self::new(
+                mem::transmute(module.find_import_in(is_crt, "malloc")?),
+                mem::transmute(module.find_import_in(is_crt, "free")?),
+            ))
+        }
+    }
+
+    /// Creates an allocator from the `malloc` and `free` functions of a
+    /// statically linked C runtime, found by their signatures.
+    ///
+    /// Each signature must match exactly one address, see
+    /// [`Module::find_unique_signature`](crate::Module::find_unique_signature).
+    ///
+    /// # Safety
+    ///
+    /// The signatures must identify the client's `malloc` and `free`.
+    pub unsafe fn from_signatures(
+        module: &crate::Module<'_>,
+        allocate: &str,
+        free: &str,
+    ) -> std::io::Result<Self> {
+        unsafe {
+            Ok(Self::new(
+                mem::transmute(module.find_unique_signature(allocate)?),
+                mem::transmute(module.find_unique_signature(free)?),
+            ))
+        }
+    }
+}
+
+static GAME_HEAP: SyncOnceCell<GameHeap> = SyncOnceCell::new();
+
+/// Initializes the allocator returned by [`game_heap`].
+///
+/// # Panics
+///
+/// Panics if the game heap has already been initialized previously.
+pub fn initialize_game_heap(heap: GameHeap) {
+    if GAME_HEAP.set(heap).is_err() {
+        panic!("Game heap was already initialized!");
+    }
+}
+
+/// Gets the allocator on the heap of the game client, if it was set up
+/// using [`initialize_game_heap`].
+pub fn game_heap() -> Option<GameHeap> {
+    GAME_HEAP.get().copied()
+}
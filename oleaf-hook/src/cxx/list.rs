@@ -0,0 +1,117 @@
+use std::{marker::PhantomData, os::raw::c_size_t, ptr};
+
+// A node of a `std::list`, chained in a ring through the head node.
+#[repr(C)]
+pub(super) struct ListNode<T> {
+    pub(super) next: *mut ListNode<T>,
+    pub(super) prev: *mut ListNode<T>,
+    pub(super) value: T,
+}
+
+/// An ABI-compatible `std::list` that is borrowed from the C++ side.
+///
+/// This represents a doubly-linked list whose nodes are chained in a
+/// ring through a sentinel head node that holds no value. The nodes are
+/// fully managed by C++ to which an immutable view on the Rust side is
+/// granted.
+///
+/// # Safety
+///
+/// The user must ensure that their handle to a [`List`] instance does
+/// not outlive the corresponding object on the C++ side.
+#[repr(C)]
+pub struct List<T> {
+    head: *mut ListNode<T>,
+    size: c_size_t,
+}
+
+impl<T> List<T> {
+    /// Gets the number of elements in the list.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Checks if the list is empty, i.e. is holding zero elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets an iterator over the elements of the list, front to back or
+    /// in reverse.
+    ///
+    /// The iterator yields at most [`List::len`] elements and stops at
+    /// null links, so it terminates even when the nodes form a cycle
+    /// that bypasses the head.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the inferred lifetime does not exceed
+    /// the duration of the managed object on the C++ side and that no
+    /// other code modifies the list during iteration.
+    pub unsafe fn iter(&self) -> ListIter<'_, T> {
+        let (front, back) = if self.head.is_null() {
+            (ptr::null_mut(), ptr::null_mut())
+        } else {
+            unsafe { ((*self.head).next, (*self.head).prev) }
+        };
+
+        ListIter {
+            head: self.head,
+            front,
+            back,
+            remaining: self.size,
+            _marker: PhantomData,
+        }
+    }
+
+    // Gets the sentinel node, which marks the end of the list.
+    pub(super) fn head(&self) -> *mut ListNode<T> {
+        self.head
+    }
+}
+
+/// An iterator over the elements of a [`List`].
+///
+/// This is created by [`List::iter`].
+pub struct ListIter<'a, T> {
+    head: *mut ListNode<T>,
+    front: *mut ListNode<T>,
+    back: *mut ListNode<T>,
+    remaining: usize,
+    _marker: PhantomData<&'a T>,
+}
+
+impl<'a, T> Iterator for ListIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 || self.front.is_null() || self.front == self.head {
+            return None;
+        }
+        self.remaining -= 1;
+
+        // SAFETY: The nodes were promised to be alive in `List::iter`.
+        let node = unsafe { &*self.front };
+        self.front = node.next;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for ListIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 || self.back.is_null() || self.back == self.head {
+            return None;
+        }
+        self.remaining -= 1;
+
+        // SAFETY: The nodes were promised to be alive in `List::iter`.
+        let node = unsafe { &*self.back };
+        self.back = node.prev;
+        Some(&node.value)
+    }
+}
@@ -0,0 +1,193 @@
+use std::{cmp::Ordering, marker::PhantomData, os::raw::*, ptr};
+
+// A `std::pair` as stored in the nodes of associative containers.
+#[repr(C)]
+pub(super) struct Pair<K, V> {
+    pub(super) key: K,
+    pub(super) value: V,
+}
+
+// A node of the red-black tree behind a `std::map`.
+//
+// The head node is marked as nil and links to the leftmost node, the
+// root and the rightmost node. The leaves of the tree link back to it.
+#[repr(C)]
+struct TreeNode<K, V> {
+    left: *mut TreeNode<K, V>,
+    parent: *mut TreeNode<K, V>,
+    right: *mut TreeNode<K, V>,
+    _color: c_char,
+    is_nil: c_char,
+    pair: Pair<K, V>,
+}
+
+// SAFETY: `node` must be null or point to a live tree node.
+unsafe fn is_nil<K, V>(node: *const TreeNode<K, V>) -> bool {
+    node.is_null() || unsafe { (*node).is_nil != 0 }
+}
+
+/// An ABI-compatible `std::map` that is borrowed from the C++ side.
+///
+/// This represents a red-black tree of key-value pairs ordered by key.
+/// The nodes are fully managed by C++ to which an immutable view on the
+/// Rust side is granted.
+///
+/// # Safety
+///
+/// The user must ensure that their handle to a [`Map`] instance does
+/// not outlive the corresponding object on the C++ side.
+#[repr(C)]
+pub struct Map<K, V> {
+    head: *mut TreeNode<K, V>,
+    size: c_size_t,
+}
+
+impl<K, V> Map<K, V> {
+    /// Gets the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Checks if the map is empty, i.e. is holding zero entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets an iterator over the entries of the map, ordered by key.
+    ///
+    /// The iterator yields at most [`Map::len`] entries and follows a
+    /// bounded number of links, so it terminates even when the nodes
+    /// form a cycle.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the inferred lifetime does not exceed
+    /// the duration of the managed object on the C++ side and that no
+    /// other code modifies the map during iteration.
+    pub unsafe fn iter(&self) -> MapIter<'_, K, V> {
+        let node = if self.head.is_null() {
+            self.head
+        } else {
+            unsafe { (*self.head).left }
+        };
+
+        MapIter {
+            node,
+            remaining: self.size,
+            // An in-order traversal walks every edge once in each direction.
+            moves: 2 * self.size + 2,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Finds the entry for which `f` returns [`Ordering::Equal`].
+    ///
+    /// `f` compares the key of an entry with the key being searched for,
+    /// which must be consistent with the order of the map.
+    ///
+    /// # Safety
+    ///
+    /// See [`Map::iter`].
+    pub unsafe fn find_by<F>(&self, mut f: F) -> Option<(&K, &V)>
+    where
+        F: FnMut(&K) -> Ordering,
+    {
+        if self.head.is_null() {
+            return None;
+        }
+
+        let mut node = unsafe { (*self.head).parent };
+        // A search never visits more nodes than there are.
+        for _ in 0..self.size {
+            if unsafe { is_nil(node) } {
+                break;
+            }
+
+            let current = unsafe { &*node };
+            node = match f(&current.pair.key) {
+                Ordering::Less => current.right,
+                Ordering::Greater => current.left,
+                Ordering::Equal => return Some((&current.pair.key, &current.pair.value)),
+            };
+        }
+
+        None
+    }
+
+    /// Gets the value for `key`.
+    ///
+    /// # Safety
+    ///
+    /// See [`Map::iter`].
+    pub unsafe fn get(&self, key: &K) -> Option<&V>
+    where
+        K: Ord,
+    {
+        unsafe { self.find_by(|k| k.cmp(key)) }.map(|(_, v)| v)
+    }
+}
+
+/// An iterator over the entries of a [`Map`].
+///
+/// This is created by [`Map::iter`].
+pub struct MapIter<'a, K, V> {
+    node: *mut TreeNode<K, V>,
+    remaining: usize,
+    moves: usize,
+    _marker: PhantomData<&'a (K, V)>,
+}
+
+impl<'a, K, V> MapIter<'a, K, V> {
+    // Follows a link, unless we already followed more links than a
+    // well-formed tree has.
+    fn step(&mut self, next: *mut TreeNode<K, V>) -> Option<*mut TreeNode<K, V>> {
+        self.moves = self.moves.checked_sub(1)?;
+        Some(next)
+    }
+
+    // SAFETY: `node` must point to a live tree node that isn't nil.
+    unsafe fn successor(&mut self, node: *mut TreeNode<K, V>) -> Option<*mut TreeNode<K, V>> {
+        unsafe {
+            if !is_nil((*node).right) {
+                // The successor is the leftmost node in the right subtree.
+                let mut next = self.step((*node).right)?;
+                while !is_nil((*next).left) {
+                    next = self.step((*next).left)?;
+                }
+                Some(next)
+            } else {
+                // The successor is the first ancestor we reach from the left.
+                let mut node = node;
+                let mut parent = self.step((*node).parent)?;
+                while !is_nil(parent) && node == (*parent).right {
+                    node = parent;
+                    parent = self.step((*parent).parent)?;
+                }
+                Some(parent)
+            }
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for MapIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // SAFETY: The nodes were promised to be alive in `Map::iter`.
+        unsafe {
+            if self.remaining == 0 || is_nil(self.node) {
+                return None;
+            }
+            self.remaining -= 1;
+
+            let node = &*self.node;
+            self.node = self.successor(self.node).unwrap_or(ptr::null_mut());
+            Some((&node.pair.key, &node.pair.value))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
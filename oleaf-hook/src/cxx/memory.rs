@@ -0,0 +1,344 @@
+use std::{
+    fmt,
+    marker::PhantomData,
+    ptr::NonNull,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+// The signature of the virtual functions of `_Ref_count_base`.
+type FnRefCount = unsafe extern "fastcall" fn(*mut RefCount);
+
+// The leading entries of the vtable of `_Ref_count_base`.
+#[repr(C)]
+struct RefCountVtable {
+    // Destroys the managed object once no strong references are left.
+    destroy: FnRefCount,
+    // Frees the control block once no references at all are left.
+    delete_this: FnRefCount,
+}
+
+// The control block of a `std::shared_ptr`, i.e. `_Ref_count_base`.
+//
+// All strong references together hold a single weak reference, which
+// keeps the control block alive until the object is destroyed.
+#[repr(C)]
+struct RefCount {
+    vtable: *const RefCountVtable,
+    uses: AtomicU32,
+    weaks: AtomicU32,
+}
+
+impl RefCount {
+    // Takes a strong reference unless the object was already destroyed.
+    fn increment_nonzero(&self) -> bool {
+        let mut count = self.uses.load(Ordering::Relaxed);
+        while count != 0 {
+            match self.uses.compare_exchange_weak(
+                count,
+                count + 1,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(current) => count = current,
+            }
+        }
+        false
+    }
+
+    // Releases a strong reference, destroying the object and releasing
+    // the collective weak reference when it was the last one.
+    //
+    // SAFETY: `this` must point to a live control block on which we
+    // hold a strong reference.
+    unsafe fn decrement(this: *mut Self) {
+        unsafe {
+            if (*this).uses.fetch_sub(1, Ordering::AcqRel) == 1 {
+                ((*(*this).vtable).destroy)(this);
+                Self::decrement_weak(this);
+            }
+        }
+    }
+
+    // SAFETY: `this` must point to a live control block on which we
+    // hold a weak reference.
+    unsafe fn decrement_weak(this: *mut Self) {
+        unsafe {
+            if (*this).weaks.fetch_sub(1, Ordering::AcqRel) == 1 {
+                ((*(*this).vtable).delete_this)(this);
+            }
+        }
+    }
+}
+
+// SAFETY: `rep` must be null or point to a live control block.
+unsafe fn use_count(rep: *const RefCount) -> u32 {
+    if rep.is_null() {
+        0
+    } else {
+        unsafe { (*rep).uses.load(Ordering::Acquire) }
+    }
+}
+
+// SAFETY: `rep` must be null or point to a live control block.
+unsafe fn weak_count(rep: *const RefCount) -> u32 {
+    if rep.is_null() {
+        0
+    } else {
+        let (uses, weaks) = unsafe {
+            (
+                (*rep).uses.load(Ordering::Acquire),
+                (*rep).weaks.load(Ordering::Acquire),
+            )
+        };
+        // Don't count the weak reference held by the strong ones.
+        weaks.saturating_sub((uses != 0) as u32)
+    }
+}
+
+/// An ABI-compatible `std::shared_ptr` that is borrowed from the C++
+/// side.
+///
+/// This represents a pointer to an object together with the control
+/// block counting the references to it. A [`SharedRef`] can be taken to
+/// keep the object alive for as long as Rust code needs it.
+///
+/// # Safety
+///
+/// The user must ensure that their handle to a [`SharedPtr`] instance
+/// does not outlive the corresponding object on the C++ side.
+#[repr(C)]
+pub struct SharedPtr<T> {
+    ptr: *mut T,
+    rep: *mut RefCount,
+}
+
+impl<T> SharedPtr<T> {
+    /// Gets a raw pointer to the managed object.
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr
+    }
+
+    /// Gets a reference to the managed object, or [`None`] if the
+    /// pointer is null.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the inferred lifetime does not exceed
+    /// the duration of the managed object and that no other code
+    /// modifies it concurrently.
+    pub unsafe fn as_ref(&self) -> Option<&T> {
+        unsafe { self.ptr.as_ref() }
+    }
+
+    /// Gets the number of strong references to the managed object.
+    pub fn use_count(&self) -> u32 {
+        // SAFETY: We are borrowing a strong reference.
+        unsafe { use_count(self.rep) }
+    }
+
+    /// Gets the number of weak references to the managed object.
+    pub fn weak_count(&self) -> u32 {
+        // SAFETY: We are borrowing a strong reference.
+        unsafe { weak_count(self.rep) }
+    }
+
+    /// Takes a new strong reference to the managed object.
+    ///
+    /// Returns [`None`] if the pointer or its control block is null.
+    pub fn share(&self) -> Option<SharedRef<T>> {
+        let ptr = NonNull::new(self.ptr)?;
+        let rep = NonNull::new(self.rep)?;
+
+        // SAFETY: The reference we are borrowing keeps the count above 0.
+        unsafe { rep.as_ref() }.uses.fetch_add(1, Ordering::AcqRel);
+        Some(SharedRef {
+            ptr,
+            rep,
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// An ABI-compatible `std::weak_ptr` that is borrowed from the C++ side.
+///
+/// # Safety
+///
+/// The user must ensure that their handle to a [`WeakPtr`] instance
+/// does not outlive the corresponding object on the C++ side.
+#[repr(C)]
+pub struct WeakPtr<T> {
+    ptr: *mut T,
+    rep: *mut RefCount,
+}
+
+impl<T> WeakPtr<T> {
+    /// Gets the number of strong references to the managed object.
+    pub fn use_count(&self) -> u32 {
+        // SAFETY: We are borrowing a weak reference.
+        unsafe { use_count(self.rep) }
+    }
+
+    /// Gets the number of weak references to the managed object.
+    pub fn weak_count(&self) -> u32 {
+        // SAFETY: We are borrowing a weak reference.
+        unsafe { weak_count(self.rep) }
+    }
+
+    /// Checks if the managed object was already destroyed.
+    pub fn expired(&self) -> bool {
+        self.use_count() == 0
+    }
+
+    /// Takes a new strong reference to the managed object, unless it was
+    /// already destroyed.
+    ///
+    /// Returns [`None`] if the object is gone or the pointer is null.
+    pub fn lock(&self) -> Option<SharedRef<T>> {
+        let ptr = NonNull::new(self.ptr)?;
+        let rep = NonNull::new(self.rep)?;
+
+        // SAFETY: The reference we are borrowing keeps the block alive.
+        if !unsafe { rep.as_ref() }.increment_nonzero() {
+            return None;
+        }
+        Some(SharedRef {
+            ptr,
+            rep,
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// A strong reference to an object managed by a `std::shared_ptr`.
+///
+/// Cloning the handle takes a new reference and dropping it releases
+/// the reference through the control block, which destroys the object
+/// once no references are left, just like C++ code would.
+///
+/// This makes it possible to keep game objects alive past a hook.
+/// Note that C++ code may still modify the object concurrently, so it is
+/// only accessed through the unsafe [`SharedRef::get`].
+#[repr(C)]
+pub struct SharedRef<T> {
+    ptr: NonNull<T>,
+    rep: NonNull<RefCount>,
+    _marker: PhantomData<T>,
+}
+
+// SAFETY: The reference counts are only ever modified atomically, like
+// in `std::sync::Arc`.
+unsafe impl<T: Send + Sync> Send for SharedRef<T> {}
+unsafe impl<T: Send + Sync> Sync for SharedRef<T> {}
+
+impl<T> SharedRef<T> {
+    /// Gets a raw pointer to the managed object.
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    /// Gets the reference as a `std::shared_ptr` for passing it to C++
+    /// code.
+    pub fn as_shared_ptr(&self) -> &SharedPtr<T> {
+        // SAFETY: The pointers are laid out like in `SharedPtr`.
+        unsafe { &*(self as *const Self as *const SharedPtr<T>) }
+    }
+
+    /// Gets a reference to the managed object.
+    ///
+    /// The strong reference keeps the object alive, but not from being
+    /// modified by C++ code.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that no other code modifies the object
+    /// for as long as the result is in use.
+    pub unsafe fn get(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+
+    /// Gets the number of strong references to the managed object.
+    pub fn use_count(&self) -> u32 {
+        self.as_shared_ptr().use_count()
+    }
+}
+
+impl<T> Clone for SharedRef<T> {
+    fn clone(&self) -> Self {
+        // SAFETY: We hold a strong reference, so the block is alive.
+        unsafe { self.rep.as_ref() }
+            .uses
+            .fetch_add(1, Ordering::AcqRel);
+        Self {
+            ptr: self.ptr,
+            rep: self.rep,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for SharedRef<T> {
+    fn drop(&mut self) {
+        // SAFETY: We hold a strong reference and the control block was
+        // created by C++ code which provides its vtable.
+        unsafe { RefCount::decrement(self.rep.as_ptr()) }
+    }
+}
+
+impl<T> fmt::Debug for SharedRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedRef")
+            .field("ptr", &self.ptr)
+            .field("use_count", &self.use_count())
+            .finish()
+    }
+}
+
+/// An ABI-compatible `std::unique_ptr` with the default deleter that is
+/// borrowed from the C++ side.
+///
+/// # Safety
+///
+/// The user must ensure that their handle to a [`UniquePtr`] instance
+/// does not outlive the corresponding object on the C++ side.
+#[repr(C)]
+pub struct UniquePtr<T> {
+    ptr: *mut T,
+}
+
+impl<T> UniquePtr<T> {
+    /// Gets a raw pointer to the owned object.
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr
+    }
+
+    /// Checks if the pointer is null, i.e. does not own an object.
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_null()
+    }
+
+    /// Gets a reference to the owned object, or [`None`] if the pointer
+    /// is null.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the inferred lifetime does not exceed
+    /// the duration of the owned object and that no other code modifies
+    /// it concurrently.
+    pub unsafe fn as_ref(&self) -> Option<&T> {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> fmt::Debug for UniquePtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("UniquePtr").field(&self.ptr).finish()
+    }
+}
+
+// Keep these in sync with the MSVC layouts.
+assert_eq_size!(SharedPtr<u8>, [u8; 0x10]);
+assert_eq_size!(WeakPtr<u8>, [u8; 0x10]);
+assert_eq_size!(SharedRef<u8>, [u8; 0x10]);
+assert_eq_size!(UniquePtr<u8>, [u8; 0x8]);
+assert_eq_size!(RefCount, [u8; 0x10]);
@@ -3,11 +3,25 @@
 //! These types are only meant to be compatible with release builds of
 //! software produced by the MSVC compiler.
 
+pub mod alloc;
+
+mod list;
+pub use self::list::{List, ListIter};
+
+mod map;
+pub use self::map::{Map, MapIter};
+
+mod memory;
+pub use self::memory::{SharedPtr, SharedRef, UniquePtr, WeakPtr};
+
 mod string;
 pub use self::string::{String, Str};
 
+mod unordered_map;
+pub use self::unordered_map::{hash_bytes, UnorderedMap, UnorderedMapIter};
+
 mod vector;
-pub use self::vector::Vector;
+pub use self::vector::{Vector, VectorBuf};
 
 mod wstring;
 pub use self::wstring::{WString, WStr};
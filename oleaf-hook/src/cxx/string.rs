@@ -1,13 +1,18 @@
 use std::{
+    borrow::Cow,
     ffi::{CStr, CString, NulError},
+    fmt,
+    ops::Deref,
     os::raw::{c_char, c_size_t},
-    ptr,
+    ptr, slice,
+    str::{self, Utf8Error},
 };
 
+use super::alloc::{self, Allocator, Global};
+
 #[repr(C)]
 union Impl {
-    // Invariant 1: One byte must always be reserved for trailing null.
-    // Invariant 2: No `\0` in the middle of the string supported.
+    // Invariant: One byte must always be reserved for trailing null.
     buf: [c_char; Self::SSO_LEN],
     ptr: *mut c_char,
 }
@@ -17,46 +22,18 @@ assert_eq_size!(Impl, [u8; 16]);
 
 impl Impl {
     const SSO_LEN: usize = 0x10;
-
-    // SAFETY: The C++ side MUST NOT modify the resulting string and `len` must be correct.
-    pub unsafe fn from_rust<S: Into<Vec<u8>>>(value: S, len: usize) -> Result<Self, NulError> {
-        let cstr = CString::new(value)?;
-
-        if len < Self::SSO_LEN {
-            // We have enough capacity to perform short string
-            // optimization while preserving the null terminator.
-            unsafe {
-                let mut buf = [0; Self::SSO_LEN];
-                ptr::copy_nonoverlapping(
-                    cstr.as_bytes().as_ptr() as *const i8,
-                    buf.as_mut_ptr(),
-                    len,
-                );
-
-                Ok(Self { buf })
-            }
-        } else {
-            Ok(Self {
-                ptr: cstr.into_raw(),
-            })
-        }
-    }
-
-    // SAFETY: This function may only be called when no SSO was
-    // employed, i.e. the input string was longer than 15 chars,
-    // and when `Impl::from_rust` was used to obtain this object.
-    pub unsafe fn free_rust(&mut self) {
-        let _ = unsafe { CString::from_raw(self.ptr) };
-    }
 }
 
 /// An ABI-compatible `std::string` that is owned by the Rust side.
 ///
-/// This is a null-terminated string that can be created from the Rust
-/// side and shared with C++ code.
+/// This is a null-terminated string that can be created and edited
+/// from the Rust side and shared with C++ code. Its storage is managed
+/// like MSVC does, with short strings of up to 15 bytes being stored
+/// inline.
 ///
-/// Instances of this string are guaranteed to contain no interior
-/// null bytes and guarantee that their raw data is null-terminated.
+/// The raw data of instances of this string is guaranteed to be
+/// null-terminated. Like MSVC, [`String::from_bytes`] allows interior
+/// null bytes for binary contents, whereas [`String::new`] rejects them.
 ///
 /// # Immutability
 ///
@@ -77,6 +54,9 @@ impl Impl {
 ///
 /// If a Rust handle on a purely C++-managed string is desired, use
 /// the [`Str`] type instead.
+///
+/// Strings that are supposed to be owned by C++ code can be created
+/// with [`Str::new_in`] on the [`GameHeap`](alloc::GameHeap) instead.
 #[repr(C)]
 pub struct String {
     ipl: Impl,
@@ -95,36 +75,197 @@ impl String {
     /// It is within the caller's responsibility that the resulting string
     /// **remains unmodified** when shared with the C++ side.
     pub unsafe fn new<S: ToString>(data: S) -> Result<Self, NulError> {
-        let data = data.to_string();
-        let size = data.len();
+        unsafe { Self::from_vec(data.to_string().into_bytes()) }
+    }
+
+    // SAFETY: Same as `String::new`.
+    pub(crate) unsafe fn from_vec(data: Vec<u8>) -> Result<Self, NulError> {
+        if data.contains(&0) {
+            return Err(CString::new(data).unwrap_err());
+        }
+        Ok(Self::from_bytes(&data))
+    }
 
-        Ok(Self {
-            ipl: unsafe { Impl::from_rust(data, size)? },
-            size,
-            capacity: size, // We don't care about allocating extra space
-        })
+    /// Creates a new string holding the raw bytes `data`, which may
+    /// include interior null bytes.
+    pub fn from_bytes(data: &[u8]) -> Self {
+        let mut this = Self::default();
+        this.push_bytes(data);
+        this
     }
 
     /// Gets a [`CStr`] view to the underlying string data.
     ///
-    /// The underlying data is guaranteed to be valid Rust string data
-    /// when all safety rules of this type were respected.
+    /// The view ends at the first interior null byte, if any.
     pub fn view(&mut self) -> &CStr {
+        unsafe { CStr::from_ptr(self.data_mut()) }
+    }
+
+    /// Appends `data` to the end of the string.
+    pub fn push_str(&mut self, data: &str) {
+        self.push_bytes(data.as_bytes());
+    }
+
+    fn push_bytes(&mut self, data: &[u8]) {
+        self.reserve(data.len());
+        // SAFETY: We reserved enough space for the data and the null.
         unsafe {
-            if self.size < Impl::SSO_LEN {
-                CStr::from_ptr(self.ipl.buf.as_mut_ptr())
-            } else {
-                CStr::from_ptr(self.ipl.ptr)
+            let end = self.data_mut().add(self.size);
+            ptr::copy_nonoverlapping(data.as_ptr() as *const c_char, end, data.len());
+            *end.add(data.len()) = 0;
+        }
+        self.size += data.len();
+    }
+
+    /// Truncates the string to zero length, keeping its storage.
+    pub fn clear(&mut self) {
+        // SAFETY: There is always room for the null terminator.
+        unsafe { *self.data_mut() = 0 };
+        self.size = 0;
+    }
+
+    /// Reserves storage for at least `additional` more bytes.
+    ///
+    /// Like MSVC, this grows the storage by at least half of its current
+    /// capacity to amortize repeated appends. Strings move from the
+    /// inline buffer to the heap once they exceed 15 bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`.
+    pub fn reserve(&mut self, additional: usize) {
+        let required = self
+            .size
+            .checked_add(additional)
+            .expect("capacity overflow");
+        if required > self.capacity {
+            let capacity = alloc::grow_str_capacity(required, self.capacity, Impl::SSO_LEN - 1);
+            self.reallocate(capacity);
+        }
+    }
+
+    /// Shrinks the storage of the string to fit its length.
+    ///
+    /// Like MSVC, this moves strings of up to 15 bytes back into the
+    /// inline buffer. Longer strings keep a capacity rounded up to the
+    /// next multiple of 16, minus the null terminator.
+    pub fn shrink_to_fit(&mut self) {
+        if self.capacity < Impl::SSO_LEN {
+            return;
+        }
+
+        if self.size < Impl::SSO_LEN {
+            let (ptr, capacity) = (unsafe { self.ipl.ptr }, self.capacity);
+            // SAFETY: The heap storage holds the data and the null and is
+            // freed only after copying them out.
+            unsafe {
+                let mut buf = [0; Impl::SSO_LEN];
+                ptr::copy_nonoverlapping(ptr, buf.as_mut_ptr(), self.size + 1);
+                self.ipl.buf = buf;
+                alloc::deallocate_str(&Global, ptr, capacity);
+            }
+            self.capacity = Impl::SSO_LEN - 1;
+        } else {
+            let capacity = self.size | (Impl::SSO_LEN - 1);
+            if capacity < self.capacity {
+                self.reallocate(capacity);
             }
         }
     }
+
+    // Moves the contents of the string into new heap storage that holds
+    // `capacity` bytes plus the null terminator.
+    fn reallocate(&mut self, capacity: usize) {
+        let ptr = alloc::allocate_str(&Global, capacity);
+        // SAFETY: The new storage is large enough for the data and the
+        // null, and the previous one is freed after copying them.
+        unsafe {
+            ptr::copy_nonoverlapping(self.data_mut(), ptr, self.size + 1);
+            if self.capacity >= Impl::SSO_LEN {
+                alloc::deallocate_str(&Global, self.ipl.ptr, self.capacity);
+            }
+        }
+
+        self.ipl.ptr = ptr;
+        self.capacity = capacity;
+    }
+
+    // Gets a pointer to the start of the string data.
+    fn data_mut(&mut self) -> *mut c_char {
+        if self.capacity < Impl::SSO_LEN {
+            unsafe { self.ipl.buf.as_mut_ptr() }
+        } else {
+            unsafe { self.ipl.ptr }
+        }
+    }
+
+    // Creates a bitwise copy of this string for embedding into objects
+    // built on the Rust side.
+    //
+    // SAFETY: The resulting view must not outlive `self`.
+    pub(crate) unsafe fn borrow(&self) -> Str {
+        Str {
+            ipl: unsafe { ptr::read(&self.ipl) },
+            size: self.size,
+            capacity: self.capacity,
+        }
+    }
+}
+
+/// Creates an empty string using the inline buffer.
+impl Default for String {
+    fn default() -> Self {
+        Self {
+            ipl: Impl {
+                buf: [0; Impl::SSO_LEN],
+            },
+            size: 0,
+            capacity: Impl::SSO_LEN - 1,
+        }
+    }
+}
+
+impl From<&str> for String {
+    fn from(data: &str) -> Self {
+        Self::from_bytes(data.as_bytes())
+    }
+}
+
+impl From<&CStr> for String {
+    fn from(data: &CStr) -> Self {
+        Self::from_bytes(data.to_bytes())
+    }
+}
+
+/// Grants access to the read-only accessors of [`Str`].
+impl Deref for String {
+    type Target = Str;
+
+    fn deref(&self) -> &Str {
+        // SAFETY: Both types share the same layout and the view lives no
+        // longer than the string itself.
+        unsafe { &*(self as *const Self as *const Str) }
+    }
+}
+
+impl fmt::Debug for String {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl fmt::Display for String {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
 }
 
 impl Drop for String {
     fn drop(&mut self) {
-        if self.size >= Impl::SSO_LEN {
-            // SAFETY: The string was created in Rust and is not SSO-optimized.
-            unsafe { self.ipl.free_rust() }
+        if self.capacity >= Impl::SSO_LEN {
+            // SAFETY: Heap storage of strings created in Rust always
+            // stems from the global allocator.
+            unsafe { alloc::deallocate_str(&Global, self.ipl.ptr, self.capacity) }
         }
     }
 }
@@ -163,11 +304,141 @@ impl Str {
     /// on the C++ side.
     pub unsafe fn view(&mut self) -> &CStr {
         unsafe {
-            if self.size < Impl::SSO_LEN {
+            if self.capacity < Impl::SSO_LEN {
                 CStr::from_ptr(self.ipl.buf.as_mut_ptr())
             } else {
                 CStr::from_ptr(self.ipl.ptr)
             }
         }
     }
+
+    /// Gets the length of the string in bytes, excluding the null
+    /// terminator.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Checks if the string is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the number of bytes the string can hold without reallocating
+    /// its storage, excluding the null terminator.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Gets the contents of the string as a byte slice, including any
+    /// interior null bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        // Reading the string is sound as long as the handle is, see `Str`.
+        unsafe {
+            if self.capacity < Impl::SSO_LEN {
+                slice::from_raw_parts(self.ipl.buf.as_ptr() as *const u8, self.size)
+            } else {
+                slice::from_raw_parts(self.ipl.ptr as *const u8, self.size)
+            }
+        }
+    }
+
+    /// Gets the contents of the string as a Rust string slice if they are
+    /// valid UTF-8.
+    pub fn to_str(&self) -> Result<&str, Utf8Error> {
+        str::from_utf8(self.as_bytes())
+    }
+
+    /// Gets the contents of the string as a Rust string, replacing invalid
+    /// UTF-8 with `U+FFFD REPLACEMENT CHARACTER`.
+    ///
+    /// This only allocates when the contents are not valid UTF-8.
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        std::string::String::from_utf8_lossy(self.as_bytes())
+    }
+
+    /// Creates a new string holding `data`, with storage
+    /// allocated from `alloc`.
+    ///
+    /// Rust code never frees the resulting string. It is meant to be
+    /// moved into an object owned by C++ code, which will free it through
+    /// its own allocator. For this to be sound, `alloc` should be the
+    /// [`GameHeap`](alloc::GameHeap).
+    pub fn new_in<A: Allocator>(data: &[u8], alloc: &A) -> Self {
+        let mut this = Self {
+            ipl: Impl {
+                buf: [0; Impl::SSO_LEN],
+            },
+            size: 0,
+            capacity: Impl::SSO_LEN - 1,
+        };
+        // SAFETY: The string is empty and thus has no heap storage yet.
+        unsafe { this.assign(data, alloc) };
+        this
+    }
+
+    /// Replaces the contents of the string with `data`.
+    ///
+    /// The current storage is reused when `data` fits into it. Otherwise
+    /// new storage is obtained from `alloc` and the previous allocation,
+    /// if any, is returned to it.
+    ///
+    /// # Safety
+    ///
+    /// The string must be a live object whose heap storage, if any, was
+    /// allocated by `alloc`. No other code may access the string while
+    /// it is being modified.
+    pub unsafe fn assign<A: Allocator>(&mut self, data: &[u8], alloc: &A) {
+        let len = data.len();
+        let sso = self.capacity < Impl::SSO_LEN;
+
+        if sso && len < Impl::SSO_LEN {
+            self.capacity = Impl::SSO_LEN - 1;
+        } else if len > self.capacity {
+            let capacity = alloc::grow_str_capacity(len, self.capacity, Impl::SSO_LEN - 1);
+            let ptr = alloc::allocate_str(alloc, capacity);
+            if !sso {
+                unsafe { alloc::deallocate_str(alloc, self.ipl.ptr, self.capacity) };
+            }
+
+            self.ipl.ptr = ptr;
+            self.capacity = capacity;
+        }
+
+        unsafe {
+            let buf = if self.capacity < Impl::SSO_LEN {
+                self.ipl.buf.as_mut_ptr()
+            } else {
+                self.ipl.ptr
+            };
+            ptr::copy_nonoverlapping(data.as_ptr() as *const c_char, buf, len);
+            *buf.add(len) = 0;
+        }
+        self.size = len;
+    }
+}
+
+// Reading the string is sound as long as the handle is, see `Str`.
+impl fmt::Debug for Str {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_string_lossy(), f)
+    }
+}
+
+impl PartialEq<str> for Str {
+    fn eq(&self, other: &str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for Str {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl fmt::Display for Str {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.to_string_lossy(), f)
+    }
 }
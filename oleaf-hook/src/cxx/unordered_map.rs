@@ -0,0 +1,144 @@
+use std::os::raw::{c_float, c_size_t};
+
+use super::{
+    list::{ListIter, ListNode},
+    map::Pair,
+    List, Vector,
+};
+
+/// Hashes `bytes` like MSVC's `std::hash` does.
+///
+/// Integral keys are hashed over their in-memory representation and
+/// strings over their characters, using the FNV-1a algorithm. This can
+/// be used to look up such keys with [`UnorderedMap::find_by_hash`].
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF29CE484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001B3)
+    })
+}
+
+/// An ABI-compatible `std::unordered_map` that is borrowed from the C++
+/// side.
+///
+/// This represents a hash table whose entries are stored in a single
+/// [`List`], where the entries of each bucket are adjacent. A vector
+/// holds the first and last node of each bucket. The table is fully
+/// managed by C++ to which an immutable view on the Rust side is
+/// granted.
+///
+/// # Safety
+///
+/// The user must ensure that their handle to an [`UnorderedMap`]
+/// instance does not outlive the corresponding object on the C++ side.
+#[repr(C)]
+pub struct UnorderedMap<K, V> {
+    max_load_factor: c_float,
+    list: List<Pair<K, V>>,
+    buckets: Vector<*mut ListNode<Pair<K, V>>>,
+    mask: c_size_t,
+    bucket_count: c_size_t,
+}
+
+impl<K, V> UnorderedMap<K, V> {
+    /// Gets the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    /// Checks if the map is empty, i.e. is holding zero entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the number of buckets in the hash table.
+    pub fn bucket_count(&self) -> usize {
+        self.bucket_count
+    }
+
+    /// Gets the maximum average number of entries per bucket before the
+    /// table is grown.
+    pub fn max_load_factor(&self) -> f32 {
+        self.max_load_factor
+    }
+
+    /// Gets an iterator over the entries of the map in no particular
+    /// order.
+    ///
+    /// The iterator guards against cycles like [`List::iter`] does.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the inferred lifetime does not exceed
+    /// the duration of the managed object on the C++ side and that no
+    /// other code modifies the map during iteration.
+    pub unsafe fn iter(&self) -> UnorderedMapIter<'_, K, V> {
+        UnorderedMapIter {
+            inner: unsafe { self.list.iter() },
+        }
+    }
+
+    /// Finds the entry with the given `hash` for which `eq` returns
+    /// `true`.
+    ///
+    /// `hash` must be computed like the map's hasher does it, e.g. with
+    /// [`hash_bytes`] for maps using `std::hash`.
+    ///
+    /// # Safety
+    ///
+    /// See [`UnorderedMap::iter`].
+    pub unsafe fn find_by_hash<F>(&self, hash: u64, mut eq: F) -> Option<(&K, &V)>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let bucket = hash as usize & self.mask;
+        let buckets = unsafe { self.buckets.as_slice() };
+        let (first, last) = match buckets.get(2 * bucket..2 * bucket + 2) {
+            Some(&[first, last]) => (first, last),
+            _ => return None,
+        };
+
+        // Empty buckets point to the end of the list.
+        let head = self.list.head();
+        let mut node = first;
+        for _ in 0..self.len() {
+            if node.is_null() || node == head {
+                break;
+            }
+
+            let current = unsafe { &*node };
+            if eq(&current.value.key) {
+                return Some((&current.value.key, &current.value.value));
+            }
+            if node == last {
+                break;
+            }
+            node = current.next;
+        }
+
+        None
+    }
+}
+
+/// An iterator over the entries of an [`UnorderedMap`].
+///
+/// This is created by [`UnorderedMap::iter`].
+pub struct UnorderedMapIter<'a, K, V> {
+    inner: ListIter<'a, Pair<K, V>>,
+}
+
+impl<'a, K, V> Iterator for UnorderedMapIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|pair| (&pair.key, &pair.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+// Make sure our view matches `_Hash` with its list, bucket vector,
+// mask and maximum index.
+assert_eq_size!(UnorderedMap<u8, u8>, [u8; 0x40]);
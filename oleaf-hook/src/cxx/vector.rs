@@ -1,4 +1,13 @@
-use std::{mem, slice};
+use std::{
+    alloc::Layout,
+    fmt,
+    mem::{self, ManuallyDrop},
+    ops::{Deref, DerefMut, Index, IndexMut},
+    ptr,
+    slice::{self, SliceIndex},
+};
+
+use super::alloc::{Allocator, GameHeap, Global};
 
 /// An ABI-compatible `std::vector` that is borrowed from the C++ side.
 ///
@@ -18,6 +27,18 @@ pub struct Vector<T> {
 }
 
 impl<T> Vector<T> {
+    // Creates a vector over storage that is managed by Rust code.
+    //
+    // SAFETY: `ptr` must point to `capacity` elements of which the first
+    // `len` are initialized and it must outlive the resulting vector.
+    pub(crate) unsafe fn from_raw_parts(ptr: *mut T, len: usize, capacity: usize) -> Self {
+        Self {
+            head: ptr,
+            tail: unsafe { ptr.add(len) },
+            end: unsafe { ptr.add(capacity) },
+        }
+    }
+
     /// Gets the length of the vector measured by the elements it holds.
     ///
     /// This is done by calculating the offset between the head and tail
@@ -94,6 +115,25 @@ impl<T> Vector<T> {
         }
     }
 
+    /// Gets the underlying vector storage as a mutable Rust slice.
+    ///
+    /// # Panics
+    ///
+    /// This function panics under the same conditions as [`Vector::len`].
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the inferred lifetime does not exceed
+    /// the duration of the managed object on the C++ side and that no
+    /// other code accesses the elements concurrently.
+    pub unsafe fn as_mut_slice(&mut self) -> &mut [T] {
+        if self.head.is_null() {
+            &mut []
+        } else {
+            unsafe { slice::from_raw_parts_mut(self.head, self.len()) }
+        }
+    }
+
     /// Gets a raw pointer to the start of the insertion region.
     ///
     /// The caller must ensure that the pointer does not outlive the vector
@@ -106,3 +146,245 @@ impl<T> Vector<T> {
         self.head
     }
 }
+
+/// An ABI-compatible `std::vector` that is owned by the Rust side.
+///
+/// The storage is obtained from an [`Allocator`], which must match the
+/// allocator of the C++ code the vector is shared with if that code is
+/// supposed to grow or free it. With the default [`Global`] allocator,
+/// C++ code may read and modify elements, but never reallocate the
+/// storage. Vectors that C++ code takes ownership of should use the
+/// [`GameHeap`].
+///
+/// A pointer to the embedded [`Vector`] can be passed to C++ functions
+/// taking a `std::vector<T>*`. When C++ code takes over the vector for
+/// good, e.g. when moving it into an object it manages, the raw
+/// [`Vector`] of a vector on the [`GameHeap`] can be obtained through
+/// [`VectorBuf::into_raw`].
+///
+/// # Interpretation
+///
+/// This type should **never** be used to interpret C++-managed
+/// `std::vector` objects. If a Rust handle on a purely C++-managed
+/// vector is desired, use the [`Vector`] type instead.
+#[repr(C)]
+pub struct VectorBuf<T, A: Allocator = Global> {
+    raw: Vector<T>,
+    alloc: A,
+}
+
+impl<T> VectorBuf<T> {
+    /// Creates a new, empty vector without allocating.
+    pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+}
+
+impl<T, A: Allocator> VectorBuf<T, A> {
+    /// Creates a new, empty vector that allocates from `alloc`.
+    ///
+    /// Like in MSVC, an empty vector holds only null pointers.
+    pub fn new_in(alloc: A) -> Self {
+        Self {
+            raw: Vector {
+                head: ptr::null_mut(),
+                tail: ptr::null_mut(),
+                end: ptr::null_mut(),
+            },
+            alloc,
+        }
+    }
+
+    /// Creates a vector holding the elements of `vec` in storage that is
+    /// allocated from `alloc`.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `T` is a Zero-Sized Type ("ZST"), which cannot be
+    /// represented by a `std::vector`.
+    pub fn from_vec_in(mut vec: Vec<T>, alloc: A) -> Self {
+        assert!(
+            mem::size_of::<T>() != 0,
+            "Zero-sized elements are unsupported"
+        );
+
+        let mut this = Self::new_in(alloc);
+        let len = vec.len();
+        if len == 0 {
+            return this;
+        }
+
+        let layout = Layout::array::<T>(len).expect("capacity overflow");
+        let head = this.alloc.allocate(layout) as *mut T;
+        if head.is_null() {
+            std::alloc::handle_alloc_error(layout);
+        }
+
+        // SAFETY: The elements are moved into the new storage and `vec`
+        // forgets about them before being dropped.
+        unsafe {
+            ptr::copy_nonoverlapping(vec.as_ptr(), head, len);
+            vec.set_len(0);
+            this.raw = Vector::from_raw_parts(head, len, len);
+        }
+        this
+    }
+
+    /// Gets a reference to the allocator backing the vector.
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    /// Gets a reference to the raw vector for sharing it with C++ code.
+    pub fn as_vector(&self) -> &Vector<T> {
+        &self.raw
+    }
+
+    /// Gets a raw pointer to the vector for passing it to C++ code.
+    ///
+    /// C++ code may only reallocate or free the storage of the vector
+    /// if it uses the same allocator as `A`.
+    pub fn as_mut_ptr(&mut self) -> *mut Vector<T> {
+        &mut self.raw
+    }
+
+    /// Moves the elements of the vector into a new [`Vec`] and frees
+    /// the storage of the vector.
+    pub fn into_vec(self) -> Vec<T> {
+        let mut this = ManuallyDrop::new(self);
+        let len = this.len();
+
+        let mut vec = Vec::with_capacity(len);
+        // SAFETY: The elements are moved into `vec` and the storage is
+        // freed without dropping them again.
+        unsafe {
+            ptr::copy_nonoverlapping(this.raw.head, vec.as_mut_ptr(), len);
+            vec.set_len(len);
+            this.raw.tail = this.raw.head;
+            this.free();
+            ptr::drop_in_place(&mut this.alloc);
+        }
+        vec
+    }
+
+    // Drops the elements and frees the storage of the vector.
+    //
+    // SAFETY: The vector must not be used again afterwards.
+    unsafe fn free(&mut self) {
+        let head = self.raw.head;
+        if head.is_null() {
+            return;
+        }
+
+        // C++ code may have reallocated the vector, so the capacity is
+        // re-read here instead of being remembered.
+        let capacity = self.raw.capacity();
+        unsafe {
+            ptr::drop_in_place(self.raw.as_mut_slice());
+            let layout = Layout::array::<T>(capacity).expect("capacity overflow");
+            self.alloc.deallocate(head as *mut u8, layout);
+        }
+    }
+}
+
+impl<T> VectorBuf<T, GameHeap> {
+    /// Consumes the vector and returns the raw [`Vector`] without
+    /// freeing its storage.
+    ///
+    /// This is used to transfer ownership of the vector to C++ code,
+    /// which will then be responsible for dropping the elements and
+    /// freeing the storage. Only storage on the [`GameHeap`] may be
+    /// freed by the client, so other allocators are not supported.
+    pub fn into_raw(self) -> Vector<T> {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never used again and `GameHeap` owns no
+        // resources that would need to be dropped.
+        unsafe { ptr::read(&this.raw) }
+    }
+}
+
+impl<T> Default for VectorBuf<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Vec<T>> for VectorBuf<T> {
+    fn from(vec: Vec<T>) -> Self {
+        Self::from_vec_in(vec, Global)
+    }
+}
+
+impl<T, A: Allocator> Drop for VectorBuf<T, A> {
+    fn drop(&mut self) {
+        // SAFETY: The vector is being dropped.
+        unsafe { self.free() }
+    }
+}
+
+// The storage is owned by us, so the borrowed views are sound for as
+// long as the vector lives.
+impl<T, A: Allocator> Deref for VectorBuf<T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { self.raw.as_slice() }
+    }
+}
+
+impl<T, A: Allocator> DerefMut for VectorBuf<T, A> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { self.raw.as_mut_slice() }
+    }
+}
+
+impl<T, I: SliceIndex<[T]>, A: Allocator> Index<I> for VectorBuf<T, A> {
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        Index::index(&**self, index)
+    }
+}
+
+impl<T, I: SliceIndex<[T]>, A: Allocator> IndexMut<I> for VectorBuf<T, A> {
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        IndexMut::index_mut(&mut **self, index)
+    }
+}
+
+impl<T, A: Allocator> IntoIterator for VectorBuf<T, A> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_vec().into_iter()
+    }
+}
+
+impl<'a, T, A: Allocator> IntoIterator for &'a VectorBuf<T, A> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, A: Allocator> IntoIterator for &'a mut VectorBuf<T, A> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T: fmt::Debug, A: Allocator> fmt::Debug for VectorBuf<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// SAFETY: The vector owns its elements just like `Vec` does.
+unsafe impl<T: Send, A: Allocator + Send> Send for VectorBuf<T, A> {}
+unsafe impl<T: Sync, A: Allocator + Sync> Sync for VectorBuf<T, A> {}
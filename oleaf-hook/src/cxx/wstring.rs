@@ -1,57 +1,29 @@
 use std::{
     char,
-    ffi::NulError,
+    ffi::{CString, NulError},
+    fmt,
     os::raw::{c_size_t, c_ushort},
     ptr, slice,
 };
 
+use super::alloc::{self, Allocator, Global};
+
 #[allow(non_camel_case_types)]
 type c_wchar_t = c_ushort;
 
 #[repr(C)]
 union Impl {
-    // Invariant 1: One byte must always be reserved for trailing null.
-    // Invariant 2: No `\0` in the middle of the string supported.
+    // Invariant: One code unit must always be reserved for trailing null.
     buf: [c_wchar_t; Self::SSO_LEN],
     ptr: *mut c_wchar_t,
 }
 
-// We require this type to be exactly 32 bytes in size.
-assert_eq_size!(Impl, [u8; 32]);
+// We require this type to be exactly 16 bytes in size, like for
+// `std::string`. The buffer thus only holds 8 `wchar_t`s.
+assert_eq_size!(Impl, [u8; 16]);
 
 impl Impl {
-    const SSO_LEN: usize = 0x10;
-
-    // SAFETY: The C++ side MUST NOT modify the resulting string and `len` must be correct.
-    pub unsafe fn from_rust<S: Into<Vec<c_wchar_t>>>(value: S) -> Result<Self, NulError> {
-        let value: Vec<u16> = value.into();
-        let len = value.len();
-
-        if len < Self::SSO_LEN {
-            // We have enough capacity to perform short string
-            // optimization while preserving the null terminator.
-            unsafe {
-                let mut buf = [0; Self::SSO_LEN];
-                ptr::copy_nonoverlapping(value.as_ptr(), buf.as_mut_ptr(), len);
-
-                Ok(Self { buf })
-            }
-        } else {
-            Ok(Self {
-                ptr: Box::<[c_wchar_t]>::into_raw(value.into_boxed_slice()) as *mut c_wchar_t,
-            })
-        }
-    }
-
-    // SAFETY: This function may only be called when  `Impl::from_rust`
-    // was used to obtain this object and `len` is an accurate depiction
-    // of `wchar_t`-sized quantities in the string.
-    pub unsafe fn free_rust(&mut self, len: usize) {
-        unsafe {
-            let data = slice::from_raw_parts_mut(self.ptr, len);
-            let _ = Box::from_raw(data as *mut [c_wchar_t]);
-        }
-    }
+    const SSO_LEN: usize = 0x8;
 }
 
 /// An ABI-compatible `std::wstring` that is owned by the Rust side.
@@ -59,8 +31,11 @@ impl Impl {
 /// This is a null-terminated string that can be created from the Rust
 /// side and shared with C++ code.
 ///
-/// Instances of this string are guaranteed to contain no interior
-/// null bytes and guarantee that their raw data is null-terminated.
+/// The raw data of instances of this string is guaranteed to be
+/// null-terminated. [`WString::new`] rejects interior null characters,
+/// but strings made from raw UTF-16 code units, such as the `WStr`
+/// fields of a [`RecordBuilder`](crate::dml::RecordBuilder), may hold
+/// them.
 ///
 /// # Immutability
 ///
@@ -81,6 +56,9 @@ impl Impl {
 ///
 /// If a Rust handle on a purely C++-managed string is desired, use
 /// the [`Str`] type instead.
+///
+/// Strings that are supposed to be owned by C++ code can be created
+/// with [`WStr::new_in`] on the [`GameHeap`](alloc::GameHeap) instead.
 #[repr(C)]
 pub struct WString {
     ipl: Impl,
@@ -99,14 +77,28 @@ impl WString {
     /// It is within the caller's responsibility that the resulting string
     /// **remains unmodified** when shared with the C++ side.
     pub unsafe fn new<S: ToString>(data: S) -> Result<Self, NulError> {
-        let data: Vec<_> = data.to_string().encode_utf16().collect();
-        let size = data.len();
+        let data = data.to_string();
+        if data.contains('\0') {
+            return Err(CString::new(data).unwrap_err());
+        }
+
+        let units: Vec<_> = data.encode_utf16().collect();
+        Ok(unsafe { Self::from_units(&units) })
+    }
+
+    // SAFETY: Same as `WString::new`.
+    pub(crate) unsafe fn from_units(data: &[c_wchar_t]) -> Self {
+        let WStr {
+            ipl,
+            size,
+            capacity,
+        } = WStr::from_units_in(data, &Global);
 
-        Ok(Self {
-            ipl: unsafe { Impl::from_rust(data)? },
+        Self {
+            ipl,
             size,
-            capacity: size, // We don't care about allocating extra space
-        })
+            capacity,
+        }
     }
 
     /// Decodes the bytes stored in a [`CStr`] as UTF-16 and returns the resulting
@@ -116,7 +108,7 @@ impl WString {
     /// in the resulting string.
     pub fn decode_utf16(&self) -> String {
         unsafe {
-            if self.size < Impl::SSO_LEN {
+            if self.capacity < Impl::SSO_LEN {
                 decode_escaped_utf16(&self.ipl.buf[..self.size])
             } else {
                 let utf16 = slice::from_raw_parts(self.ipl.ptr, self.size);
@@ -133,12 +125,13 @@ impl Drop for WString {
     #[inline]
     fn drop(&mut self) {
         unsafe {
-            if self.size < Impl::SSO_LEN {
+            if self.capacity < Impl::SSO_LEN {
                 *self.ipl.buf.get_unchecked_mut(0) = 0;
             } else {
                 ptr::write(self.ipl.ptr, 0);
-                // SAFETY: The string was allocated by Rust code without SSO.
-                self.ipl.free_rust(self.size)
+                // SAFETY: Heap storage of strings created in Rust always
+                // stems from the global allocator.
+                alloc::deallocate_str(&Global, self.ipl.ptr, self.capacity);
             }
         }
     }
@@ -177,14 +170,142 @@ impl WStr {
     /// is actually valid UTF-16 under the hood. This may not actually be valid
     /// string data.
     pub unsafe fn decode_utf16(&self) -> String {
+        decode_escaped_utf16(self.as_utf16())
+    }
+
+    /// Gets the length of the string in UTF-16 code units, excluding the
+    /// null terminator.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Checks if the string is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the number of UTF-16 code units the string can hold without
+    /// reallocating its storage, excluding the null terminator.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Gets the raw UTF-16 code units of the string, including any
+    /// interior null characters.
+    ///
+    /// No validation is done to ensure this is actually valid UTF-16.
+    pub fn as_utf16(&self) -> &[c_wchar_t] {
+        // Reading the string is sound as long as the handle is, see `WStr`.
         unsafe {
-            if self.size < Impl::SSO_LEN {
-                decode_escaped_utf16(&self.ipl.buf[..self.size])
+            if self.capacity < Impl::SSO_LEN {
+                &self.ipl.buf[..self.size]
             } else {
-                let utf16 = slice::from_raw_parts(self.ipl.ptr, self.size);
-                decode_escaped_utf16(utf16)
+                slice::from_raw_parts(self.ipl.ptr, self.size)
+            }
+        }
+    }
+
+    /// Creates a new string holding the UTF-16 encoding of `data`, with
+    /// storage allocated from `alloc`.
+    ///
+    /// Rust code never frees the resulting string. It is meant to be
+    /// moved into an object owned by C++ code, which will free it through
+    /// its own allocator. For this to be sound, `alloc` should be the
+    /// [`GameHeap`](alloc::GameHeap).
+    pub fn new_in<A: Allocator>(data: &str, alloc: &A) -> Self {
+        let units: Vec<c_wchar_t> = data.encode_utf16().collect();
+        Self::from_units_in(&units, alloc)
+    }
+
+    // Creates a new string holding the UTF-16 code units `data`, like
+    // `WStr::new_in`.
+    pub(crate) fn from_units_in<A: Allocator>(data: &[c_wchar_t], alloc: &A) -> Self {
+        let mut this = Self {
+            ipl: Impl {
+                buf: [0; Impl::SSO_LEN],
+            },
+            size: 0,
+            capacity: Impl::SSO_LEN - 1,
+        };
+        // SAFETY: The string is empty and thus has no heap storage yet.
+        unsafe { this.assign_units(data, alloc) };
+        this
+    }
+
+    /// Replaces the contents of the string with the UTF-16 encoding
+    /// of `data`.
+    ///
+    /// The current storage is reused when `data` fits into it. Otherwise
+    /// new storage is obtained from `alloc` and the previous allocation,
+    /// if any, is returned to it.
+    ///
+    /// # Safety
+    ///
+    /// The string must be a live object whose heap storage, if any, was
+    /// allocated by `alloc`. No other code may access the string while
+    /// it is being modified.
+    pub unsafe fn assign<A: Allocator>(&mut self, data: &str, alloc: &A) {
+        let units: Vec<c_wchar_t> = data.encode_utf16().collect();
+        unsafe { self.assign_units(&units, alloc) }
+    }
+
+    // Replaces the contents of the string with the UTF-16 code units
+    // `units`, like `WStr::assign`.
+    //
+    // SAFETY: Same as `WStr::assign`.
+    pub(crate) unsafe fn assign_units<A: Allocator>(&mut self, units: &[c_wchar_t], alloc: &A) {
+        let len = units.len();
+        let sso = self.capacity < Impl::SSO_LEN;
+
+        if sso && len < Impl::SSO_LEN {
+            self.capacity = Impl::SSO_LEN - 1;
+        } else if len > self.capacity {
+            let capacity = alloc::grow_str_capacity(len, self.capacity, Impl::SSO_LEN - 1);
+            let ptr = alloc::allocate_str(alloc, capacity);
+            if !sso {
+                unsafe { alloc::deallocate_str(alloc, self.ipl.ptr, self.capacity) };
             }
+
+            self.ipl.ptr = ptr;
+            self.capacity = capacity;
         }
+
+        unsafe {
+            let buf = if self.capacity < Impl::SSO_LEN {
+                self.ipl.buf.as_mut_ptr()
+            } else {
+                self.ipl.ptr
+            };
+            ptr::copy_nonoverlapping(units.as_ptr(), buf, len);
+            *buf.add(len) = 0;
+        }
+        self.size = len;
+    }
+}
+
+/// Compares the UTF-16 code units without allocating.
+impl PartialEq<str> for WStr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_utf16().iter().copied().eq(other.encode_utf16())
+    }
+}
+
+impl PartialEq<&str> for WStr {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl fmt::Debug for WStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&decode_escaped_utf16(self.as_utf16()), f)
+    }
+}
+
+impl fmt::Display for WStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&decode_escaped_utf16(self.as_utf16()), f)
     }
 }
 
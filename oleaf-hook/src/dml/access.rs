@@ -0,0 +1,283 @@
+use std::{collections::HashMap, error::Error, fmt, os::raw::*};
+
+use super::{Field, FieldValue, Record, TypeId};
+use crate::cxx;
+
+/// An error that occurred while accessing a [`Field`] by name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FieldError {
+    /// The record has no field of the given name.
+    Missing { name: String },
+    /// The field holds a value of a different type than requested.
+    TypeMismatch {
+        name: String,
+        expected: TypeId,
+        found: TypeId,
+    },
+    /// The field has a type ID that is not known to us, so its value
+    /// cannot be interpreted.
+    UnknownType { name: String, type_id: u8 },
+    /// The string field does not point to any string object.
+    NullString { name: String },
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing { name } => write!(f, "record has no field '{}'", name),
+            Self::TypeMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "field '{}' is of type {:?}, expected {:?}",
+                name, found, expected
+            ),
+            Self::UnknownType { name, type_id } => {
+                write!(f, "field '{}' has unknown type ID {}", name, type_id)
+            }
+            Self::NullString { name } => write!(f, "string field '{}' is null", name),
+        }
+    }
+}
+
+impl Error for FieldError {}
+
+/// A Rust type that can be read from the value of a [`Field`].
+///
+/// This is used by [`Record::get_as`] and the code generated by
+/// `#[derive(DmlRecord)]`.
+pub trait FromField: Sized {
+    /// The type that fields must have to be read as `Self`.
+    const TYPE_ID: TypeId;
+
+    /// Converts a field value into `Self`, returning [`None`] when it
+    /// holds a different type.
+    ///
+    /// # Safety
+    ///
+    /// String values must point to valid string objects.
+    unsafe fn from_value(value: FieldValue<'_>) -> Option<Self>;
+}
+
+/// A Rust type that can be read from the fields of a [`Record`].
+///
+/// This is implemented by `#[derive(DmlRecord)]` and used for the
+/// `#[record]` arguments of event handlers.
+pub trait FromRecord: Sized {
+    /// Reads `Self` from the fields of `record`.
+    ///
+    /// # Safety
+    ///
+    /// The record must be a live object managed by C++ code, or one that
+    /// was built on the Rust side with valid field storage. C++ code must
+    /// not modify it during the conversion.
+    unsafe fn from_record(record: &Record) -> Result<Self, FieldError>;
+}
+
+macro_rules! impl_from_field {
+    ($($ty:ty => $variant:ident $(| $other:ident)*),* $(,)?) => {
+        $(
+            impl FromField for $ty {
+                const TYPE_ID: TypeId = TypeId::$variant;
+
+                unsafe fn from_value(value: FieldValue<'_>) -> Option<Self> {
+                    match value {
+                        FieldValue::$variant(v) $(| FieldValue::$other(v))* => Some(v),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_field! {
+    c_char => Byt,
+    c_uchar => UByt,
+    c_ushort => UShrt,
+    c_int => Int,
+    c_uint => UInt,
+    c_ulonglong => Gid | ULong,
+    c_float => Flt,
+    c_double => Dbl,
+    c_longlong => Long,
+}
+
+/// Reads `Str` as well as `WStr` fields, replacing invalid data with
+/// `U+FFFD REPLACEMENT CHARACTER`.
+impl FromField for String {
+    const TYPE_ID: TypeId = TypeId::Str;
+
+    unsafe fn from_value(value: FieldValue<'_>) -> Option<Self> {
+        match value {
+            FieldValue::Str(v) => Some(v.to_string_lossy().into_owned()),
+            FieldValue::WStr(v) => Some(unsafe { v.decode_utf16() }),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the raw bytes of `Str` as well as `Blob` fields.
+impl FromField for Vec<u8> {
+    const TYPE_ID: TypeId = TypeId::Str;
+
+    unsafe fn from_value(value: FieldValue<'_>) -> Option<Self> {
+        match value {
+            FieldValue::Str(v) | FieldValue::Blob(v) => Some(v.as_bytes().to_vec()),
+            _ => None,
+        }
+    }
+}
+
+impl Field {
+    /// Reads the value of this field as `T`.
+    ///
+    /// # Safety
+    ///
+    /// The field must be a live object managed by C++ code, or one that
+    /// was built on the Rust side with valid string storage.
+    pub unsafe fn value_as<T: FromField>(&self) -> Result<T, FieldError> {
+        let value = unsafe { self.value() }?;
+        unsafe { T::from_value(value) }.ok_or_else(|| FieldError::TypeMismatch {
+            name: self.name.to_string_lossy().into_owned(),
+            expected: T::TYPE_ID,
+            found: self.type_id(),
+        })
+    }
+}
+
+// Generates the typed getters for a type with a `get` method.
+macro_rules! typed_getters {
+    ($($getter:ident: $variant:ident => $ty:ty),* $(,)?) => {
+        $(
+            #[doc = concat!(
+                "Gets the value of the `", stringify!($variant), "` field `name`.\n\n",
+                "# Safety\n\n",
+                "The lifetime of the result may not be representative of the real\n",
+                "lifetime of the data.\n\n",
+                "The caller is responsible for ensuring the availability of the\n",
+                "requested data."
+            )]
+            pub unsafe fn $getter(&self, name: &str) -> Result<$ty, FieldError> {
+                let field = unsafe { self.get(name) }.ok_or_else(|| FieldError::Missing {
+                    name: name.to_owned(),
+                })?;
+
+                match unsafe { field.value() }? {
+                    FieldValue::$variant(v) => Ok(v),
+                    _ => Err(FieldError::TypeMismatch {
+                        name: name.to_owned(),
+                        expected: TypeId::$variant,
+                        found: field.type_id(),
+                    }),
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_typed_getters {
+    ($($ty:ty),*) => {
+        $(
+            impl $ty {
+                typed_getters! {
+                    get_i8: Byt => c_char,
+                    get_u8: UByt => c_uchar,
+                    get_u16: UShrt => c_ushort,
+                    get_i32: Int => c_int,
+                    get_u32: UInt => c_uint,
+                    get_gid: Gid => c_ulonglong,
+                    get_f32: Flt => c_float,
+                    get_f64: Dbl => c_double,
+                    get_str: Str => &cxx::Str,
+                    get_wstr: WStr => &cxx::WStr,
+                    get_blob: Blob => &cxx::Str,
+                    get_i64: Long => c_longlong,
+                    get_u64: ULong => c_ulonglong,
+                }
+            }
+        )*
+    };
+}
+
+impl_typed_getters!(Record, FieldIndex<'_>);
+
+impl Record {
+    /// Finds the first field with the given name.
+    ///
+    /// This is a linear search. Use [`Record::index`] when looking up
+    /// many fields of a large record.
+    ///
+    /// # Safety
+    ///
+    /// The lifetime of the result may not be representative of the real
+    /// lifetime of the data.
+    ///
+    /// The caller is responsible for ensuring the availability of the
+    /// requested data.
+    pub unsafe fn get(&self, name: &str) -> Option<&Field> {
+        unsafe { self.fields() }.iter().find(|f| f.name == name)
+    }
+
+    /// Reads the value of the first field with the given name as `T`.
+    ///
+    /// # Safety
+    ///
+    /// The record must be a live object managed by C++ code, or one that
+    /// was built on the Rust side with valid field storage.
+    pub unsafe fn get_as<T: FromField>(&self, name: &str) -> Result<T, FieldError> {
+        match unsafe { self.get(name) } {
+            Some(field) => unsafe { field.value_as() },
+            None => Err(FieldError::Missing {
+                name: name.to_owned(),
+            }),
+        }
+    }
+
+    /// Builds a [`FieldIndex`] for constant-time lookup of fields by name.
+    ///
+    /// # Safety
+    ///
+    /// The lifetime of the result may not be representative of the real
+    /// lifetime of the data.
+    ///
+    /// The caller is responsible for ensuring the availability of the
+    /// requested data.
+    pub unsafe fn index(&self) -> FieldIndex<'_> {
+        let fields = unsafe { self.fields() };
+
+        let mut names = HashMap::with_capacity(fields.len());
+        for (i, field) in fields.iter().enumerate() {
+            // Keep the first field on duplicate names, just like `Record::get`.
+            names.entry(field.name.as_bytes()).or_insert(i);
+        }
+
+        FieldIndex { fields, names }
+    }
+}
+
+/// A precomputed mapping of field names to the [`Field`]s of a
+/// [`Record`].
+///
+/// Offers the same getters as [`Record`].
+pub struct FieldIndex<'a> {
+    fields: &'a [Field],
+    names: HashMap<&'a [u8], usize>,
+}
+
+impl<'a> FieldIndex<'a> {
+    /// Finds the first field with the given name.
+    ///
+    /// # Safety
+    ///
+    /// The lifetime of the result may not be representative of the real
+    /// lifetime of the data.
+    ///
+    /// The caller is responsible for ensuring the availability of the
+    /// requested data.
+    pub unsafe fn get(&self, name: &str) -> Option<&'a Field> {
+        self.names.get(name.as_bytes()).map(|&i| &self.fields[i])
+    }
+}
@@ -0,0 +1,334 @@
+use std::{io, mem::ManuallyDrop, os::raw::*, ptr};
+
+use super::{Field, FieldValue, OwnedRecord, OwnedValue, Record};
+use crate::cxx;
+
+// A field value whose string data has not been copied into C++ storage yet.
+enum PendingValue {
+    Byt(c_char),
+    UByt(c_uchar),
+    UShrt(c_ushort),
+    Int(c_int),
+    UInt(c_uint),
+    Gid(c_ulonglong),
+    Flt(c_float),
+    Dbl(c_double),
+    Str(Vec<u8>),
+    WStr(Vec<c_ushort>),
+    Blob(Vec<u8>),
+    Long(c_longlong),
+    ULong(c_ulonglong),
+}
+
+/// A builder for DML [`Record`]s in Rust-owned memory.
+///
+/// Records built this way may be handed to the client's own event
+/// handlers, e.g. through [`event::invoke`]. For this to work, the
+/// record and its fields need the vtables of their C++ counterparts,
+/// which can be copied from an existing record using
+/// [`RecordBuilder::vtables_from`] or found through RTTI with
+/// `Module::find_vtable` on Windows.
+///
+/// ```ignore
+/// let mut record = RecordBuilder::new()
+///     .vtables_from(template)
+///     .uint("QuestID", 42)
+///     .wstr("Title", "Hello")
+///     .build()?;
+/// unsafe { event::invoke("HandleQuestDialog", &mut record)? };
+/// ```
+///
+/// [`event::invoke`]: crate::event::invoke
+pub struct RecordBuilder {
+    vtable: *mut c_void,
+    field_vtable: *mut c_void,
+    fields: Vec<(Vec<u8>, PendingValue)>,
+}
+
+macro_rules! scalar_fields {
+    ($($method:ident: $variant:ident => $ty:ty;)*) => {
+        $(
+            #[doc = concat!("Appends a field of type `", stringify!($variant), "`.")]
+            pub fn $method<N: Into<Vec<u8>>>(self, name: N, value: $ty) -> Self {
+                self.push(name, PendingValue::$variant(value))
+            }
+        )*
+    };
+}
+
+impl RecordBuilder {
+    /// Creates a new builder for a record without fields.
+    ///
+    /// The record and its fields have no vtables unless set explicitly.
+    pub fn new() -> Self {
+        Self {
+            vtable: ptr::null_mut(),
+            field_vtable: ptr::null_mut(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Creates a new builder holding all fields of `record`.
+    pub fn from_owned(record: &OwnedRecord) -> Self {
+        record.fields.iter().fold(Self::new(), |builder, field| {
+            builder.value(field.name.as_str(), &field.value)
+        })
+    }
+
+    /// Sets the vtable of the record.
+    pub fn vtable(mut self, vtable: *mut c_void) -> Self {
+        self.vtable = vtable;
+        self
+    }
+
+    /// Sets the vtable shared by all fields of the record.
+    pub fn field_vtable(mut self, vtable: *mut c_void) -> Self {
+        self.field_vtable = vtable;
+        self
+    }
+
+    /// Copies the record vtable and the field vtable from `record`.
+    ///
+    /// The field vtable is only copied when `record` has any fields.
+    ///
+    /// # Safety
+    ///
+    /// `record` must be a valid record obtained from the client.
+    pub unsafe fn vtables_from(mut self, record: &Record) -> Self {
+        self.vtable = record.vtable;
+        if let Some(field) = unsafe { record.fields() }.first() {
+            self.field_vtable = field.vtable;
+        }
+        self
+    }
+
+    fn push<N: Into<Vec<u8>>>(mut self, name: N, value: PendingValue) -> Self {
+        self.fields.push((name.into(), value));
+        self
+    }
+
+    scalar_fields! {
+        byt: Byt => c_char;
+        ubyt: UByt => c_uchar;
+        ushrt: UShrt => c_ushort;
+        int: Int => c_int;
+        uint: UInt => c_uint;
+        gid: Gid => c_ulonglong;
+        flt: Flt => c_float;
+        dbl: Dbl => c_double;
+    }
+
+    /// Appends a field of type `Str` holding the raw bytes `value`,
+    /// which may include interior null bytes.
+    pub fn str<N: Into<Vec<u8>>, V: Into<Vec<u8>>>(self, name: N, value: V) -> Self {
+        self.push(name, PendingValue::Str(value.into()))
+    }
+
+    /// Appends a field of type `WStr` holding `value` encoded as UTF-16.
+    pub fn wstr<N: Into<Vec<u8>>>(self, name: N, value: &str) -> Self {
+        self.wstr_units(name, value.encode_utf16().collect())
+    }
+
+    /// Appends a field of type `WStr` holding the raw UTF-16 code units
+    /// `value`.
+    pub fn wstr_units<N: Into<Vec<u8>>>(self, name: N, value: Vec<c_ushort>) -> Self {
+        self.push(name, PendingValue::WStr(value))
+    }
+
+    /// Appends a field holding a copy of `value`.
+    pub fn value<N: Into<Vec<u8>>>(self, name: N, value: &OwnedValue) -> Self {
+        let value = match value {
+            OwnedValue::Byt(v) => PendingValue::Byt(*v),
+            OwnedValue::UByt(v) => PendingValue::UByt(*v),
+            OwnedValue::UShrt(v) => PendingValue::UShrt(*v),
+            OwnedValue::Int(v) => PendingValue::Int(*v),
+            OwnedValue::UInt(v) => PendingValue::UInt(*v),
+            OwnedValue::Gid(v) => PendingValue::Gid(*v),
+            OwnedValue::Flt(v) => PendingValue::Flt(*v),
+            OwnedValue::Dbl(v) => PendingValue::Dbl(*v),
+            OwnedValue::Str(v) => PendingValue::Str(v.clone()),
+            OwnedValue::WStr(v) => PendingValue::WStr(v.encode_utf16().collect()),
+            OwnedValue::Blob(v) => PendingValue::Blob(v.clone()),
+            OwnedValue::Long(v) => PendingValue::Long(*v),
+            OwnedValue::ULong(v) => PendingValue::ULong(*v),
+        };
+        self.push(name, value)
+    }
+
+    /// Builds the record.
+    ///
+    /// This function will error if a field name contains interior null
+    /// bytes, as the client compares names as C strings. String values
+    /// are copied byte by byte and may contain them.
+    ///
+    /// It will also error on `Blob`, `Long` and `ULong` values, whose
+    /// raw type IDs are not known yet.
+    pub fn build(self) -> io::Result<RecordBuf> {
+        let nul_error = |name: &[u8]| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "field name '{}' contains interior null bytes",
+                    String::from_utf8_lossy(name)
+                ),
+            )
+        };
+
+        // SAFETY: The strings are kept alive for as long as the record
+        // and are never modified by us.
+        let mut names = Vec::with_capacity(self.fields.len());
+        let mut strs = Vec::new();
+        let mut wstrs = Vec::new();
+        for (name, value) in &self.fields {
+            unsafe {
+                names.push(cxx::String::from_vec(name.clone()).map_err(|_| nul_error(name))?);
+                match value {
+                    PendingValue::Str(v) | PendingValue::Blob(v) => {
+                        strs.push(cxx::String::from_bytes(v))
+                    }
+                    PendingValue::WStr(v) => wstrs.push(cxx::WString::from_units(v)),
+                    _ => (),
+                }
+            }
+        }
+
+        // The string storage is complete, so we can now point into it.
+        let (mut strs_iter, mut wstrs_iter) = (strs.iter(), wstrs.iter());
+        let mut fields: Vec<_> = self
+            .fields
+            .iter()
+            .zip(&names)
+            .map(|((name_bytes, value), name)| unsafe {
+                let value = match value {
+                    PendingValue::Byt(v) => FieldValue::Byt(*v),
+                    PendingValue::UByt(v) => FieldValue::UByt(*v),
+                    PendingValue::UShrt(v) => FieldValue::UShrt(*v),
+                    PendingValue::Int(v) => FieldValue::Int(*v),
+                    PendingValue::UInt(v) => FieldValue::UInt(*v),
+                    PendingValue::Gid(v) => FieldValue::Gid(*v),
+                    PendingValue::Flt(v) => FieldValue::Flt(*v),
+                    PendingValue::Dbl(v) => FieldValue::Dbl(*v),
+                    PendingValue::Str(_) => {
+                        let s = strs_iter.next().unwrap() as *const cxx::String;
+                        FieldValue::Str(&*(s as *const cxx::Str))
+                    }
+                    PendingValue::WStr(_) => {
+                        let s = wstrs_iter.next().unwrap() as *const cxx::WString;
+                        FieldValue::WStr(&*(s as *const cxx::WStr))
+                    }
+                    PendingValue::Blob(_) => {
+                        let s = strs_iter.next().unwrap() as *const cxx::String;
+                        FieldValue::Blob(&*(s as *const cxx::Str))
+                    }
+                    PendingValue::Long(v) => FieldValue::Long(*v),
+                    PendingValue::ULong(v) => FieldValue::ULong(*v),
+                };
+
+                let mut field = Field::from_value(name.borrow(), value).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "field '{}' has a type whose raw ID is not known",
+                            String::from_utf8_lossy(name_bytes)
+                        ),
+                    )
+                })?;
+                field.vtable = self.field_vtable;
+                Ok(field)
+            })
+            .collect::<io::Result<_>>()?;
+
+        // SAFETY: `fields` is kept alive for as long as the record.
+        let record = unsafe {
+            let vector =
+                cxx::Vector::from_raw_parts(fields.as_mut_ptr(), fields.len(), fields.len());
+            Box::new(Record::from_fields(self.vtable, vector))
+        };
+
+        Ok(RecordBuf {
+            record: ManuallyDrop::new(record),
+            storage: ManuallyDrop::new(Storage {
+                _fields: fields,
+                _names: names,
+                _strs: strs,
+                _wstrs: wstrs,
+            }),
+        })
+    }
+}
+
+impl Default for RecordBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Everything borrowed by a `RecordBuf`'s record.
+struct Storage {
+    _fields: Vec<Field>,
+    _names: Vec<cxx::String>,
+    _strs: Vec<cxx::String>,
+    _wstrs: Vec<cxx::WString>,
+}
+
+/// A DML [`Record`] in Rust-owned memory, created by a [`RecordBuilder`].
+///
+/// The memory layout is fully compatible with records created by the
+/// client. The record starts out with a reference count of 1, which is
+/// held by this object.
+///
+/// C++ code may take further references to the record while it is
+/// being handled. If any of them are still held when this object is
+/// dropped, the memory is leaked instead of freed so that the client
+/// never accesses a dangling record. Use [`RecordBuf::release`] to
+/// find out whether this happened. C++ code must never release the
+/// reference held by this object.
+pub struct RecordBuf {
+    // Boxed so the pointer handed out to C++ code stays stable.
+    record: ManuallyDrop<Box<Record>>,
+    storage: ManuallyDrop<Storage>,
+}
+
+impl RecordBuf {
+    /// Gets a reference to the record.
+    pub fn record(&self) -> &Record {
+        &self.record
+    }
+
+    /// Gets a raw pointer to the record for passing it to C++ code.
+    pub fn as_mut_ptr(&mut self) -> *mut Record {
+        &mut **self.record
+    }
+
+    /// Frees the record, unless C++ code still holds references to it.
+    ///
+    /// In that case, the record is leaked together with its fields and
+    /// a pointer to it is returned.
+    #[must_use = "a leaked record should be reported"]
+    pub fn release(self) -> Option<*mut Record> {
+        let mut this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never dropped, so this only runs once.
+        unsafe { this.free() }
+    }
+
+    // SAFETY: Must not be called more than once.
+    unsafe fn free(&mut self) -> Option<*mut Record> {
+        if self.record.ref_count() != 1 {
+            return Some(&mut **self.record);
+        }
+
+        // SAFETY: Neither is accessed again after being dropped here.
+        unsafe {
+            ManuallyDrop::drop(&mut self.record);
+            ManuallyDrop::drop(&mut self.storage);
+        }
+        None
+    }
+}
+
+impl Drop for RecordBuf {
+    fn drop(&mut self) {
+        // SAFETY: This is the last use of `self`.
+        let _ = unsafe { self.free() };
+    }
+}
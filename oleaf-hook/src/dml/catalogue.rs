@@ -0,0 +1,298 @@
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    fs, io,
+    path::Path,
+};
+
+use super::{
+    wire::{self, FieldDef},
+    OwnedRecord, TypeId,
+};
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A DML message as declared in a protocol definition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    /// The number that identifies the message within its service.
+    pub number: u8,
+    /// The name of the message, e.g. `MSG_QUESTDIALOG`.
+    pub name: String,
+    /// The description of the message.
+    pub description: String,
+    /// The name of the client function handling the message.
+    pub handler: String,
+    /// The access level required to send the message.
+    pub access_level: u8,
+    /// The fields that are transferred over the network, in order.
+    pub fields: Vec<FieldDef>,
+}
+
+impl Message {
+    /// Decodes the body of this message from its wire format.
+    pub fn decode(&self, data: &[u8]) -> io::Result<OwnedRecord> {
+        wire::decode(data, &self.fields)
+    }
+
+    /// Encodes `record` as the body of this message.
+    pub fn encode(&self, record: &OwnedRecord) -> io::Result<Vec<u8>> {
+        wire::encode(record, &self.fields)
+    }
+}
+
+/// A DML service that groups related messages, as declared in one
+/// `*Messages.xml` protocol definition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Service {
+    /// The unique ID of the service.
+    pub id: u8,
+    /// The short name of the protocol, e.g. `WIZARD`.
+    pub protocol_type: String,
+    /// The version of the protocol.
+    pub version: i32,
+    /// The description of the protocol.
+    pub description: String,
+    // Sorted by message number.
+    messages: Vec<Message>,
+}
+
+// Gets the trimmed text of the child element `name` of `node`.
+fn child_text<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|c| c.has_tag_name(name))
+        .map(|c| c.text().unwrap_or("").trim())
+}
+
+// Gets the `RECORD` element of a protocol info or message element.
+fn record_node<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+) -> io::Result<roxmltree::Node<'a, 'input>> {
+    node.children()
+        .find(|c| c.has_tag_name("RECORD"))
+        .ok_or_else(|| invalid_data(format!("'{}' has no RECORD", node.tag_name().name())))
+}
+
+fn parse_number<T: std::str::FromStr>(value: Option<&str>, what: &str) -> io::Result<T> {
+    value
+        .ok_or_else(|| invalid_data(format!("missing {}", what)))?
+        .parse()
+        .map_err(|_| invalid_data(format!("malformed {}", what)))
+}
+
+fn parse_message(node: roxmltree::Node<'_, '_>) -> io::Result<(Message, Option<u8>)> {
+    let name = node.tag_name().name();
+    let record = record_node(node)?;
+
+    let mut fields = Vec::new();
+    for field in record.children().filter(|c| c.is_element()) {
+        let field_name = field.tag_name().name();
+        // Metadata about the message is not sent over the network.
+        if field_name.starts_with('_') || field.attribute("NOXFER") == Some("TRUE") {
+            continue;
+        }
+
+        let ty = field
+            .attribute("TYPE")
+            .ok_or_else(|| invalid_data(format!("{}.{} has no TYPE", name, field_name)))?;
+        let type_id = TypeId::from_name(ty).ok_or_else(|| {
+            invalid_data(format!("{}.{} has unknown TYPE {}", name, field_name, ty))
+        })?;
+        fields.push(FieldDef::new(field_name, type_id));
+    }
+
+    let order = match child_text(record, "_MsgOrder") {
+        Some(order) => Some(parse_number(Some(order), "_MsgOrder")?),
+        None => None,
+    };
+    let message = Message {
+        number: 0,
+        name: child_text(record, "_MsgName").unwrap_or(name).to_owned(),
+        description: child_text(record, "_MsgDescription")
+            .unwrap_or("")
+            .to_owned(),
+        handler: child_text(record, "_MsgHandler").unwrap_or("").to_owned(),
+        access_level: match child_text(record, "_MsgAccessLvl") {
+            Some(lvl) => parse_number(Some(lvl), "_MsgAccessLvl")?,
+            None => 0,
+        },
+        fields,
+    };
+
+    Ok((message, order))
+}
+
+impl Service {
+    /// Parses a service from the contents of a protocol definition.
+    ///
+    /// Messages are numbered by their `_MsgOrder` when all of them
+    /// declare one. When none do, they are numbered in the alphabetical
+    /// order of their names, starting at 1, just like the client does.
+    /// A definition in which only some messages declare `_MsgOrder` is
+    /// rejected.
+    pub fn parse(xml: &str) -> io::Result<Self> {
+        let doc = roxmltree::Document::parse(xml).map_err(|e| invalid_data(e.to_string()))?;
+        let root = doc.root_element();
+
+        let info = root
+            .children()
+            .find(|c| c.has_tag_name("_ProtocolInfo"))
+            .ok_or_else(|| invalid_data("missing _ProtocolInfo".to_owned()))?;
+        let info = record_node(info)?;
+
+        let mut messages = root
+            .children()
+            .filter(|c| c.is_element() && !c.has_tag_name("_ProtocolInfo"))
+            .map(parse_message)
+            .collect::<io::Result<Vec<_>>>()?;
+
+        if let Some((with, without)) = messages
+            .iter()
+            .find(|(_, order)| order.is_some())
+            .zip(messages.iter().find(|(_, order)| order.is_none()))
+        {
+            return Err(invalid_data(format!(
+                "{} declares a _MsgOrder, but {} does not",
+                with.0.name, without.0.name
+            )));
+        }
+
+        if messages.iter().all(|(_, order)| order.is_some()) {
+            for (message, order) in &mut messages {
+                message.number = order.unwrap();
+            }
+        } else {
+            messages.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+            for (i, (message, _)) in messages.iter_mut().enumerate() {
+                message.number = u8::try_from(i + 1)
+                    .map_err(|_| invalid_data("too many messages".to_owned()))?;
+            }
+        }
+
+        let mut messages: Vec<_> = messages.into_iter().map(|(m, _)| m).collect();
+        messages.sort_by_key(|m| m.number);
+        if let Some(w) = messages.windows(2).find(|w| w[0].number == w[1].number) {
+            return Err(invalid_data(format!(
+                "{} and {} share message number {}",
+                w[0].name, w[1].name, w[0].number
+            )));
+        }
+
+        Ok(Self {
+            id: parse_number(child_text(info, "ServiceID"), "ServiceID")?,
+            protocol_type: child_text(info, "ProtocolType").unwrap_or("").to_owned(),
+            version: match child_text(info, "ProtocolVersion") {
+                Some(version) => parse_number(Some(version), "ProtocolVersion")?,
+                None => 0,
+            },
+            description: child_text(info, "ProtocolDescription")
+                .unwrap_or("")
+                .to_owned(),
+            messages,
+        })
+    }
+
+    /// Gets all messages of the service ordered by their number.
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// Gets the message with the given number.
+    pub fn message(&self, number: u8) -> Option<&Message> {
+        self.messages
+            .binary_search_by_key(&number, |m| m.number)
+            .ok()
+            .map(|i| &self.messages[i])
+    }
+
+    /// Gets the message with the given name.
+    pub fn message_by_name(&self, name: &str) -> Option<&Message> {
+        self.messages.iter().find(|m| m.name == name)
+    }
+}
+
+/// A collection of DML [`Service`]s indexed by their IDs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Catalogue {
+    services: BTreeMap<u8, Service>,
+}
+
+impl Catalogue {
+    /// Creates a new, empty catalogue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads all `*Messages.xml` protocol definitions in the directory
+    /// `path` into a new catalogue.
+    pub fn load_dir<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut catalogue = Self::new();
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            let is_protocol = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map_or(false, |name| name.ends_with("Messages.xml"));
+
+            if is_protocol {
+                catalogue.load_file(path)?;
+            }
+        }
+
+        Ok(catalogue)
+    }
+
+    /// Loads the protocol definition at `path` into the catalogue.
+    ///
+    /// This function will error if the catalogue already holds a
+    /// service with the same ID.
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<&Service> {
+        let path = path.as_ref();
+        let service = Service::parse(&fs::read_to_string(path)?)
+            .map_err(|e| invalid_data(format!("{}: {}", path.display(), e)))?;
+
+        self.insert(service)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+    }
+
+    /// Inserts `service` into the catalogue.
+    ///
+    /// This function will error if the catalogue already holds a
+    /// service with the same ID.
+    pub fn insert(&mut self, service: Service) -> io::Result<&Service> {
+        match self.services.entry(service.id) {
+            Entry::Occupied(entry) => Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!(
+                    "service {} ({}) conflicts with {}",
+                    service.id,
+                    service.protocol_type,
+                    entry.get().protocol_type
+                ),
+            )),
+            Entry::Vacant(entry) => Ok(entry.insert(service)),
+        }
+    }
+
+    /// Gets all services ordered by their IDs.
+    pub fn services(&self) -> impl Iterator<Item = &Service> {
+        self.services.values()
+    }
+
+    /// Gets the service with the given ID.
+    pub fn service(&self, id: u8) -> Option<&Service> {
+        self.services.get(&id)
+    }
+
+    /// Gets the message `number` of the service `service`.
+    pub fn message(&self, service: u8, number: u8) -> Option<&Message> {
+        self.service(service)?.message(number)
+    }
+
+    /// Finds a message by its name in all services.
+    pub fn find_message(&self, name: &str) -> Option<(&Service, &Message)> {
+        self.services()
+            .find_map(|s| s.message_by_name(name).map(|m| (s, m)))
+    }
+}
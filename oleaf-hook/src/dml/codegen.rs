@@ -0,0 +1,267 @@
+//! Generation of typed Rust structs for the messages of a DML [`Service`].
+//!
+//! This is meant to be used from build scripts:
+//!
+//! ```ignore
+//! // build.rs
+//! let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("quest.rs");
+//! oleaf_hook::dml::codegen::generate_file("QuestMessages.xml", out).unwrap();
+//! println!("cargo:rerun-if-changed=QuestMessages.xml");
+//!
+//! // lib.rs
+//! pub mod quest {
+//!     include!(concat!(env!("OUT_DIR"), "/quest.rs"));
+//! }
+//! ```
+//!
+//! Every message becomes a struct named after its handler with one
+//! public field per transferred DML field. The structs derive
+//! [`DmlRecord`] for conversion from in-memory [`Record`]s and get
+//! `read_from`/`write_to` as well as `decode`/`encode` methods for the
+//! [`wire`] format.
+//!
+//! Code using the generated structs must depend on `oleaf-hook`.
+//!
+//! [`DmlRecord`]: crate::DmlRecord
+//! [`Record`]: super::Record
+//! [`wire`]: super::wire
+
+use std::{fmt::Write as _, fs, io, path::Path};
+
+use super::{Message, Service, TypeId};
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "try",
+    "type", "unsafe", "use", "where", "while", "yield",
+];
+
+// Gets the Rust type that represents values of type `type_id`.
+fn rust_type(type_id: TypeId) -> io::Result<&'static str> {
+    Ok(match type_id {
+        TypeId::Gid => "u64",
+        TypeId::Int => "i32",
+        TypeId::UInt => "u32",
+        TypeId::Flt => "f32",
+        TypeId::Byt => "i8",
+        TypeId::UByt => "u8",
+        TypeId::UShrt => "u16",
+        TypeId::Dbl => "f64",
+        TypeId::Str | TypeId::WStr => "::std::string::String",
+        TypeId::Blob => "::std::vec::Vec<u8>",
+        TypeId::Long => "i64",
+        TypeId::ULong => "u64",
+        TypeId::Unknown(id) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported type {}", id),
+            ))
+        }
+    })
+}
+
+// Converts a DML name such as `QuestID` into a snake case identifier.
+fn field_ident(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+
+    let mut ident = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_ascii_alphanumeric() {
+            ident.push('_');
+            continue;
+        }
+
+        if c.is_ascii_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).map_or(false, char::is_ascii_lowercase);
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_lower)
+            {
+                ident.push('_');
+            }
+        }
+        ident.push(c.to_ascii_lowercase());
+    }
+
+    // `_` on its own is not a valid field name.
+    if ident.chars().all(|c| c == '_') || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+// Converts a message into a camel case struct name, preferring the
+// mixed-case handler name over the upper-case message name.
+fn struct_ident(message: &Message) -> String {
+    let name = if message.handler.is_empty() {
+        &message.name
+    } else {
+        &message.handler
+    };
+    let name = name.strip_prefix("MSG_").unwrap_or(name);
+
+    let mut ident = String::with_capacity(name.len());
+    for part in name.split(|c: char| !c.is_ascii_alphanumeric()) {
+        // Parts in all caps carry no word boundaries we could preserve.
+        let shouting = !part.chars().any(|c| c.is_ascii_lowercase());
+
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            ident.push(first.to_ascii_uppercase());
+            if shouting {
+                ident.extend(chars.map(|c| c.to_ascii_lowercase()));
+            } else {
+                ident.extend(chars);
+            }
+        }
+    }
+
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, 'M');
+    }
+    ident
+}
+
+fn generate_message(
+    out: &mut String,
+    service: &Service,
+    message: &Message,
+    ident: &str,
+) -> io::Result<()> {
+    let mut fields = Vec::with_capacity(message.fields.len());
+    for def in &message.fields {
+        let mut field = field_ident(&def.name);
+        while fields.iter().any(|(f, _, _)| *f == field) {
+            field.push('_');
+        }
+        fields.push((field, def, rust_type(def.type_id)?));
+    }
+
+    // Writing to a `String` never fails.
+    if !message.description.is_empty() {
+        writeln!(out, "#[doc = {:?}]", message.description).unwrap();
+    }
+    writeln!(
+        out,
+        "#[derive(Clone, Debug, PartialEq, ::oleaf_hook::DmlRecord)]"
+    )
+    .unwrap();
+    writeln!(out, "pub struct {} {{", ident).unwrap();
+    for (field, def, ty) in &fields {
+        writeln!(out, "    #[dml(rename = {:?})]", def.name).unwrap();
+        writeln!(out, "    pub {}: {},", field, ty).unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+
+    writeln!(out, "impl {} {{", ident).unwrap();
+    writeln!(out, "    pub const SERVICE_ID: u8 = {};", service.id).unwrap();
+    writeln!(out, "    pub const NUMBER: u8 = {};", message.number).unwrap();
+    writeln!(
+        out,
+        "    pub const NAME: &'static str = {:?};\n",
+        message.name
+    )
+    .unwrap();
+
+    // Messages without fields never touch the reader or writer.
+    let (r, w) = if fields.is_empty() {
+        ("_r", "_w")
+    } else {
+        ("r", "w")
+    };
+
+    writeln!(
+        out,
+        "    pub fn read_from<R: ::std::io::Read>({}: &mut R) -> ::std::io::Result<Self> {{",
+        r
+    )
+    .unwrap();
+    writeln!(out, "        ::std::io::Result::Ok(Self {{").unwrap();
+    for (field, def, _) in &fields {
+        writeln!(
+            out,
+            "            {}: ::oleaf_hook::dml::wire::WireType::read(r, {:?}, ::oleaf_hook::dml::TypeId::{:?})?,",
+            field, def.name, def.type_id
+        )
+        .unwrap();
+    }
+    writeln!(out, "        }})\n    }}\n").unwrap();
+
+    writeln!(
+        out,
+        "    pub fn write_to<W: ::std::io::Write>(&self, {}: &mut W) -> ::std::io::Result<()> {{",
+        w
+    )
+    .unwrap();
+    for (field, def, _) in &fields {
+        writeln!(
+            out,
+            "        ::oleaf_hook::dml::wire::WireType::write(&self.{}, w, {:?}, ::oleaf_hook::dml::TypeId::{:?})?;",
+            field, def.name, def.type_id
+        )
+        .unwrap();
+    }
+    writeln!(out, "        ::std::io::Result::Ok(())\n    }}\n").unwrap();
+
+    out.push_str(
+        "    pub fn decode(mut data: &[u8]) -> ::std::io::Result<Self> {
+        let message = Self::read_from(&mut data)?;
+        ::oleaf_hook::dml::wire::expect_end(data)?;
+        ::std::io::Result::Ok(message)
+    }
+
+    pub fn encode(&self) -> ::std::io::Result<::std::vec::Vec<u8>> {
+        let mut buf = ::std::vec::Vec::new();
+        self.write_to(&mut buf)?;
+        ::std::io::Result::Ok(buf)
+    }
+}
+
+",
+    );
+
+    Ok(())
+}
+
+/// Generates the Rust source code for all messages of `service`.
+///
+/// The output consists of items only and may be `include!`d into any
+/// module.
+pub fn generate(service: &Service) -> io::Result<String> {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "// Generated from the {} protocol definition. Do not edit.\n",
+        service.protocol_type
+    )
+    .unwrap();
+    writeln!(out, "pub const SERVICE_ID: u8 = {};\n", service.id).unwrap();
+
+    let mut idents = Vec::with_capacity(service.messages().len());
+    for message in service.messages() {
+        let mut ident = struct_ident(message);
+        while idents.contains(&ident) {
+            ident.push('_');
+        }
+
+        generate_message(&mut out, service, message, &ident)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", message.name, e)))?;
+        idents.push(ident);
+    }
+
+    Ok(out)
+}
+
+/// Parses the protocol definition at `input` and writes the generated
+/// code to `output`.
+///
+/// Build scripts should also print `cargo:rerun-if-changed` for `input`.
+pub fn generate_file<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q) -> io::Result<()> {
+    let service = Service::parse(&fs::read_to_string(input)?)?;
+    fs::write(output, generate(&service)?)
+}
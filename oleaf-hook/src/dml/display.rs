@@ -0,0 +1,278 @@
+use std::{fmt, io, mem, ptr, slice};
+
+use super::{offset_of, Field, FieldValue, Record, TypeId};
+
+// Records, fields and their values implement the traits directly. A
+// reference to any of them is only obtained by unsafe means, which
+// already requires the data they point to to be available.
+
+impl fmt::Display for TypeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeId::Unknown(id) => write!(f, "UNKNOWN({})", id),
+            known => f.write_str(known.name().unwrap()),
+        }
+    }
+}
+
+impl fmt::Debug for FieldValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Byt(v) => f.debug_tuple("Byt").field(v).finish(),
+            Self::UByt(v) => f.debug_tuple("UByt").field(v).finish(),
+            Self::UShrt(v) => f.debug_tuple("UShrt").field(v).finish(),
+            Self::Int(v) => f.debug_tuple("Int").field(v).finish(),
+            Self::UInt(v) => f.debug_tuple("UInt").field(v).finish(),
+            Self::Gid(v) => f.debug_tuple("Gid").field(v).finish(),
+            Self::Flt(v) => f.debug_tuple("Flt").field(v).finish(),
+            Self::Dbl(v) => f.debug_tuple("Dbl").field(v).finish(),
+            Self::Str(v) => f.debug_tuple("Str").field(v).finish(),
+            Self::WStr(v) => f.debug_tuple("WStr").field(v).finish(),
+            Self::Blob(v) => f.debug_tuple("Blob").field(v).finish(),
+            Self::Long(v) => f.debug_tuple("Long").field(v).finish(),
+            Self::ULong(v) => f.debug_tuple("ULong").field(v).finish(),
+        }
+    }
+}
+
+/// Formats the plain value, with strings neither quoted nor escaped.
+///
+/// Blobs are written as contiguous lowercase hex digits.
+impl fmt::Display for FieldValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Byt(v) => v.fmt(f),
+            Self::UByt(v) => v.fmt(f),
+            Self::UShrt(v) => v.fmt(f),
+            Self::Int(v) => v.fmt(f),
+            Self::UInt(v) => v.fmt(f),
+            Self::Gid(v) => v.fmt(f),
+            Self::Flt(v) => v.fmt(f),
+            Self::Dbl(v) => v.fmt(f),
+            Self::Str(v) => fmt::Display::fmt(v, f),
+            Self::WStr(v) => fmt::Display::fmt(v, f),
+            Self::Blob(v) => v.as_bytes().iter().try_for_each(|b| write!(f, "{:02x}", b)),
+            Self::Long(v) => v.fmt(f),
+            Self::ULong(v) => v.fmt(f),
+        }
+    }
+}
+
+// Writes the value of `field` as shown in record tables, with strings
+// quoted so that whitespace stays visible.
+fn write_table_value(f: &mut dyn fmt::Write, field: &Field) -> fmt::Result {
+    match unsafe { field.value() } {
+        Ok(FieldValue::Str(s)) => write!(f, "{:?}", s),
+        Ok(FieldValue::WStr(s)) => write!(f, "{:?}", s),
+        Ok(value) => write!(f, "{}", value),
+        Err(_) => f.write_str("?"),
+    }
+}
+
+impl fmt::Debug for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Field")
+            .field("name", unsafe { self.name() })
+            .field("type_id", &self.type_id())
+            .field("value", &unsafe { self.value() }.ok())
+            .finish()
+    }
+}
+
+/// Formats the field as `name: TYPE = value`.
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} = ", unsafe { self.name() }, self.type_id())?;
+        write_table_value(f, self)
+    }
+}
+
+impl fmt::Debug for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Record")
+            .field("vtable", &self.vtable)
+            .field("ref_count", &self.ref_count())
+            .field("fields", &unsafe { self.fields() })
+            .finish()
+    }
+}
+
+/// Formats the record as the table written by [`Record::dump`].
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = unsafe { self.fields() };
+        let names: Vec<_> = fields
+            .iter()
+            .map(|field| unsafe { field.name() }.to_string())
+            .collect();
+        let types: Vec<_> = fields
+            .iter()
+            .map(|field| field.type_id().to_string())
+            .collect();
+
+        let name_width = names.iter().map(String::len).max().unwrap_or(0).max(4);
+        let type_width = types.iter().map(String::len).max().unwrap_or(0).max(4);
+
+        writeln!(
+            f,
+            "{:name_width$}  {:type_width$}  VALUE",
+            "NAME",
+            "TYPE",
+            name_width = name_width,
+            type_width = type_width
+        )?;
+        for ((field, name), ty) in fields.iter().zip(&names).zip(&types) {
+            write!(
+                f,
+                "{:name_width$}  {:type_width$}  ",
+                name,
+                ty,
+                name_width = name_width,
+                type_width = type_width
+            )?;
+            write_table_value(f, field)?;
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a hexdump of `bytes` to `f`, 16 bytes per row.
+///
+/// Every row is annotated with the names of the `regions` that start
+/// in it, given as pairs of a name and an offset into `bytes`.
+pub fn write_hexdump(
+    f: &mut dyn fmt::Write,
+    bytes: &[u8],
+    regions: &[(&str, usize)],
+) -> fmt::Result {
+    for (row, chunk) in bytes.chunks(16).enumerate() {
+        let start = row * 16;
+        write!(f, "{:#06x}: ", start)?;
+        for i in 0..16 {
+            match chunk.get(i) {
+                Some(byte) => write!(f, "{:02x} ", byte)?,
+                None => f.write_str("   ")?,
+            }
+        }
+
+        f.write_str("|")?;
+        for &byte in chunk {
+            let c = if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            };
+            f.write_char(c)?;
+        }
+        write!(f, "{:1$}|", "", 16 - chunk.len())?;
+
+        for (name, offset) in regions {
+            if (start..start + 16).contains(offset) {
+                write!(f, " {}@{:#x}", name, offset)?;
+            }
+        }
+        writeln!(f)?;
+    }
+
+    Ok(())
+}
+
+impl Field {
+    /// Writes a hexdump of the raw memory of the field to `w`.
+    ///
+    /// Every row is annotated with the members of the field that start
+    /// in it, including the unknown regions. This is meant to help with
+    /// reverse engineering their purpose.
+    ///
+    /// # Safety
+    ///
+    /// The field must have been created by C++ code. Padding bytes of
+    /// fields built on the Rust side may be uninitialized.
+    pub unsafe fn hexdump<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        let regions = [
+            ("vtable", offset_of(self, ptr::addr_of!(self.vtable))),
+            (
+                "double_storage",
+                offset_of(self, ptr::addr_of!(self.double_storage)),
+            ),
+            (
+                "float_storage",
+                offset_of(self, ptr::addr_of!(self.float_storage)),
+            ),
+            (
+                "int_storage",
+                offset_of(self, ptr::addr_of!(self.int_storage)),
+            ),
+            (
+                "str_storage",
+                offset_of(self, ptr::addr_of!(self.str_storage)),
+            ),
+            (
+                "wstr_storage",
+                offset_of(self, ptr::addr_of!(self.wstr_storage)),
+            ),
+            (
+                "gid_storage",
+                offset_of(self, ptr::addr_of!(self.gid_storage)),
+            ),
+            ("type_id", offset_of(self, ptr::addr_of!(self.type_id))),
+            ("_31", offset_of(self, ptr::addr_of!(self._31))),
+            ("_38", offset_of(self, ptr::addr_of!(self._38))),
+            ("name", offset_of(self, ptr::addr_of!(self.name))),
+            ("_70", offset_of(self, ptr::addr_of!(self._70))),
+        ];
+
+        unsafe { write_raw_hexdump(w, self, &regions) }
+    }
+}
+
+impl Record {
+    /// Writes the fields of the record as an aligned table of their
+    /// names, types and values to `w`.
+    ///
+    /// Values of unknown types are shown as `?`.
+    ///
+    /// # Safety
+    ///
+    /// The record must be a valid record whose fields, including their
+    /// names and string values, are available while it is written.
+    pub unsafe fn dump<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        write!(w, "{}", self)
+    }
+
+    /// Writes a hexdump of the raw memory of the record itself, without
+    /// its fields, to `w`.
+    ///
+    /// See [`Field::hexdump`] for the format.
+    ///
+    /// # Safety
+    ///
+    /// See [`Field::hexdump`].
+    pub unsafe fn hexdump<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        let regions = [
+            ("vtable", offset_of(self, ptr::addr_of!(self.vtable))),
+            ("_08", offset_of(self, ptr::addr_of!(self._08))),
+            ("ref_count", offset_of(self, ptr::addr_of!(self.ref_count))),
+            ("fields", offset_of(self, ptr::addr_of!(self.fields))),
+        ];
+
+        unsafe { write_raw_hexdump(w, self, &regions) }
+    }
+}
+
+// SAFETY: All bytes of `value`, including padding, must be initialized.
+unsafe fn write_raw_hexdump<T, W: io::Write>(
+    w: &mut W,
+    value: &T,
+    regions: &[(&str, usize)],
+) -> io::Result<()> {
+    let bytes =
+        unsafe { slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) };
+
+    let mut out = String::new();
+    // Writing to a `String` never fails.
+    write_hexdump(&mut out, bytes, regions).unwrap();
+    w.write_all(out.as_bytes())
+}
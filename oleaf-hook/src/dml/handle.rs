@@ -0,0 +1,174 @@
+use std::{
+    fmt,
+    lazy::SyncOnceCell,
+    mem,
+    os::raw::{c_uint, c_void},
+    ptr::NonNull,
+    sync::atomic::Ordering,
+};
+
+use super::Record;
+
+/// The function signature for the client's function that releases a
+/// reference to a [`Record`], freeing it when none are left.
+pub type FnRelease = unsafe extern "fastcall" fn(*mut Record);
+
+// The signature of a virtual release function, which receives `flags`
+// as its second argument.
+type FnVirtualRelease = unsafe extern "fastcall" fn(*mut Record, c_uint);
+
+#[derive(Clone, Copy)]
+enum Release {
+    Function(FnRelease),
+    VtableSlot(usize, c_uint),
+}
+
+static RELEASE: SyncOnceCell<Release> = SyncOnceCell::new();
+
+fn initialize_release(release: Release) {
+    if RELEASE.set(release).is_err() {
+        panic!("Record release function was already initialized!");
+    }
+}
+
+/// Initializes the function used by [`RecordRef`] to release records,
+/// typically found through a signature scan.
+///
+/// # Panics
+///
+/// Panics if the release function has already been initialized previously.
+pub fn initialize_release_function(func: FnRelease) {
+    initialize_release(Release::Function(func));
+}
+
+/// Initializes [`RecordRef`] to release records through the virtual
+/// function at index `slot` of their vtables.
+///
+/// The function is called with the record and `flags` as arguments.
+/// On x64, both are passed in registers and cleaned up by the caller,
+/// so a function that takes no flags simply ignores them.
+///
+/// # Panics
+///
+/// Panics if the release function has already been initialized previously.
+pub fn initialize_release_slot(slot: usize, flags: c_uint) {
+    initialize_release(Release::VtableSlot(slot, flags));
+}
+
+impl Record {
+    /// Gets the number of references that are currently held to the
+    /// record.
+    pub fn ref_count(&self) -> u32 {
+        self.ref_count.load(Ordering::Acquire)
+    }
+}
+
+/// A counted reference to a [`Record`] that keeps it alive.
+///
+/// Cloning the handle takes a new reference and dropping it releases
+/// the reference through the client's own release function, which has
+/// to be set up using [`initialize_release_function`] or
+/// [`initialize_release_slot`] first.
+///
+/// This makes it possible to keep records from event handlers around
+/// for later or asynchronous processing, after the handler returned.
+/// Note that C++ code may still modify the record concurrently, which
+/// is why accessing it through [`RecordRef::get`] is unsafe.
+pub struct RecordRef {
+    ptr: NonNull<Record>,
+    release: Release,
+}
+
+// SAFETY: The reference count is only ever modified atomically, both by
+// us and by the client.
+unsafe impl Send for RecordRef {}
+unsafe impl Sync for RecordRef {}
+
+impl RecordRef {
+    /// Takes a new reference to the record at `ptr`.
+    ///
+    /// Returns [`None`] if `ptr` is null.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the release function was not yet initialized.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a live record whose reference count is in
+    /// use, i.e. one that was created by the client or through a
+    /// [`RecordBuilder`](super::RecordBuilder).
+    pub unsafe fn new(ptr: *mut Record) -> Option<Self> {
+        let release = *RELEASE
+            .get()
+            .expect("Record release function was not yet initialized!");
+
+        let ptr = NonNull::new(ptr)?;
+        unsafe { ptr.as_ref() }
+            .ref_count
+            .fetch_add(1, Ordering::AcqRel);
+        Some(Self { ptr, release })
+    }
+
+    /// Gets a reference to the record.
+    ///
+    /// # Safety
+    ///
+    /// The record stays alive, but the caller must ensure that C++ code
+    /// does not modify it while the reference is in use.
+    pub unsafe fn get(&self) -> &Record {
+        unsafe { self.ptr.as_ref() }
+    }
+
+    /// Gets the number of references that are currently held to the
+    /// record, including this one.
+    pub fn ref_count(&self) -> u32 {
+        // SAFETY: We hold a reference, so the record is still alive.
+        unsafe { self.ptr.as_ref() }.ref_count()
+    }
+
+    /// Gets a raw pointer to the record for passing it to C++ code.
+    pub fn as_ptr(&self) -> *mut Record {
+        self.ptr.as_ptr()
+    }
+}
+
+impl Clone for RecordRef {
+    fn clone(&self) -> Self {
+        // SAFETY: We hold a reference, so the record is still alive.
+        unsafe { self.ptr.as_ref() }
+            .ref_count
+            .fetch_add(1, Ordering::AcqRel);
+        Self {
+            ptr: self.ptr,
+            release: self.release,
+        }
+    }
+}
+
+impl Drop for RecordRef {
+    fn drop(&mut self) {
+        let record = self.ptr.as_ptr();
+        // SAFETY: The release function is trusted to match the record,
+        // which is still alive because we hold a reference.
+        unsafe {
+            match self.release {
+                Release::Function(func) => func(record),
+                Release::VtableSlot(slot, flags) => {
+                    let vtable = (*record).vtable as *const *const c_void;
+                    let func: FnVirtualRelease = mem::transmute(*vtable.add(slot));
+                    func(record, flags)
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for RecordRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordRef")
+            .field("ptr", &self.ptr)
+            .field("ref_count", &self.ref_count())
+            .finish()
+    }
+}
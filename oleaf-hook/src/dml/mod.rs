@@ -0,0 +1,392 @@
+//! ABI-compatible types depicting relevant primitives of the DML system.
+
+use std::{os::raw::*, ptr, sync::atomic::AtomicU32};
+
+use crate::cxx;
+
+mod access;
+pub use self::access::{FieldError, FieldIndex, FromField, FromRecord};
+
+mod builder;
+pub use self::builder::{RecordBuf, RecordBuilder};
+
+mod catalogue;
+pub use self::catalogue::{Catalogue, Message, Service};
+
+mod display;
+pub use self::display::write_hexdump;
+
+mod handle;
+pub use self::handle::{
+    initialize_release_function, initialize_release_slot, FnRelease, RecordRef,
+};
+
+mod mutate;
+
+mod owned;
+pub use self::owned::{OwnedField, OwnedRecord, OwnedValue, ToOwnedError};
+
+pub mod codegen;
+
+pub mod schema;
+
+pub mod wire;
+
+/// A unique ID that indicates the type of a DML [`Field`].
+///
+/// Fields store the ID as a raw byte, which is decoded using
+/// [`TypeId::from_raw`]. IDs we don't know about are preserved as
+/// [`TypeId::Unknown`].
+///
+/// `Blob`, `Long` and `ULong` appear in DML protocol definitions, but
+/// their raw IDs have not been taken from the client's type table yet.
+/// Until then, fields of these types decode as [`TypeId::Unknown`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TypeId {
+    Gid,
+    Int,
+    UInt,
+    Flt,
+    Byt,
+    UByt,
+    UShrt,
+    Dbl,
+    Str,
+    WStr,
+    Blob,
+    Long,
+    ULong,
+
+    Unknown(u8),
+}
+
+impl TypeId {
+    /// Decodes the raw type ID stored in a [`Field`].
+    pub fn from_raw(raw: u8) -> Self {
+        match raw {
+            1 => Self::Gid,
+            2 => Self::Int,
+            3 => Self::UInt,
+            4 => Self::Flt,
+            5 => Self::Byt,
+            6 => Self::UByt,
+            7 => Self::UShrt,
+            8 => Self::Dbl,
+            9 => Self::Str,
+            10 => Self::WStr,
+            raw => Self::Unknown(raw),
+        }
+    }
+
+    /// Gets the raw type ID as stored in a [`Field`], or [`None`] for
+    /// types whose raw ID is not known yet.
+    pub fn to_raw(self) -> Option<u8> {
+        match self {
+            Self::Gid => Some(1),
+            Self::Int => Some(2),
+            Self::UInt => Some(3),
+            Self::Flt => Some(4),
+            Self::Byt => Some(5),
+            Self::UByt => Some(6),
+            Self::UShrt => Some(7),
+            Self::Dbl => Some(8),
+            Self::Str => Some(9),
+            Self::WStr => Some(10),
+            Self::Blob | Self::Long | Self::ULong => None,
+            Self::Unknown(raw) => Some(raw),
+        }
+    }
+
+    /// Gets the type for its name in DML protocol definitions, e.g.
+    /// `UINT` or `WSTR`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "GID" => Some(Self::Gid),
+            "INT" => Some(Self::Int),
+            "UINT" => Some(Self::UInt),
+            "FLT" => Some(Self::Flt),
+            "BYT" => Some(Self::Byt),
+            "UBYT" => Some(Self::UByt),
+            "USHRT" => Some(Self::UShrt),
+            "DBL" => Some(Self::Dbl),
+            "STR" => Some(Self::Str),
+            "WSTR" => Some(Self::WStr),
+            "BLOB" => Some(Self::Blob),
+            "LONG" => Some(Self::Long),
+            "ULONG" => Some(Self::ULong),
+            _ => None,
+        }
+    }
+
+    /// Gets the name of the type in DML protocol definitions, or [`None`]
+    /// for unknown types.
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Self::Gid => Some("GID"),
+            Self::Int => Some("INT"),
+            Self::UInt => Some("UINT"),
+            Self::Flt => Some("FLT"),
+            Self::Byt => Some("BYT"),
+            Self::UByt => Some("UBYT"),
+            Self::UShrt => Some("USHRT"),
+            Self::Dbl => Some("DBL"),
+            Self::Str => Some("STR"),
+            Self::WStr => Some("WSTR"),
+            Self::Blob => Some("BLOB"),
+            Self::Long => Some("LONG"),
+            Self::ULong => Some("ULONG"),
+            Self::Unknown(_) => None,
+        }
+    }
+}
+
+/// A DML field that is part of a [`Record`].
+///
+/// This type is borrowed from C++ client code for introspection on the
+/// Rust side and should never be constructed manually.
+#[repr(C)]
+pub struct Field {
+    vtable: *mut c_void,
+    double_storage: c_double,
+    float_storage: c_float,
+    int_storage: c_int,
+    str_storage: *mut cxx::Str,
+    wstr_storage: *mut cxx::WStr,
+    gid_storage: c_ulonglong,
+    type_id: c_uchar,
+    _31: [u8; 0x7],
+    _38: [u8; 0x18],
+    name: cxx::Str,
+    _70: [u8; 0x8],
+}
+
+/// Representation of DML field values for introspection in Rust code.
+///
+/// [`Field::value`] does not produce `Blob`, `Long` and `ULong` values
+/// yet, see [`TypeId`].
+pub enum FieldValue<'dml> {
+    Byt(c_char),
+    UByt(c_uchar),
+    UShrt(c_ushort),
+    Int(c_int),
+    UInt(c_uint),
+    Gid(c_ulonglong),
+    Flt(c_float),
+    Dbl(c_double),
+    Str(&'dml cxx::Str),
+    WStr(&'dml cxx::WStr),
+    /// Binary data, which the client keeps in a string object.
+    Blob(&'dml cxx::Str),
+    Long(c_longlong),
+    ULong(c_ulonglong),
+}
+
+impl Field {
+    // Creates a field for a record whose memory is managed by Rust code,
+    // or `None` if the raw ID of the value's type is not known.
+    //
+    // SAFETY: `name` and the string storage referenced by `value` must
+    // outlive the resulting field.
+    pub(crate) unsafe fn from_value(name: cxx::Str, value: FieldValue<'_>) -> Option<Self> {
+        let mut field = Self {
+            vtable: ptr::null_mut(),
+            double_storage: 0.0,
+            float_storage: 0.0,
+            int_storage: 0,
+            str_storage: ptr::null_mut(),
+            wstr_storage: ptr::null_mut(),
+            gid_storage: 0,
+            type_id: 0,
+            _31: [0; 0x7],
+            _38: [0; 0x18],
+            name,
+            _70: [0; 0x8],
+        };
+
+        let type_id = match value {
+            FieldValue::Byt(v) => {
+                field.int_storage = v as c_int;
+                TypeId::Byt
+            }
+            FieldValue::UByt(v) => {
+                field.int_storage = v as c_int;
+                TypeId::UByt
+            }
+            FieldValue::UShrt(v) => {
+                field.int_storage = v as c_int;
+                TypeId::UShrt
+            }
+            FieldValue::Int(v) => {
+                field.int_storage = v;
+                TypeId::Int
+            }
+            FieldValue::UInt(v) => {
+                field.int_storage = v as c_int;
+                TypeId::UInt
+            }
+            FieldValue::Gid(v) => {
+                field.gid_storage = v;
+                TypeId::Gid
+            }
+            FieldValue::Flt(v) => {
+                field.float_storage = v;
+                TypeId::Flt
+            }
+            FieldValue::Dbl(v) => {
+                field.double_storage = v;
+                TypeId::Dbl
+            }
+            FieldValue::Str(v) => {
+                field.str_storage = v as *const cxx::Str as *mut cxx::Str;
+                TypeId::Str
+            }
+            FieldValue::WStr(v) => {
+                field.wstr_storage = v as *const cxx::WStr as *mut cxx::WStr;
+                TypeId::WStr
+            }
+            FieldValue::Blob(_) => TypeId::Blob,
+            FieldValue::Long(_) => TypeId::Long,
+            FieldValue::ULong(_) => TypeId::ULong,
+        };
+        field.type_id = type_id.to_raw()?;
+
+        Some(field)
+    }
+
+    /// Gets the name of the field.
+    ///
+    /// # Safety
+    ///
+    /// The lifetime of `self` and thus also the inferred lifetime of the
+    /// return value may not be representative of the real lifetime of
+    /// the data.
+    ///
+    /// The caller is responsible for ensuring the availability of the
+    /// requested data.
+    pub unsafe fn name(&self) -> &cxx::Str {
+        &self.name
+    }
+
+    /// Gets the [`TypeId`] of the value stored in this field.
+    pub fn type_id(&self) -> TypeId {
+        TypeId::from_raw(self.type_id)
+    }
+
+    /// Gets the value of this field.
+    ///
+    /// This function will error if the field has a type that is not known
+    /// to us, as its storage cannot be interpreted then, or if it is a
+    /// string field without a string object.
+    ///
+    /// # Safety
+    ///
+    /// The lifetime of the result may not be representative of the real
+    /// lifetime of the data.
+    ///
+    /// The caller is responsible for ensuring the availability of the
+    /// requested data.
+    pub unsafe fn value(&self) -> Result<FieldValue<'_>, FieldError> {
+        Ok(match self.type_id() {
+            TypeId::Gid => FieldValue::Gid(self.gid_storage),
+            TypeId::Int => FieldValue::Int(self.int_storage),
+            TypeId::UInt => FieldValue::UInt(self.int_storage as c_uint),
+            TypeId::Flt => FieldValue::Flt(self.float_storage),
+            TypeId::Byt => FieldValue::Byt(self.int_storage as c_char),
+            TypeId::UByt => FieldValue::UByt(self.int_storage as c_uchar),
+            TypeId::UShrt => FieldValue::UShrt(self.int_storage as c_ushort),
+            TypeId::Dbl => FieldValue::Dbl(self.double_storage),
+            TypeId::Str => FieldValue::Str(unsafe { &*self.string_storage(self.str_storage)? }),
+            TypeId::WStr => FieldValue::WStr(unsafe { &*self.string_storage(self.wstr_storage)? }),
+
+            TypeId::Blob | TypeId::Long | TypeId::ULong | TypeId::Unknown(_) => {
+                return Err(FieldError::UnknownType {
+                    name: self.name.to_string_lossy().into_owned(),
+                    type_id: self.type_id,
+                })
+            }
+        })
+    }
+}
+
+impl Field {
+    // Checks that the string storage `ptr` of this field can be accessed.
+    fn string_storage<T>(&self, ptr: *mut T) -> Result<*mut T, FieldError> {
+        if ptr.is_null() {
+            Err(FieldError::NullString {
+                name: self.name.to_string_lossy().into_owned(),
+            })
+        } else {
+            Ok(ptr)
+        }
+    }
+}
+
+assert_eq_size!(Field, [u8; 0x78]);
+
+// Gets the offset of `field` within the object at `base`.
+fn offset_of<T, U>(base: &T, field: *const U) -> usize {
+    field as usize - base as *const T as usize
+}
+
+impl Field {
+    // Gets the name, offset and contents of every region of unknown
+    // purpose in the field.
+    pub(crate) fn unknown_regions(&self) -> [(&'static str, usize, &[u8]); 3] {
+        [
+            ("_31", offset_of(self, ptr::addr_of!(self._31)), &self._31),
+            ("_38", offset_of(self, ptr::addr_of!(self._38)), &self._38),
+            ("_70", offset_of(self, ptr::addr_of!(self._70)), &self._70),
+        ]
+    }
+}
+
+/// A DML record that groups together several DML [`Field`]s holding data.
+///
+/// Records are the bodies of DML messages and the primary format of
+/// structured data in the game. We will exfiltrate most information
+/// from records.
+#[repr(C)]
+pub struct Record {
+    vtable: *mut c_void,
+    _08: [u8; 0x10],
+    // The client modifies the count with interlocked operations.
+    ref_count: AtomicU32,
+    fields: cxx::Vector<Field>,
+}
+
+impl Record {
+    // Creates a record for fields whose memory is managed by Rust code.
+    //
+    // SAFETY: The storage behind `fields` must outlive the resulting record.
+    pub(crate) unsafe fn from_fields(vtable: *mut c_void, fields: cxx::Vector<Field>) -> Self {
+        Self {
+            vtable,
+            _08: [0; 0x10],
+            ref_count: AtomicU32::new(1),
+            fields,
+        }
+    }
+
+    /// Gets a slice holding all the [`Field`]s in the record.
+    ///
+    /// # Safety
+    ///
+    /// The lifetime of the result may not be representative of the real
+    /// lifetime of the data.
+    ///
+    /// The caller is responsible for ensuring the availability of the
+    /// requested data.
+    pub unsafe fn fields(&self) -> &[Field] {
+        unsafe { self.fields.as_slice() }
+    }
+}
+
+impl Record {
+    // Gets the name, offset and contents of every region of unknown
+    // purpose in the record.
+    pub(crate) fn unknown_regions(&self) -> [(&'static str, usize, &[u8]); 1] {
+        [("_08", offset_of(self, ptr::addr_of!(self._08)), &self._08)]
+    }
+}
+
+assert_eq_size!(Record, [u8; 0x38]);
@@ -0,0 +1,111 @@
+use std::os::raw::*;
+
+use super::{Field, FieldError, Record, TypeId};
+use crate::cxx::alloc::Allocator;
+
+macro_rules! numeric_setters {
+    ($($setter:ident: $variant:ident => $ty:ty, $storage:ident as $repr:ty;)*) => {
+        $(
+            #[doc = concat!(
+                "Sets the value of this field if it is of type `",
+                stringify!($variant),
+                "`."
+            )]
+            pub fn $setter(&mut self, value: $ty) -> Result<(), FieldError> {
+                self.expect_type(TypeId::$variant)?;
+                self.$storage = value as $repr;
+                Ok(())
+            }
+        )*
+    };
+}
+
+impl Field {
+    fn expect_type(&self, expected: TypeId) -> Result<(), FieldError> {
+        if self.type_id() == expected {
+            Ok(())
+        } else {
+            Err(FieldError::TypeMismatch {
+                // SAFETY: The name is embedded in the field itself.
+                name: self.name.to_string_lossy().into_owned(),
+                expected,
+                found: self.type_id(),
+            })
+        }
+    }
+
+    numeric_setters! {
+        set_i8: Byt => c_char, int_storage as c_int;
+        set_u8: UByt => c_uchar, int_storage as c_int;
+        set_u16: UShrt => c_ushort, int_storage as c_int;
+        set_i32: Int => c_int, int_storage as c_int;
+        set_u32: UInt => c_uint, int_storage as c_int;
+        set_gid: Gid => c_ulonglong, gid_storage as c_ulonglong;
+        set_f32: Flt => c_float, float_storage as c_float;
+        set_f64: Dbl => c_double, double_storage as c_double;
+    }
+
+    /// Replaces the value of this field if it is of type `Str`.
+    ///
+    /// See [`Str::assign`](crate::cxx::Str::assign) for how storage is managed.
+    ///
+    /// # Safety
+    ///
+    /// The field must point to a live string object whose heap storage,
+    /// if any, was allocated by `alloc`.
+    pub unsafe fn set_str<A: Allocator>(
+        &mut self,
+        value: &[u8],
+        alloc: &A,
+    ) -> Result<(), FieldError> {
+        self.expect_type(TypeId::Str)?;
+        let storage = self.string_storage(self.str_storage)?;
+        unsafe { (*storage).assign(value, alloc) };
+        Ok(())
+    }
+
+    /// Replaces the value of this field if it is of type `WStr`.
+    ///
+    /// See [`WStr::assign`](crate::cxx::WStr::assign) for how storage is managed.
+    ///
+    /// # Safety
+    ///
+    /// The field must point to a live string object whose heap storage,
+    /// if any, was allocated by `alloc`.
+    pub unsafe fn set_wstr<A: Allocator>(
+        &mut self,
+        value: &str,
+        alloc: &A,
+    ) -> Result<(), FieldError> {
+        self.expect_type(TypeId::WStr)?;
+        let storage = self.string_storage(self.wstr_storage)?;
+        unsafe { (*storage).assign(value, alloc) };
+        Ok(())
+    }
+}
+
+impl Record {
+    /// Gets a mutable slice holding all the [`Field`]s in the record.
+    ///
+    /// # Safety
+    ///
+    /// The lifetime of the result may not be representative of the real
+    /// lifetime of the data.
+    ///
+    /// The caller is responsible for ensuring the availability of the
+    /// requested data and that no other code accesses it concurrently.
+    pub unsafe fn fields_mut(&mut self) -> &mut [Field] {
+        unsafe { self.fields.as_mut_slice() }
+    }
+
+    /// Finds the first field with the given name for modification.
+    ///
+    /// # Safety
+    ///
+    /// See [`Record::fields_mut`].
+    pub unsafe fn get_mut(&mut self, name: &str) -> Option<&mut Field> {
+        unsafe { self.fields_mut() }
+            .iter_mut()
+            .find(|f| f.name == name)
+    }
+}
@@ -0,0 +1,147 @@
+use std::{error::Error, fmt, os::raw::*};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::{Field, FieldError, FieldValue, Record};
+
+/// An owned copy of a DML [`Record`] that is fully managed by Rust.
+///
+/// Unlike [`Record`], this can be kept around after a hook returned
+/// and be sent to other threads.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OwnedRecord {
+    /// The fields of the record in their original order.
+    pub fields: Vec<OwnedField>,
+}
+
+impl OwnedRecord {
+    /// Finds the first field with the given name.
+    pub fn field(&self, name: &str) -> Option<&OwnedField> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+}
+
+/// An owned copy of a DML [`Field`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OwnedField {
+    /// The name of the field.
+    pub name: String,
+    /// The value of the field.
+    pub value: OwnedValue,
+}
+
+/// The owned counterpart to [`FieldValue`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum OwnedValue {
+    Byt(c_char),
+    UByt(c_uchar),
+    UShrt(c_ushort),
+    Int(c_int),
+    UInt(c_uint),
+    Gid(c_ulonglong),
+    Flt(c_float),
+    Dbl(c_double),
+    /// The raw bytes of the string, which need not be valid UTF-8.
+    Str(Vec<u8>),
+    WStr(String),
+    Blob(Vec<u8>),
+    Long(c_longlong),
+    ULong(c_ulonglong),
+}
+
+/// An error that occurred while converting a [`Record`] into an
+/// [`OwnedRecord`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ToOwnedError {
+    /// The name of a field is not valid UTF-8.
+    InvalidName,
+    /// The type of the field could not be determined.
+    UnknownType { field: String },
+    /// A string or blob field does not point to any string object.
+    NullString { field: String },
+    /// A `WStr` field is not valid UTF-16.
+    InvalidString { field: String },
+}
+
+impl fmt::Display for ToOwnedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidName => write!(f, "field name is not valid UTF-8"),
+            Self::UnknownType { field } => write!(f, "field '{}' has an unknown type", field),
+            Self::NullString { field } => write!(f, "string field '{}' is null", field),
+            Self::InvalidString { field } => {
+                write!(f, "string field '{}' holds malformed data", field)
+            }
+        }
+    }
+}
+
+impl Error for ToOwnedError {}
+
+impl Field {
+    /// Creates an owned copy of the field.
+    ///
+    /// Names and `WStr` values are validated instead of being lossily
+    /// converted, so the result always compares equal for equal
+    /// input data. `Str` values are copied byte by byte.
+    ///
+    /// # Safety
+    ///
+    /// The field must be a live object managed by C++ code, or one that
+    /// was built on the Rust side with valid string storage.
+    pub unsafe fn to_owned(&self) -> Result<OwnedField, ToOwnedError> {
+        let name = self.name.as_bytes();
+        let name = std::str::from_utf8(name)
+            .map_err(|_| ToOwnedError::InvalidName)?
+            .to_owned();
+
+        let value = match unsafe { self.value() } {
+            Ok(FieldValue::Byt(v)) => OwnedValue::Byt(v),
+            Ok(FieldValue::UByt(v)) => OwnedValue::UByt(v),
+            Ok(FieldValue::UShrt(v)) => OwnedValue::UShrt(v),
+            Ok(FieldValue::Int(v)) => OwnedValue::Int(v),
+            Ok(FieldValue::UInt(v)) => OwnedValue::UInt(v),
+            Ok(FieldValue::Gid(v)) => OwnedValue::Gid(v),
+            Ok(FieldValue::Flt(v)) => OwnedValue::Flt(v),
+            Ok(FieldValue::Dbl(v)) => OwnedValue::Dbl(v),
+            Ok(FieldValue::Str(v)) => OwnedValue::Str(v.as_bytes().to_vec()),
+            Ok(FieldValue::WStr(v)) => match String::from_utf16(v.as_utf16()) {
+                Ok(s) => OwnedValue::WStr(s),
+                Err(_) => return Err(ToOwnedError::InvalidString { field: name }),
+            },
+            Ok(FieldValue::Blob(v)) => OwnedValue::Blob(v.as_bytes().to_vec()),
+            Ok(FieldValue::Long(v)) => OwnedValue::Long(v),
+            Ok(FieldValue::ULong(v)) => OwnedValue::ULong(v),
+            Err(FieldError::NullString { .. }) => {
+                return Err(ToOwnedError::NullString { field: name })
+            }
+            Err(_) => return Err(ToOwnedError::UnknownType { field: name }),
+        };
+
+        Ok(OwnedField { name, value })
+    }
+}
+
+impl Record {
+    /// Creates an owned copy of the record and all its fields.
+    ///
+    /// Fails on the first field that cannot be converted. See
+    /// [`Field::to_owned`] for details.
+    ///
+    /// # Safety
+    ///
+    /// The record must be a live object managed by C++ code, or one that
+    /// was built on the Rust side with valid field storage.
+    pub unsafe fn to_owned(&self) -> Result<OwnedRecord, ToOwnedError> {
+        let fields = unsafe { self.fields() }
+            .iter()
+            .map(|f| unsafe { f.to_owned() })
+            .collect::<Result<_, _>>()?;
+
+        Ok(OwnedRecord { fields })
+    }
+}
@@ -0,0 +1,501 @@
+//! Detection of changes to the layouts of DML records between game
+//! versions.
+//!
+//! While recording, the layout of every [`Record`] passed to an event
+//! handler is captured into a [`Snapshot`], i.e. the names and types of
+//! its fields in order, keyed by the name of the event. Handlers with
+//! `#[record]` arguments are observed automatically, others may call
+//! [`observe`] themselves.
+//!
+//! Snapshots are saved in a line-based text format:
+//!
+//! ```text
+//! [HandleQuestDialog]
+//! QuestID UINT
+//! Title WSTR
+//! ```
+//!
+//! Kinds and field names that could not be read back verbatim, e.g.
+//! because they are empty or contain whitespace, are written as quoted
+//! strings with Rust escape sequences, such as `"Quest Title" WSTR`.
+//!
+//! Comparing the snapshots taken before and after a game patch with
+//! [`Snapshot::diff`] then reveals which fields were added, removed,
+//! renamed, retyped or moved.
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt, fs,
+    io::{self, BufRead, Write},
+    lazy::SyncLazy,
+    mem,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use super::{wire::FieldDef, Record, TypeId};
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn type_name(type_id: TypeId) -> String {
+    match type_id {
+        TypeId::Unknown(id) => id.to_string(),
+        known => known.name().unwrap().to_owned(),
+    }
+}
+
+fn parse_type(name: &str) -> Option<TypeId> {
+    TypeId::from_name(name).or_else(|| name.parse().ok().map(TypeId::Unknown))
+}
+
+// Quotes `name` if it would not survive being read back as is.
+fn quote(name: &str) -> Cow<'_, str> {
+    let plain = !name.is_empty()
+        && !name.starts_with(&['#', '['][..])
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || c == '"' || c == '\\');
+
+    if plain {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(format!("{:?}", name))
+    }
+}
+
+// Parses a string quoted by `quote` from the start of `s`, returning it
+// together with the remainder of `s`.
+fn unquote(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.strip_prefix('"')?.char_indices();
+    let mut name = String::new();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((name, &s[i + 2..])),
+            '\\' => name.push(match chars.next()?.1 {
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                '0' => '\0',
+                'u' => {
+                    let (hex, _) = chars.as_str().strip_prefix('{')?.split_once('}')?;
+                    let c = char::from_u32(u32::from_str_radix(hex, 16).ok()?)?;
+                    chars.nth(hex.len() + 1)?;
+                    c
+                }
+                c @ ('\\' | '"' | '\'') => c,
+                _ => return None,
+            }),
+            c => name.push(c),
+        }
+    }
+    None
+}
+
+/// The observed field layouts of DML records, keyed by their kind.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    kinds: BTreeMap<String, Vec<FieldDef>>,
+}
+
+impl Snapshot {
+    /// Creates a new, empty snapshot.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the layout of the record kind `kind`, returning the previous
+    /// layout.
+    pub fn insert<S: Into<String>>(
+        &mut self,
+        kind: S,
+        fields: Vec<FieldDef>,
+    ) -> Option<Vec<FieldDef>> {
+        self.kinds.insert(kind.into(), fields)
+    }
+
+    /// Gets the layout of the record kind `kind`.
+    pub fn get(&self, kind: &str) -> Option<&[FieldDef]> {
+        self.kinds.get(kind).map(Vec::as_slice)
+    }
+
+    /// Gets all record kinds and their layouts ordered by kind.
+    pub fn kinds(&self) -> impl Iterator<Item = (&str, &[FieldDef])> {
+        self.kinds.iter().map(|(k, v)| (k.as_str(), v.as_slice()))
+    }
+
+    /// Records the layout of `record` as the kind `kind`, unless the
+    /// kind is already known.
+    ///
+    /// # Safety
+    ///
+    /// `record` must be a valid record.
+    pub unsafe fn observe(&mut self, kind: &str, record: &Record) {
+        if self.kinds.contains_key(kind) {
+            return;
+        }
+
+        let fields = unsafe { record.fields() }
+            .iter()
+            .map(|field| {
+                let name = unsafe { field.name() }.as_bytes();
+                FieldDef::new(String::from_utf8_lossy(name), field.type_id())
+            })
+            .collect();
+        self.kinds.insert(kind.to_owned(), fields);
+    }
+
+    /// Reads a snapshot in the text format from `r`.
+    pub fn read_from<R: BufRead>(r: R) -> io::Result<Self> {
+        let mut snapshot = Self::new();
+        let mut current = None;
+
+        for (n, line) in r.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(kind) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let kind = if kind.starts_with('"') {
+                    match unquote(kind) {
+                        Some((kind, "")) => kind,
+                        _ => {
+                            return Err(invalid_data(format!(
+                                "line {}: malformed kind '{}'",
+                                n + 1,
+                                line
+                            )))
+                        }
+                    }
+                } else {
+                    kind.to_owned()
+                };
+
+                snapshot.kinds.insert(kind.clone(), Vec::new());
+                current = Some(kind);
+                continue;
+            }
+
+            let malformed = || invalid_data(format!("line {}: malformed field '{}'", n + 1, line));
+            let fields = current
+                .as_ref()
+                .and_then(|kind| snapshot.kinds.get_mut(kind))
+                .ok_or_else(malformed)?;
+            let (name, ty) = if line.starts_with('"') {
+                let (name, rest) = unquote(line).ok_or_else(malformed)?;
+                let ty = rest.strip_prefix(' ').ok_or_else(malformed)?;
+                (name, ty.trim_start())
+            } else {
+                let (name, ty) = line.rsplit_once(' ').ok_or_else(malformed)?;
+                (name.trim_end().to_owned(), ty)
+            };
+            let type_id = parse_type(ty).ok_or_else(malformed)?;
+            fields.push(FieldDef::new(name, type_id));
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Writes the snapshot in the text format to `w`.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for (kind, fields) in &self.kinds {
+            writeln!(w, "[{}]", quote(kind))?;
+            for field in fields {
+                writeln!(w, "{} {}", quote(&field.name), type_name(field.type_id))?;
+            }
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+
+    /// Loads a snapshot from the file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_from(io::BufReader::new(fs::File::open(path)?))
+    }
+
+    /// Saves the snapshot to the file at `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut buf = Vec::new();
+        self.write_to(&mut buf)?;
+        fs::write(path, buf)
+    }
+
+    /// Compares the snapshot to a `newer` one and lists all changes.
+    ///
+    /// Fields are matched up by name. A removed field is assumed to be
+    /// renamed when a field of the same type was added in its place.
+    pub fn diff(&self, newer: &Snapshot) -> Vec<Change> {
+        let mut changes = Vec::new();
+
+        for (kind, old) in &self.kinds {
+            match newer.kinds.get(kind) {
+                Some(new) => diff_fields(kind, old, new, &mut changes),
+                None => changes.push(Change::KindRemoved { kind: kind.clone() }),
+            }
+        }
+        for kind in newer.kinds.keys() {
+            if !self.kinds.contains_key(kind) {
+                changes.push(Change::KindAdded { kind: kind.clone() });
+            }
+        }
+
+        changes
+    }
+}
+
+/// A change to the layout of a record kind between two [`Snapshot`]s.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// The record kind was not observed before.
+    KindAdded { kind: String },
+    /// The record kind is no longer observed.
+    KindRemoved { kind: String },
+    /// A field was added.
+    Added { kind: String, field: FieldDef },
+    /// A field was removed.
+    Removed { kind: String, field: FieldDef },
+    /// A field was replaced by one with another name but the same type.
+    Renamed {
+        kind: String,
+        from: String,
+        to: String,
+        type_id: TypeId,
+    },
+    /// A field kept its name but changed its type.
+    Retyped {
+        kind: String,
+        name: String,
+        from: TypeId,
+        to: TypeId,
+    },
+    /// A field moved relative to the other fields.
+    Moved {
+        kind: String,
+        name: String,
+        from: usize,
+        to: usize,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KindAdded { kind } => write!(f, "{}: new record kind", kind),
+            Self::KindRemoved { kind } => write!(f, "{}: record kind is gone", kind),
+            Self::Added { kind, field } => write!(
+                f,
+                "{}: added {} {}",
+                kind,
+                field.name,
+                type_name(field.type_id)
+            ),
+            Self::Removed { kind, field } => write!(
+                f,
+                "{}: removed {} {}",
+                kind,
+                field.name,
+                type_name(field.type_id)
+            ),
+            Self::Renamed {
+                kind,
+                from,
+                to,
+                type_id,
+            } => write!(
+                f,
+                "{}: renamed {} to {} ({})",
+                kind,
+                from,
+                to,
+                type_name(*type_id)
+            ),
+            Self::Retyped {
+                kind,
+                name,
+                from,
+                to,
+            } => write!(
+                f,
+                "{}: retyped {} from {} to {}",
+                kind,
+                name,
+                type_name(*from),
+                type_name(*to)
+            ),
+            Self::Moved {
+                kind,
+                name,
+                from,
+                to,
+            } => write!(f, "{}: moved {} from #{} to #{}", kind, name, from, to),
+        }
+    }
+}
+
+// Gets the names of the longest common subsequence of `a` and `b`.
+fn longest_common_names<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<&'a str> {
+    let mut table = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            table[i][j] = if a[i] == b[j] {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut names = Vec::with_capacity(table[0][0]);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            names.push(a[i]);
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    names
+}
+
+// Gets the name of the nearest field before `index` that exists in both
+// layouts, which identifies the slot of an added or removed field.
+fn anchor<'a>(fields: &'a [FieldDef], index: usize, other: &[FieldDef]) -> Option<&'a str> {
+    fields[..index]
+        .iter()
+        .rev()
+        .map(|f| f.name.as_str())
+        .find(|name| other.iter().any(|o| o.name == *name))
+}
+
+fn diff_fields(kind: &str, old: &[FieldDef], new: &[FieldDef], changes: &mut Vec<Change>) {
+    let position = |fields: &[FieldDef], name: &str| fields.iter().position(|f| f.name == name);
+
+    // Fields that only exist on one side were renamed, removed or added.
+    let removed: Vec<_> = (0..old.len())
+        .filter(|&i| position(new, &old[i].name).is_none())
+        .collect();
+    let mut added: Vec<_> = (0..new.len())
+        .filter(|&i| position(old, &new[i].name).is_none())
+        .collect();
+
+    for i in removed {
+        let field = &old[i];
+        let slot = anchor(old, i, new);
+        let renamed = added
+            .iter()
+            .position(|&j| new[j].type_id == field.type_id && anchor(new, j, old) == slot);
+
+        match renamed {
+            Some(pos) => {
+                let to = &new[added.remove(pos)];
+                changes.push(Change::Renamed {
+                    kind: kind.to_owned(),
+                    from: field.name.clone(),
+                    to: to.name.clone(),
+                    type_id: field.type_id,
+                });
+            }
+            None => changes.push(Change::Removed {
+                kind: kind.to_owned(),
+                field: field.clone(),
+            }),
+        }
+    }
+    changes.extend(added.into_iter().map(|j| Change::Added {
+        kind: kind.to_owned(),
+        field: new[j].clone(),
+    }));
+
+    // Fields on both sides may have changed their types or their order.
+    let common_old: Vec<_> = old
+        .iter()
+        .map(|f| f.name.as_str())
+        .filter(|name| position(new, name).is_some())
+        .collect();
+    let common_new: Vec<_> = new
+        .iter()
+        .map(|f| f.name.as_str())
+        .filter(|name| position(old, name).is_some())
+        .collect();
+    let in_order = longest_common_names(&common_old, &common_new);
+
+    for name in common_old {
+        let (from, to) = (position(old, name).unwrap(), position(new, name).unwrap());
+        if old[from].type_id != new[to].type_id {
+            changes.push(Change::Retyped {
+                kind: kind.to_owned(),
+                name: name.to_owned(),
+                from: old[from].type_id,
+                to: new[to].type_id,
+            });
+        }
+        if !in_order.contains(&name) {
+            changes.push(Change::Moved {
+                kind: kind.to_owned(),
+                name: name.to_owned(),
+                from,
+                to,
+            });
+        }
+    }
+}
+
+// Lets `observe` return before locking the snapshot, as hooks call it
+// for every record even when no snapshot is being taken.
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+static RECORDED: SyncLazy<Mutex<Snapshot>> = SyncLazy::new(|| Mutex::new(Snapshot::new()));
+
+/// Starts recording record layouts into a new snapshot.
+///
+/// Layouts recorded previously are discarded.
+pub fn start() {
+    *RECORDED.lock().unwrap() = Snapshot::new();
+    RECORDING.store(true, Ordering::Release);
+}
+
+/// Stops recording and returns the snapshot of all layouts observed.
+pub fn stop() -> Snapshot {
+    RECORDING.store(false, Ordering::Release);
+    mem::take(&mut *RECORDED.lock().unwrap())
+}
+
+/// Gets a copy of the layouts observed so far.
+pub fn snapshot() -> Snapshot {
+    RECORDED.lock().unwrap().clone()
+}
+
+/// Checks if layouts are currently being recorded.
+#[inline]
+pub fn is_recording() -> bool {
+    RECORDING.load(Ordering::Acquire)
+}
+
+/// Records the layout of `record` as the kind `kind` while recording.
+///
+/// Only the first layout observed for each kind is kept.
+///
+/// # Safety
+///
+/// `record` must be a valid record.
+pub unsafe fn observe(kind: &str, record: &Record) {
+    if !is_recording() {
+        return;
+    }
+
+    if let Ok(mut recorded) = RECORDED.lock() {
+        unsafe { recorded.observe(kind, record) };
+    }
+}
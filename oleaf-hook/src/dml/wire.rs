@@ -0,0 +1,314 @@
+//! Codec for the serialized DML message bodies sent over the network.
+//!
+//! Records are encoded as the plain concatenation of their field values
+//! in the order given by a schema, without any names or type tags:
+//!
+//! - All scalars are stored in little-endian byte order.
+//! - `GID`s are 64-bit unsigned integers.
+//! - `STR`s are prefixed with their length in bytes as a `u16`. They
+//!   are byte strings and need not be valid UTF-8.
+//! - `WSTR`s are prefixed with their length in UTF-16 code units as a
+//!   `u16`, followed by the code units themselves.
+//! - `BLOB`s are encoded just like `STR`s.
+//! - `LONG`s and `ULONG`s are 64-bit signed and unsigned integers.
+//!
+//! This module does not depend on the client and works on any platform.
+
+use std::io::{self, Read, Write};
+
+use super::{OwnedField, OwnedRecord, OwnedValue, TypeId};
+
+/// The definition of a single field in the schema of a record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldDef {
+    /// The name of the field.
+    pub name: String,
+    /// The type of the field.
+    pub type_id: TypeId,
+}
+
+impl FieldDef {
+    /// Creates a new field definition.
+    pub fn new<S: Into<String>>(name: S, type_id: TypeId) -> Self {
+        Self {
+            name: name.into(),
+            type_id,
+        }
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+fn read_array<R: Read, const N: usize>(r: &mut R) -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_str<R: Read>(r: &mut R) -> io::Result<Vec<u8>> {
+    let len = u16::from_le_bytes(read_array(r)?) as usize;
+    let mut buf = vec![0; len];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_value<R: Read>(r: &mut R, name: &str, type_id: TypeId) -> io::Result<OwnedValue> {
+    Ok(match type_id {
+        TypeId::Gid => OwnedValue::Gid(u64::from_le_bytes(read_array(r)?)),
+        TypeId::Int => OwnedValue::Int(i32::from_le_bytes(read_array(r)?)),
+        TypeId::UInt => OwnedValue::UInt(u32::from_le_bytes(read_array(r)?)),
+        TypeId::Flt => OwnedValue::Flt(f32::from_le_bytes(read_array(r)?)),
+        TypeId::Byt => OwnedValue::Byt(i8::from_le_bytes(read_array(r)?)),
+        TypeId::UByt => OwnedValue::UByt(u8::from_le_bytes(read_array(r)?)),
+        TypeId::UShrt => OwnedValue::UShrt(u16::from_le_bytes(read_array(r)?)),
+        TypeId::Dbl => OwnedValue::Dbl(f64::from_le_bytes(read_array(r)?)),
+        TypeId::Str => OwnedValue::Str(read_str(r)?),
+        TypeId::WStr => {
+            let len = u16::from_le_bytes(read_array(r)?) as usize;
+            let mut units = Vec::with_capacity(len);
+            for _ in 0..len {
+                units.push(u16::from_le_bytes(read_array(r)?));
+            }
+
+            let s = String::from_utf16(&units)
+                .map_err(|_| invalid_data(format!("field '{}' is not valid UTF-16", name)))?;
+            OwnedValue::WStr(s)
+        }
+        TypeId::Blob => OwnedValue::Blob(read_str(r)?),
+        TypeId::Long => OwnedValue::Long(i64::from_le_bytes(read_array(r)?)),
+        TypeId::ULong => OwnedValue::ULong(u64::from_le_bytes(read_array(r)?)),
+        TypeId::Unknown(id) => {
+            return Err(invalid_input(format!(
+                "field '{}' has unsupported type {}",
+                name, id
+            )))
+        }
+    })
+}
+
+fn write_len<W: Write>(w: &mut W, len: usize, name: &str) -> io::Result<()> {
+    let len = u16::try_from(len)
+        .map_err(|_| invalid_input(format!("field '{}' exceeds the maximum length", name)))?;
+    w.write_all(&len.to_le_bytes())
+}
+
+fn write_str<W: Write>(w: &mut W, name: &str, value: &[u8]) -> io::Result<()> {
+    write_len(w, value.len(), name)?;
+    w.write_all(value)
+}
+
+fn write_wstr<W: Write>(w: &mut W, name: &str, value: &str) -> io::Result<()> {
+    let units: Vec<u16> = value.encode_utf16().collect();
+    write_len(w, units.len(), name)?;
+    for unit in units {
+        w.write_all(&unit.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn write_value<W: Write>(
+    w: &mut W,
+    name: &str,
+    type_id: TypeId,
+    value: &OwnedValue,
+) -> io::Result<()> {
+    match (type_id, value) {
+        (TypeId::Gid, OwnedValue::Gid(v)) => w.write_all(&v.to_le_bytes()),
+        (TypeId::Int, OwnedValue::Int(v)) => w.write_all(&v.to_le_bytes()),
+        (TypeId::UInt, OwnedValue::UInt(v)) => w.write_all(&v.to_le_bytes()),
+        (TypeId::Flt, OwnedValue::Flt(v)) => w.write_all(&v.to_le_bytes()),
+        (TypeId::Byt, OwnedValue::Byt(v)) => w.write_all(&v.to_le_bytes()),
+        (TypeId::UByt, OwnedValue::UByt(v)) => w.write_all(&v.to_le_bytes()),
+        (TypeId::UShrt, OwnedValue::UShrt(v)) => w.write_all(&v.to_le_bytes()),
+        (TypeId::Dbl, OwnedValue::Dbl(v)) => w.write_all(&v.to_le_bytes()),
+        (TypeId::Str, OwnedValue::Str(v)) => write_str(w, name, v),
+        (TypeId::WStr, OwnedValue::WStr(v)) => write_wstr(w, name, v),
+        (TypeId::Blob, OwnedValue::Blob(v)) => write_str(w, name, v),
+        (TypeId::Long, OwnedValue::Long(v)) => w.write_all(&v.to_le_bytes()),
+        (TypeId::ULong, OwnedValue::ULong(v)) => w.write_all(&v.to_le_bytes()),
+        (type_id, value) => Err(invalid_input(format!(
+            "field '{}' is of type {:?}, but got {:?}",
+            name, type_id, value
+        ))),
+    }
+}
+
+/// A Rust type that can be read and written as a DML value.
+///
+/// This is implemented for the Rust counterparts of all [`OwnedValue`]
+/// variants and used by the code emitted by [`codegen`].
+///
+/// [`codegen`]: super::codegen
+pub trait WireType: Sized {
+    /// Reads the field `name` of type `type_id` from `r`.
+    fn read<R: Read>(r: &mut R, name: &str, type_id: TypeId) -> io::Result<Self>;
+
+    /// Writes `self` to `w` as the field `name` of type `type_id`.
+    fn write<W: Write>(&self, w: &mut W, name: &str, type_id: TypeId) -> io::Result<()>;
+}
+
+fn type_mismatch(name: &str, type_id: TypeId, ty: &str) -> io::Error {
+    invalid_input(format!(
+        "field '{}' is of type {:?}, which cannot be represented as {}",
+        name, type_id, ty
+    ))
+}
+
+macro_rules! impl_wire_type {
+    ($($ty:ty => $($variant:ident)|+),* $(,)?) => {
+        $(
+            impl WireType for $ty {
+                fn read<R: Read>(r: &mut R, name: &str, type_id: TypeId) -> io::Result<Self> {
+                    match type_id {
+                        $(TypeId::$variant)|+ => match read_value(r, name, type_id)? {
+                            $(OwnedValue::$variant(v))|+ => Ok(v),
+                            _ => unreachable!(),
+                        },
+                        _ => Err(type_mismatch(name, type_id, stringify!($ty))),
+                    }
+                }
+
+                fn write<W: Write>(&self, w: &mut W, name: &str, type_id: TypeId) -> io::Result<()> {
+                    match type_id {
+                        $(
+                            TypeId::$variant => {
+                                write_value(w, name, type_id, &OwnedValue::$variant(*self))
+                            }
+                        )+
+                        _ => Err(type_mismatch(name, type_id, stringify!($ty))),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_wire_type! {
+    i8 => Byt,
+    u8 => UByt,
+    u16 => UShrt,
+    i32 => Int,
+    u32 => UInt,
+    u64 => Gid | ULong,
+    i64 => Long,
+    f32 => Flt,
+    f64 => Dbl,
+}
+
+/// Reads and writes `STR` as well as `WSTR` values.
+///
+/// `STR` values that are not valid UTF-8 cannot be read as `String`,
+/// use `Vec<u8>` for them instead.
+impl WireType for String {
+    fn read<R: Read>(r: &mut R, name: &str, type_id: TypeId) -> io::Result<Self> {
+        match type_id {
+            TypeId::Str | TypeId::WStr => match read_value(r, name, type_id)? {
+                OwnedValue::Str(v) => String::from_utf8(v)
+                    .map_err(|_| invalid_data(format!("field '{}' is not valid UTF-8", name))),
+                OwnedValue::WStr(v) => Ok(v),
+                _ => unreachable!(),
+            },
+            _ => Err(type_mismatch(name, type_id, "String")),
+        }
+    }
+
+    fn write<W: Write>(&self, w: &mut W, name: &str, type_id: TypeId) -> io::Result<()> {
+        match type_id {
+            TypeId::Str => write_str(w, name, self.as_bytes()),
+            TypeId::WStr => write_wstr(w, name, self),
+            _ => Err(type_mismatch(name, type_id, "String")),
+        }
+    }
+}
+
+/// Reads and writes the raw bytes of `STR` and `BLOB` values.
+impl WireType for Vec<u8> {
+    fn read<R: Read>(r: &mut R, name: &str, type_id: TypeId) -> io::Result<Self> {
+        match type_id {
+            TypeId::Str | TypeId::Blob => match read_value(r, name, type_id)? {
+                OwnedValue::Str(v) | OwnedValue::Blob(v) => Ok(v),
+                _ => unreachable!(),
+            },
+            _ => Err(type_mismatch(name, type_id, "Vec<u8>")),
+        }
+    }
+
+    fn write<W: Write>(&self, w: &mut W, name: &str, type_id: TypeId) -> io::Result<()> {
+        match type_id {
+            TypeId::Str | TypeId::Blob => write_str(w, name, self),
+            _ => Err(type_mismatch(name, type_id, "Vec<u8>")),
+        }
+    }
+}
+
+/// Reads a record with the given `schema` from `r`.
+///
+/// The fields of the resulting record are in schema order.
+pub fn read_record<R: Read>(r: &mut R, schema: &[FieldDef]) -> io::Result<OwnedRecord> {
+    let fields = schema
+        .iter()
+        .map(|def| {
+            Ok(OwnedField {
+                name: def.name.clone(),
+                value: read_value(r, &def.name, def.type_id)?,
+            })
+        })
+        .collect::<io::Result<_>>()?;
+
+    Ok(OwnedRecord { fields })
+}
+
+/// Writes `record` to `w` in the layout given by `schema`.
+///
+/// Fields are looked up by name, so their order in `record` does not
+/// matter. Fields which are not part of the schema are ignored.
+pub fn write_record<W: Write>(
+    w: &mut W,
+    schema: &[FieldDef],
+    record: &OwnedRecord,
+) -> io::Result<()> {
+    for def in schema {
+        let field = record
+            .field(&def.name)
+            .ok_or_else(|| invalid_input(format!("record has no field '{}'", def.name)))?;
+        write_value(w, &def.name, def.type_id, &field.value)?;
+    }
+
+    Ok(())
+}
+
+/// Decodes a record with the given `schema` from `data`.
+///
+/// Unlike [`read_record`], this fails if not all of `data` was consumed.
+pub fn decode(data: &[u8], schema: &[FieldDef]) -> io::Result<OwnedRecord> {
+    let mut rest = data;
+    let record = read_record(&mut rest, schema)?;
+
+    expect_end(rest)?;
+    Ok(record)
+}
+
+/// Fails if there is `rest` data left after decoding a record.
+pub fn expect_end(rest: &[u8]) -> io::Result<()> {
+    if rest.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid_data(format!(
+            "{} trailing bytes after record",
+            rest.len()
+        )))
+    }
+}
+
+/// Encodes `record` in the layout given by `schema`.
+pub fn encode(record: &OwnedRecord, schema: &[FieldDef]) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    write_record(&mut buf, schema, record)?;
+    Ok(buf)
+}
@@ -1,8 +1,18 @@
-use std::{lazy::SyncOnceCell, os::raw::c_void};
+use std::{
+    error::Error,
+    fmt,
+    lazy::SyncOnceCell,
+    mem,
+    os::raw::c_void,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+    time::Instant,
+};
 
 use detour::static_detour;
 
-use crate::cxx;
+use crate::{cxx, dml::Record, metrics, trace, unload, unwind};
 
 // Not part of the public API. Used by generated code.
 #[doc(hidden)]
@@ -14,6 +24,11 @@ pub static INIT_EVENT_DETOURS: [unsafe fn(*mut c_void)] = [..];
 #[linkme::distributed_slice]
 pub static UNHOOK_EVENT_DETOURS: [unsafe fn()] = [..];
 
+// Not part of the public API. Used by generated code.
+#[doc(hidden)]
+#[linkme::distributed_slice]
+pub static EVENT_HANDLER_NAMES: [&'static str] = [..];
+
 static_detour! {
     /// The detour for installing custom event handlers.
     ///
@@ -32,8 +47,14 @@ pub type FnSendEvent =
 /// The function signature for a dispatcher's event handler getter.
 pub type FnGetEventHandler = extern "fastcall" fn(*mut c_void, *mut cxx::String) -> *mut c_void;
 
+/// The function signature for event handlers taking a DML record.
+pub type FnEventHandler = unsafe extern "fastcall" fn(*mut c_void, *mut Record) -> *mut c_void;
+
 static EVENT_HANDLER_GETTER: SyncOnceCell<FnGetEventHandler> = SyncOnceCell::new();
 
+// The dispatcher most recently passed to `send_event_detour`.
+static LAST_DISPATCHER: AtomicPtr<c_void> = AtomicPtr::new(ptr::null_mut());
+
 /// Initializes the global event handler getter to the given functions.
 ///
 /// # Panics
@@ -63,15 +84,116 @@ pub fn find_event_by_name(dispatcher: *mut c_void, name: &mut cxx::String) -> Op
     }
 }
 
+/// Gets the dispatcher that was most recently seen by
+/// [`send_event_detour`], if any.
+pub fn last_dispatcher() -> Option<*mut c_void> {
+    let dispatcher = LAST_DISPATCHER.load(Ordering::Acquire);
+    if !dispatcher.is_null() {
+        Some(dispatcher)
+    } else {
+        None
+    }
+}
+
+/// An error that occurred while invoking an event handler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvokeError {
+    /// No dispatcher was seen by [`send_event_detour`] yet.
+    NoDispatcher,
+    /// The event name contains interior null bytes.
+    InvalidName,
+    /// The dispatcher has no handler for the event.
+    NotFound { name: String },
+}
+
+impl fmt::Display for InvokeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoDispatcher => write!(f, "no event dispatcher was seen yet"),
+            Self::InvalidName => write!(f, "event name contains interior null bytes"),
+            Self::NotFound { name } => write!(f, "no handler for event '{}'", name),
+        }
+    }
+}
+
+impl Error for InvokeError {}
+
+/// Invokes the client's handler for the event `name` with `record`,
+/// using the dispatcher from [`last_dispatcher`].
+///
+/// See [`invoke_on`] for details.
+///
+/// # Panics
+///
+/// Panics when the event handler getter was not yet initialized.
+///
+/// # Safety
+///
+/// See [`invoke_on`].
+pub unsafe fn invoke(name: &str, record: &mut Record) -> Result<*mut c_void, InvokeError> {
+    let dispatcher = last_dispatcher().ok_or(InvokeError::NoDispatcher)?;
+    unsafe { invoke_on(dispatcher, name, record) }
+}
+
+/// Invokes the handler of `dispatcher` for the event `name` with `record`.
+///
+/// The handler is looked up through [`find_event_by_name`] and called
+/// with `dispatcher` as its `this` object. If a detour was installed
+/// for the handler using the [`oleaf_hook::event`](macro@crate::event)
+/// macro, it runs as well. Records for this are best created with a
+/// [`RecordBuilder`](crate::dml::RecordBuilder).
+///
+/// # Panics
+///
+/// Panics when the event handler getter was not yet initialized.
+///
+/// # Safety
+///
+/// `dispatcher` must be a valid dispatcher object and the handler must
+/// take a DML record, matching [`FnEventHandler`]. `record` must have
+/// the vtables of the client's records because handlers may call into
+/// them. The call must happen on the thread that usually dispatches
+/// events.
+pub unsafe fn invoke_on(
+    dispatcher: *mut c_void,
+    name: &str,
+    record: &mut Record,
+) -> Result<*mut c_void, InvokeError> {
+    let mut event = unsafe { cxx::String::new(name) }.map_err(|_| InvokeError::InvalidName)?;
+    let handler =
+        find_event_by_name(dispatcher, &mut event).ok_or_else(|| InvokeError::NotFound {
+            name: name.to_owned(),
+        })?;
+
+    // SAFETY: The caller guarantees that the handler has this signature.
+    let handler = unsafe { mem::transmute::<*mut c_void, FnEventHandler>(handler) };
+    Ok(unsafe { handler(dispatcher, record) })
+}
+
+/// Checks if a Rust handler was defined for the event `name` using the
+/// [`oleaf_hook::event`](macro@crate::event) macro.
+pub fn has_handler(name: &[u8]) -> bool {
+    EVENT_HANDLER_NAMES
+        .iter()
+        .any(|handler| handler.as_bytes() == name)
+}
+
 /// A detour for [`SendEventHook`] that is used to install custom event
 /// handlers for data exfiltration.
 ///
 /// The initialization of the hook should be performed by the crate user.
 ///
 /// This will also set up all the detours that were defined using the
-/// [`oleaf_hook::event`] macro.
+/// [`oleaf_hook::event`](macro@crate::event) macro.
+///
+/// Use [`unhook_all`] to uninstall all the detours, or [`unload::shutdown`]
+/// to also wait for all calls to finish before unloading the library.
 ///
-/// Use [`unhook_all`] to uninstall all the detours.
+/// When a [`trace`] recorder is running, every call will be logged to it.
+/// All calls are accounted for in [`metrics::SEND_EVENT_METRICS`].
+///
+/// Panics are caught and reported through [`unwind`]. The original
+/// function is then still called if that has not happened yet.
 ///
 /// # Safety
 ///
@@ -83,13 +205,48 @@ pub fn send_event_detour(
     name: *mut cxx::Str,
     unk: *mut c_void,
 ) -> *mut c_void {
-    // Get a handle to the dispatcher object and call all event detour installers.
-    for ptr in INIT_EVENT_DETOURS {
-        unsafe { ptr(dispatcher) }
-    }
+    let _in_flight = unload::InFlight::enter();
+    let timer = metrics::Timer::start(&metrics::SEND_EVENT_METRICS);
+    let frame = unwind::Frame::enter();
+
+    LAST_DISPATCHER.store(dispatcher, Ordering::Release);
+
+    // Panics must never unwind into the C++ code that called us.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        // Get a handle to the dispatcher object and call all event detour installers.
+        for ptr in INIT_EVENT_DETOURS {
+            unsafe { ptr(dispatcher) }
+        }
+
+        let event_name = unsafe { (*name).as_bytes() };
+        if let Some(handler_metrics) = metrics::find(event_name) {
+            handler_metrics.count_dispatch();
+        }
+
+        // Log the event if the user asked for it.
+        if trace::is_recording() {
+            trace::record(dispatcher, event_name);
+        }
+
+        // Call the original C++ function.
+        let start = Instant::now();
+        unwind::mark_original_called();
+        let ret = unsafe { SendEventHook.call(dispatcher, name, unk) };
+        timer.record_original(start);
+
+        ret
+    }));
+
+    result.unwrap_or_else(|payload| {
+        unwind::report("SendEvent", &*payload);
+        metrics::SEND_EVENT_METRICS.count_panic();
 
-    // Call the original C++ function.
-    unsafe { SendEventHook.call(dispatcher, name, unk) }
+        if frame.original_called() {
+            ptr::null_mut()
+        } else {
+            unsafe { SendEventHook.call(dispatcher, name, unk) }
+        }
+    })
 }
 
 /// Unhooks all event handler detours that were set up by [`send_event_detour`].
@@ -1,7 +1,7 @@
 //!
 
 #![deny(unsafe_op_in_unsafe_fn, rustdoc::broken_intra_doc_links)]
-#![feature(arbitrary_enum_discriminant, c_size_t, once_cell)]
+#![feature(c_size_t, once_cell)]
 
 #[macro_use]
 extern crate static_assertions;
@@ -14,7 +14,22 @@ pub mod dml;
 
 pub mod event;
 
+pub mod metrics;
+
+#[cfg(windows)]
 pub mod module;
+#[cfg(windows)]
 pub use self::module::Module;
 
+#[cfg(windows)]
 pub mod paging;
+
+pub mod re;
+
+pub mod replay;
+
+pub mod trace;
+
+pub mod unload;
+
+pub mod unwind;
@@ -0,0 +1,361 @@
+//! Lock-free per-event metrics for hooks.
+//!
+//! Every handler defined through the [`oleaf_hook::event`] macro and
+//! the [`send_event_detour`] itself keep an [`EventMetrics`] instance.
+//! It counts calls and caught panics and records latency histograms for
+//! the time spent in the Rust handler and in the original C++ function.
+//!
+//! All counters are plain atomics, so updating them never blocks the
+//! game thread. Use [`snapshot`] to read them or [`dump`] to print a
+//! summary table.
+//!
+//! [`oleaf_hook::event`]: macro@crate::event
+//! [`send_event_detour`]: crate::event::send_event_detour
+
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    io::{self, Write},
+    lazy::SyncLazy,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// The number of buckets in a [`Histogram`].
+///
+/// Bucket `n` counts durations of less than `2^n` nanoseconds that did
+/// not fit into the previous bucket. The last bucket also takes all
+/// durations that exceed its range.
+pub const BUCKETS: usize = 40;
+
+/// A lock-free latency histogram with logarithmic buckets.
+pub struct Histogram {
+    buckets: [AtomicU64; BUCKETS],
+    total_nanos: AtomicU64,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU64 = AtomicU64::new(0);
+
+impl Histogram {
+    /// Creates a new, empty histogram.
+    pub const fn new() -> Self {
+        Self {
+            buckets: [ZERO; BUCKETS],
+            total_nanos: ZERO,
+        }
+    }
+
+    /// Records a single measured duration.
+    pub fn record(&self, duration: Duration) {
+        let nanos = duration.as_nanos().min(u64::MAX as u128) as u64;
+        let bucket = (64 - nanos.leading_zeros() as usize).min(BUCKETS - 1);
+
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.total_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// Takes a snapshot of the current histogram state.
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut buckets = [0; BUCKETS];
+        for (snap, bucket) in buckets.iter_mut().zip(&self.buckets) {
+            *snap = bucket.load(Ordering::Relaxed);
+        }
+
+        HistogramSnapshot {
+            buckets,
+            total: Duration::from_nanos(self.total_nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn reset(&self) {
+        for bucket in &self.buckets {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.total_nanos.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A point-in-time copy of a [`Histogram`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistogramSnapshot {
+    /// The number of recorded durations per bucket.
+    pub buckets: [u64; BUCKETS],
+    /// The sum of all recorded durations.
+    pub total: Duration,
+}
+
+impl HistogramSnapshot {
+    /// Gets the number of recorded durations.
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// Gets the mean of all recorded durations.
+    pub fn mean(&self) -> Duration {
+        match self.count() {
+            0 => Duration::ZERO,
+            count => Duration::from_nanos((self.total.as_nanos() / count as u128) as u64),
+        }
+    }
+
+    /// Estimates the `p`-th percentile, where `p` is in `0.0..=1.0`.
+    ///
+    /// The result is the upper bound of the bucket that contains the
+    /// percentile and thus overestimates by at most a factor of two.
+    pub fn percentile(&self, p: f64) -> Duration {
+        let target = (self.count() as f64 * p.clamp(0.0, 1.0)).ceil() as u64;
+
+        let mut seen = 0;
+        for (n, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if count != 0 && seen >= target {
+                return Duration::from_nanos(1 << n);
+            }
+        }
+        Duration::ZERO
+    }
+}
+
+/// Metrics for a single event or hook.
+pub struct EventMetrics {
+    name: &'static str,
+    calls: AtomicU64,
+    dispatches: AtomicU64,
+    panics: AtomicU64,
+    handler: Histogram,
+    original: Histogram,
+}
+
+impl EventMetrics {
+    /// Creates new metrics for the event `name`.
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            calls: ZERO,
+            dispatches: ZERO,
+            panics: ZERO,
+            handler: Histogram::new(),
+            original: Histogram::new(),
+        }
+    }
+
+    /// Gets the name of the event these metrics belong to.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Takes a snapshot of the current metrics.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            name: self.name,
+            calls: self.calls.load(Ordering::Relaxed),
+            dispatches: self.dispatches.load(Ordering::Relaxed),
+            panics: self.panics.load(Ordering::Relaxed),
+            handler: self.handler.snapshot(),
+            original: self.original.snapshot(),
+        }
+    }
+
+    /// Resets all counters to zero.
+    pub fn reset(&self) {
+        self.calls.store(0, Ordering::Relaxed);
+        self.dispatches.store(0, Ordering::Relaxed);
+        self.panics.store(0, Ordering::Relaxed);
+        self.handler.reset();
+        self.original.reset();
+    }
+
+    // Counts a panic that was caught in this hook.
+    pub(crate) fn count_panic(&self) {
+        self.panics.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Counts an event that was fired through `SendEvent`.
+    pub(crate) fn count_dispatch(&self) {
+        self.dispatches.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A point-in-time copy of [`EventMetrics`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    /// The name of the event.
+    pub name: &'static str,
+    /// The number of calls to the hook.
+    pub calls: u64,
+    /// The number of times the event was fired through `SendEvent`.
+    pub dispatches: u64,
+    /// The number of panics that unwound through the hook.
+    pub panics: u64,
+    /// The time spent in Rust code, excluding the original function.
+    pub handler: HistogramSnapshot,
+    /// The time spent in the original function.
+    pub original: HistogramSnapshot,
+}
+
+// Not part of the public API. Used by generated code.
+#[doc(hidden)]
+#[linkme::distributed_slice]
+pub static EVENT_METRICS: [EventMetrics] = [..];
+
+/// The metrics of [`send_event_detour`](crate::event::send_event_detour).
+pub static SEND_EVENT_METRICS: EventMetrics = EventMetrics::new("SendEvent");
+
+// Looked up for every call to `send_event_detour`, so built only once.
+static BY_NAME: SyncLazy<HashMap<&'static [u8], &'static EventMetrics>> = SyncLazy::new(|| {
+    EVENT_METRICS
+        .iter()
+        .map(|m| (m.name.as_bytes(), m))
+        .collect()
+});
+
+/// Finds the metrics of the handler for the event `name`.
+pub fn find(name: &[u8]) -> Option<&'static EventMetrics> {
+    BY_NAME.get(name).copied()
+}
+
+/// Takes a snapshot of [`SEND_EVENT_METRICS`] and all event handlers.
+pub fn snapshot() -> Vec<Snapshot> {
+    std::iter::once(&SEND_EVENT_METRICS)
+        .chain(EVENT_METRICS.iter())
+        .map(EventMetrics::snapshot)
+        .collect()
+}
+
+/// Resets the metrics of [`SEND_EVENT_METRICS`] and all event handlers.
+pub fn reset() {
+    std::iter::once(&SEND_EVENT_METRICS)
+        .chain(EVENT_METRICS.iter())
+        .for_each(EventMetrics::reset);
+}
+
+/// Writes a summary table of all metrics to `w`.
+pub fn dump<W: Write>(w: &mut W) -> io::Result<()> {
+    let snapshots = snapshot();
+    let width = snapshots.iter().map(|s| s.name.len()).max().unwrap_or(0);
+
+    writeln!(
+        w,
+        "{:<width$} {:>10} {:>10} {:>7} {:>12} {:>12} {:>12} {:>12}",
+        "event",
+        "calls",
+        "fired",
+        "panics",
+        "rust mean",
+        "rust p99",
+        "orig mean",
+        "orig p99",
+        width = width
+    )?;
+    for s in snapshots {
+        writeln!(
+            w,
+            "{:<width$} {:>10} {:>10} {:>7} {:>12?} {:>12?} {:>12?} {:>12?}",
+            s.name,
+            s.calls,
+            s.dispatches,
+            s.panics,
+            s.handler.mean(),
+            s.handler.percentile(0.99),
+            s.original.mean(),
+            s.original.percentile(0.99),
+            width = width
+        )?;
+    }
+
+    Ok(())
+}
+
+static DUMPING: AtomicBool = AtomicBool::new(false);
+
+static DUMP_THREAD: SyncLazy<Mutex<Option<JoinHandle<()>>>> = SyncLazy::new(|| Mutex::new(None));
+
+/// The shortest interval accepted by [`start_periodic_dump`].
+pub const MIN_DUMP_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Spawns a thread that [`dump`]s all metrics to stdout every `interval`.
+///
+/// Intervals shorter than [`MIN_DUMP_INTERVAL`] are raised to it.
+/// A previously started dump thread is stopped first.
+pub fn start_periodic_dump(interval: Duration) {
+    stop_periodic_dump();
+
+    let interval = interval.max(MIN_DUMP_INTERVAL);
+    DUMPING.store(true, Ordering::SeqCst);
+    let handle = thread::spawn(move || {
+        let mut last = Instant::now();
+
+        while DUMPING.load(Ordering::SeqCst) {
+            thread::sleep(MIN_DUMP_INTERVAL);
+            if last.elapsed() >= interval {
+                let _ = dump(&mut io::stdout().lock());
+                last = Instant::now();
+            }
+        }
+    });
+    *DUMP_THREAD.lock().unwrap() = Some(handle);
+}
+
+/// Stops the thread started by [`start_periodic_dump`] and waits for it
+/// to exit.
+pub fn stop_periodic_dump() {
+    DUMPING.store(false, Ordering::SeqCst);
+    if let Some(handle) = DUMP_THREAD.lock().unwrap().take() {
+        let _ = handle.join();
+    }
+}
+
+// Not part of the public API. Used by generated code.
+//
+// Measures a single call to a hook and records it when dropped. The
+// time spent in the original function is tracked separately and
+// subtracted from the handler time.
+#[doc(hidden)]
+pub struct Timer {
+    metrics: &'static EventMetrics,
+    start: Instant,
+    original: Cell<Duration>,
+}
+
+impl Timer {
+    #[inline]
+    pub fn start(metrics: &'static EventMetrics) -> Self {
+        metrics.calls.fetch_add(1, Ordering::Relaxed);
+        Self {
+            metrics,
+            start: Instant::now(),
+            original: Cell::new(Duration::ZERO),
+        }
+    }
+
+    #[inline]
+    pub fn record_original(&self, start: Instant) {
+        let elapsed = start.elapsed();
+        self.metrics.original.record(elapsed);
+        self.original.set(self.original.get() + elapsed);
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        let total = self.start.elapsed();
+        self.metrics
+            .handler
+            .record(total.saturating_sub(self.original.get()));
+
+        if thread::panicking() {
+            self.metrics.count_panic();
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     fmt, io,
     mem::{self, MaybeUninit},
+    os::raw::c_void,
     slice,
 };
 
@@ -53,6 +54,148 @@ impl<'a> Module<'a> {
         self.memory.len()
     }
 
+    /// Finds the vtable of the class with the decorated RTTI type name
+    /// `name` and returns a pointer to its first entry.
+    ///
+    /// The name is given as found in MSVC type descriptors, e.g.
+    /// `.?AVRecord@DML@@`. Only the vtable for the complete object,
+    /// not those of any base class subobjects, is considered.
+    pub fn find_vtable(&self, name: &str) -> io::Result<*const c_void> {
+        let not_found = |what: &str| {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("failed to find {} for {} in PE memory", what, name),
+            )
+        };
+
+        // The name is stored null-terminated in the type descriptor,
+        // after its vtable pointer and a reserved pointer.
+        let mut needle = name.as_bytes().to_vec();
+        needle.push(0);
+        let name_offset = self
+            .memory
+            .windows(needle.len())
+            .position(|window| window == needle.as_slice())
+            .filter(|&offset| offset >= 0x10)
+            .ok_or_else(|| not_found("type descriptor"))?;
+        let type_descriptor = (name_offset - 0x10) as u32;
+
+        // On x64, the complete object locator references the type
+        // descriptor and itself by their offsets from the image base.
+        let locator = (0..self.memory.len().saturating_sub(0x18))
+            .step_by(4)
+            .find(|&offset| {
+                let field = |i: usize| self.read_u32(offset + i * 4).ok();
+                field(0) == Some(1)
+                    && field(1) == Some(0)
+                    && field(3) == Some(type_descriptor)
+                    && field(5) == Some(offset as u32)
+            })
+            .ok_or_else(|| not_found("complete object locator"))?;
+
+        // The vtable is preceded by a pointer to its object locator.
+        let locator_ptr = (self.base() + locator) as u64;
+        (0..self.memory.len().saturating_sub(0x8))
+            .step_by(8)
+            .find(|&offset| self.read_u64(offset).ok() == Some(locator_ptr))
+            .map(|offset| self.memory[offset + 8..].as_ptr() as *const c_void)
+            .ok_or_else(|| not_found("vtable"))
+    }
+
+    /// Finds the function `name` that this module imports by name from
+    /// any DLL and returns its address as resolved by the loader.
+    pub fn find_import(&self, name: &str) -> io::Result<*const c_void> {
+        self.find_import_in(|_| true, name)
+    }
+
+    /// Finds the function `name` that this module imports by name from
+    /// a DLL for whose name `dll` returns `true`, and returns its address
+    /// as resolved by the loader.
+    ///
+    /// DLL names are passed as stored in the import directory, which
+    /// does not normalize their case.
+    pub fn find_import_in<F: Fn(&str) -> bool>(
+        &self,
+        dll: F,
+        name: &str,
+    ) -> io::Result<*const c_void> {
+        let not_found = || {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("failed to find import {} in PE memory", name),
+            )
+        };
+
+        // The import directory is the second data directory of the
+        // PE32+ optional header, which follows the NT signature and
+        // the file header.
+        let nt_headers = self.read_u32(0x3C)? as usize;
+        let imports = self.read_u32(nt_headers + 0x18 + 0x78)? as usize;
+        if imports == 0 {
+            return Err(not_found());
+        }
+
+        // The descriptor list is terminated by a zeroed entry.
+        for descriptor in (imports..).step_by(0x14) {
+            let names = self.read_u32(descriptor)? as usize;
+            let addresses = self.read_u32(descriptor + 0x10)? as usize;
+            if addresses == 0 {
+                break;
+            }
+            if names == 0 {
+                // Without a lookup table, the names are gone after binding.
+                continue;
+            }
+            if !dll(&self.read_c_str(self.read_u32(descriptor + 0x0C)? as usize)?) {
+                continue;
+            }
+
+            for i in 0.. {
+                let thunk = self.read_u64(names + i * 8)?;
+                if thunk == 0 {
+                    break;
+                }
+                // Imports by ordinal have the most significant bit set.
+                if thunk >> 63 != 0 {
+                    continue;
+                }
+
+                // The name is preceded by a 2-byte hint.
+                let imported = self.read_c_str(thunk as usize + 2)?;
+                if imported == name {
+                    return Ok(self.read_u64(addresses + i * 8)? as *const c_void);
+                }
+            }
+        }
+
+        Err(not_found())
+    }
+
+    fn read_c_str(&self, offset: usize) -> io::Result<String> {
+        let bytes = self.memory.get(offset..).ok_or_else(out_of_bounds)?;
+        let len = bytes
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(out_of_bounds)?;
+        Ok(String::from_utf8_lossy(&bytes[..len]).into_owned())
+    }
+
+    fn read_array<const N: usize>(&self, offset: usize) -> io::Result<[u8; N]> {
+        offset
+            .checked_add(N)
+            .and_then(|end| self.memory.get(offset..end))
+            .map(|bytes| bytes.try_into().unwrap())
+            .ok_or_else(out_of_bounds)
+    }
+
+    fn read_u32(&self, offset: usize) -> io::Result<u32> {
+        self.read_array(offset).map(u32::from_le_bytes)
+    }
+
+    fn read_u64(&self, offset: usize) -> io::Result<u64> {
+        self.read_array(offset).map(u64::from_le_bytes)
+    }
+
     /// Finds the first address in this module that matches the signature
     /// `pattern` and returns a pointer to the byte at that address.
     ///
@@ -62,6 +205,33 @@ impl<'a> Module<'a> {
     ///
     /// Example: `AB 01 32 ?? 48`
     pub fn find_signature(&self, pattern: &str) -> io::Result<*const u8> {
+        self.signature_matches(pattern)
+            .next()
+            .ok_or_else(signature_not_found)
+    }
+
+    /// Finds the only address in this module that matches the signature
+    /// `pattern` and returns a pointer to the byte at that address.
+    ///
+    /// Unlike [`Module::find_signature`], this fails if the signature
+    /// matches more than one address, as it then does not reliably
+    /// identify what it is meant to.
+    ///
+    /// See [`Module::find_signature`] for the format of `pattern`.
+    pub fn find_unique_signature(&self, pattern: &str) -> io::Result<*const u8> {
+        let mut matches = self.signature_matches(pattern);
+        let first = matches.next().ok_or_else(signature_not_found)?;
+
+        match matches.count() {
+            0 => Ok(first),
+            n => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("signature pattern matches {} addresses in PE memory", n + 1),
+            )),
+        }
+    }
+
+    fn signature_matches<'p>(&'p self, pattern: &str) -> impl Iterator<Item = *const u8> + 'p {
         let pattern_elements: Vec<_> = pattern
             .split(' ')
             .map(|e| u8::from_str_radix(e, 16).ok())
@@ -69,7 +239,7 @@ impl<'a> Module<'a> {
 
         self.memory
             .windows(pattern_elements.len())
-            .find(|window| {
+            .filter(move |window| {
                 pattern_elements
                     .iter()
                     .zip(window.iter())
@@ -78,15 +248,23 @@ impl<'a> Module<'a> {
                     })
             })
             .map(|window| window.as_ptr())
-            .ok_or_else(|| {
-                io::Error::new(
-                    io::ErrorKind::UnexpectedEof,
-                    "failed to find signature pattern in PE memory",
-                )
-            })
     }
 }
 
+fn out_of_bounds() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "PE structure points outside of module memory",
+    )
+}
+
+fn signature_not_found() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "failed to find signature pattern in PE memory",
+    )
+}
+
 impl<'a> fmt::Debug for Module<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
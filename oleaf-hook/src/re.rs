@@ -0,0 +1,484 @@
+//! Statistics for reverse engineering unknown regions of C++ objects.
+//!
+//! A [`RegionStats`] collects the contents of the same region of memory
+//! across many object instances. It classifies every byte as constant,
+//! a small enum or varying, and every 8-byte aligned slot by whether it
+//! looks like a pointer into a module or the heap. [`Correlated`] stats
+//! additionally group the samples by a key, such as the [`TypeId`] of a
+//! DML field, to reveal bytes that depend on it.
+//!
+//! [`DmlSurvey`] applies this to the unknown regions of DML records and
+//! fields:
+//!
+//! ```ignore
+//! static SURVEY: SyncLazy<Mutex<DmlSurvey>> = SyncLazy::new(|| {
+//!     Mutex::new(DmlSurvey::new(AddressRanges::current()))
+//! });
+//!
+//! #[event("HandleQuestDialog")]
+//! fn quest_dialog_handler(this: *mut c_void, record: *mut Record) {
+//!     unsafe { SURVEY.lock().unwrap().observe(&*record) };
+//!     call_original!()
+//! }
+//!
+//! // Later on:
+//! println!("{}", SURVEY.lock().unwrap());
+//! ```
+
+use std::{collections::BTreeMap, fmt, ops::Range};
+
+#[cfg(windows)]
+use windows::Win32::System::Memory::{
+    VirtualQuery, MEMORY_BASIC_INFORMATION, MEM_COMMIT, MEM_IMAGE, MEM_PRIVATE,
+};
+
+use crate::dml::{Record, TypeId};
+
+/// The maximum number of distinct values for a byte to count as enum.
+pub const ENUM_LIMIT: usize = 8;
+
+/// What a value looks like when interpreted as a pointer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointerKind {
+    /// The value is zero.
+    Null,
+    /// The value points into the image of a loaded module.
+    Module,
+    /// The value points into heap memory.
+    Heap,
+    /// The value is not a pointer into any known memory.
+    Other,
+}
+
+/// The ranges of memory used to classify pointer-like values.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AddressRanges {
+    // Both are sorted and free of empty or overlapping ranges, so that
+    // they can be binary searched.
+    module: Vec<Range<usize>>,
+    heap: Vec<Range<usize>>,
+}
+
+// Sorts `ranges` by their start and merges the ones that overlap.
+fn normalize(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.retain(|r| !r.is_empty());
+    ranges.sort_unstable_by_key(|r| r.start);
+
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+// Checks if any of the normalized `ranges` contains `addr`.
+fn contains(ranges: &[Range<usize>], addr: usize) -> bool {
+    let after = ranges.partition_point(|r| r.start <= addr);
+    after > 0 && ranges[after - 1].contains(&addr)
+}
+
+impl AddressRanges {
+    /// Creates ranges from the memory of loaded module images and the
+    /// committed memory that is private to the process, which includes
+    /// all heaps.
+    pub fn new(module: Vec<Range<usize>>, heap: Vec<Range<usize>>) -> Self {
+        Self {
+            module: normalize(module),
+            heap: normalize(heap),
+        }
+    }
+
+    /// Gets the memory of loaded module images, sorted by address.
+    pub fn module(&self) -> &[Range<usize>] {
+        &self.module
+    }
+
+    /// Gets the committed memory that is private to the process, sorted
+    /// by address.
+    pub fn heap(&self) -> &[Range<usize>] {
+        &self.heap
+    }
+
+    /// Classifies `value` by where it would point to.
+    pub fn classify(&self, value: u64) -> PointerKind {
+        let addr = match usize::try_from(value) {
+            Ok(0) => return PointerKind::Null,
+            Ok(addr) => addr,
+            Err(_) => return PointerKind::Other,
+        };
+
+        if contains(&self.module, addr) {
+            PointerKind::Module
+        } else if contains(&self.heap, addr) {
+            PointerKind::Heap
+        } else {
+            PointerKind::Other
+        }
+    }
+
+    /// Queries the ranges of the current process.
+    ///
+    /// The ranges are a snapshot and should be refreshed from time to
+    /// time, as the heaps grow.
+    #[cfg(windows)]
+    pub fn current() -> Self {
+        let (mut module, mut heap) = (Vec::new(), Vec::new());
+        let mut addr: usize = 0;
+        loop {
+            let mut info = MEMORY_BASIC_INFORMATION::default();
+            let written = unsafe {
+                VirtualQuery(
+                    addr as *const _,
+                    &mut info,
+                    std::mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+                )
+            };
+            if written == 0 {
+                break;
+            }
+
+            let base = info.BaseAddress as usize;
+            let range = base..base + info.RegionSize;
+            if info.State == MEM_COMMIT {
+                if info.Type == MEM_IMAGE {
+                    module.push(range.clone());
+                } else if info.Type == MEM_PRIVATE {
+                    heap.push(range.clone());
+                }
+            }
+
+            if range.end <= addr {
+                break;
+            }
+            addr = range.end;
+        }
+
+        Self::new(module, heap)
+    }
+}
+
+/// How a single byte of a region behaved across all samples.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ByteKind {
+    /// No samples were observed yet.
+    Unseen,
+    /// The byte always had the same value.
+    Constant(u8),
+    /// The byte took at most [`ENUM_LIMIT`] distinct values, in order.
+    Enum(Vec<u8>),
+    /// The byte took more distinct values.
+    Varying,
+}
+
+impl fmt::Display for ByteKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unseen => f.write_str("unseen"),
+            Self::Constant(v) => write!(f, "const {:02x}", v),
+            Self::Enum(values) => {
+                f.write_str("enum")?;
+                values.iter().try_for_each(|v| write!(f, " {:02x}", v))
+            }
+            Self::Varying => f.write_str("varies"),
+        }
+    }
+}
+
+/// How often an 8-byte slot of a region held each [`PointerKind`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PointerCounts {
+    pub null: u64,
+    pub module: u64,
+    pub heap: u64,
+    pub other: u64,
+}
+
+impl PointerCounts {
+    /// Checks if the slot ever held a pointer into known memory.
+    pub fn is_pointer_like(&self) -> bool {
+        self.module + self.heap > 0
+    }
+}
+
+impl fmt::Display for PointerCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pointer: {} null, {} module, {} heap, {} other",
+            self.null, self.module, self.heap, self.other
+        )
+    }
+}
+
+/// Statistics on the contents of a region of memory across samples.
+///
+/// All offsets are relative to the start of the object that contains
+/// the region, so that they match up with hexdumps of the object and
+/// pointer slots are aligned like in memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionStats {
+    base: usize,
+    samples: u64,
+    // The distinct values of every byte, up to one more than the limit.
+    values: Vec<Vec<u8>>,
+    slots: Vec<PointerCounts>,
+}
+
+impl RegionStats {
+    /// Creates empty statistics for a region of `len` bytes at offset
+    /// `base` within its object.
+    pub fn new(base: usize, len: usize) -> Self {
+        let first_slot = Self::first_slot(base);
+        Self {
+            base,
+            samples: 0,
+            values: vec![Vec::new(); len],
+            slots: vec![PointerCounts::default(); len.saturating_sub(first_slot) / 8],
+        }
+    }
+
+    // Gets the index of the first byte of the region that is 8-byte aligned.
+    fn first_slot(base: usize) -> usize {
+        (8 - base % 8) % 8
+    }
+
+    /// Gets the offsets the region covers within its object.
+    pub fn range(&self) -> Range<usize> {
+        self.base..self.base + self.values.len()
+    }
+
+    /// Gets the number of samples observed.
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Adds a sample of the region's contents, classifying pointer-like
+    /// values using `ranges`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` does not match the length of the region.
+    pub fn observe(&mut self, bytes: &[u8], ranges: &AddressRanges) {
+        assert_eq!(
+            bytes.len(),
+            self.values.len(),
+            "sample has the wrong length"
+        );
+
+        self.samples += 1;
+        for (values, &byte) in self.values.iter_mut().zip(bytes) {
+            if values.len() <= ENUM_LIMIT {
+                if let Err(pos) = values.binary_search(&byte) {
+                    values.insert(pos, byte);
+                }
+            }
+        }
+
+        let aligned = &bytes[Self::first_slot(self.base).min(bytes.len())..];
+        for (counts, slot) in self.slots.iter_mut().zip(aligned.chunks_exact(8)) {
+            let value = u64::from_le_bytes(slot.try_into().unwrap());
+            match ranges.classify(value) {
+                PointerKind::Null => counts.null += 1,
+                PointerKind::Module => counts.module += 1,
+                PointerKind::Heap => counts.heap += 1,
+                PointerKind::Other => counts.other += 1,
+            }
+        }
+    }
+
+    /// Gets how the byte at `offset` behaved.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is outside of the region.
+    pub fn byte(&self, offset: usize) -> ByteKind {
+        assert!(self.range().contains(&offset), "offset is out of bounds");
+
+        match self.values[offset - self.base].as_slice() {
+            [] => ByteKind::Unseen,
+            [value] => ByteKind::Constant(*value),
+            values if values.len() <= ENUM_LIMIT => ByteKind::Enum(values.to_vec()),
+            _ => ByteKind::Varying,
+        }
+    }
+
+    /// Gets the pointer classification of the 8-byte aligned slot at
+    /// `offset`, if it lies within the region.
+    pub fn pointers(&self, offset: usize) -> Option<PointerCounts> {
+        if offset % 8 != 0 || offset < self.base {
+            return None;
+        }
+        let index = (offset - self.base).checked_sub(Self::first_slot(self.base))? / 8;
+        self.slots.get(index).copied()
+    }
+}
+
+impl fmt::Display for RegionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:#x}..{:#x}, {} samples",
+            self.range().start,
+            self.range().end,
+            self.samples
+        )?;
+        for offset in self.range() {
+            if let Some(counts) = self.pointers(offset).filter(PointerCounts::is_pointer_like) {
+                writeln!(f, "  {:#06x}  {}", offset, counts)?;
+            }
+            writeln!(f, "  {:#06x}  {}", offset, self.byte(offset))?;
+        }
+        Ok(())
+    }
+}
+
+/// [`RegionStats`] that are also grouped by a key, e.g. a type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Correlated<K> {
+    overall: RegionStats,
+    by_key: BTreeMap<K, RegionStats>,
+}
+
+impl<K: Ord> Correlated<K> {
+    /// Creates empty statistics for a region of `len` bytes at offset
+    /// `base` within its object.
+    pub fn new(base: usize, len: usize) -> Self {
+        Self {
+            overall: RegionStats::new(base, len),
+            by_key: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a sample of the region's contents for `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` does not match the length of the region.
+    pub fn observe(&mut self, key: K, bytes: &[u8], ranges: &AddressRanges) {
+        self.overall.observe(bytes, ranges);
+        self.by_key
+            .entry(key)
+            .or_insert_with(|| RegionStats::new(self.overall.base, bytes.len()))
+            .observe(bytes, ranges);
+    }
+
+    /// Gets the statistics across all samples.
+    pub fn overall(&self) -> &RegionStats {
+        &self.overall
+    }
+
+    /// Gets the statistics of the samples for each key.
+    pub fn by_key(&self) -> &BTreeMap<K, RegionStats> {
+        &self.by_key
+    }
+}
+
+/// Shows the overall statistics, followed by the bytes that vary overall
+/// but are constant for each key.
+///
+/// The latter are only shown for at least two keys, as a single key
+/// cannot explain any variation.
+impl<K: fmt::Display> fmt::Display for Correlated<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.overall.fmt(f)?;
+        if self.by_key.len() < 2 {
+            return Ok(());
+        }
+
+        for offset in self.overall.range() {
+            if matches!(self.overall.byte(offset), ByteKind::Constant(_)) {
+                continue;
+            }
+
+            let mut keyed = self
+                .by_key
+                .iter()
+                .map(|(key, stats)| (key, stats.byte(offset)));
+            if keyed
+                .clone()
+                .all(|(_, kind)| matches!(kind, ByteKind::Constant(_)))
+            {
+                write!(f, "  {:#06x}  determined by key:", offset)?;
+                keyed.try_for_each(|(key, kind)| write!(f, " {}={}", key, kind))?;
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A survey of the unknown regions of DML [`Record`]s and their fields.
+///
+/// Field regions are correlated with the type of the field.
+#[derive(Clone, Debug)]
+pub struct DmlSurvey {
+    ranges: AddressRanges,
+    records: BTreeMap<&'static str, RegionStats>,
+    fields: BTreeMap<&'static str, Correlated<TypeId>>,
+}
+
+impl DmlSurvey {
+    /// Creates a new survey classifying pointers using `ranges`.
+    pub fn new(ranges: AddressRanges) -> Self {
+        Self {
+            ranges,
+            records: BTreeMap::new(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// Replaces the ranges used to classify pointers.
+    pub fn set_ranges(&mut self, ranges: AddressRanges) {
+        self.ranges = ranges;
+    }
+
+    /// Adds the unknown regions of `record` and all its fields as
+    /// samples.
+    ///
+    /// # Safety
+    ///
+    /// `record` must be a valid record obtained from the client.
+    pub unsafe fn observe(&mut self, record: &Record) {
+        for (name, base, bytes) in record.unknown_regions() {
+            self.records
+                .entry(name)
+                .or_insert_with(|| RegionStats::new(base, bytes.len()))
+                .observe(bytes, &self.ranges);
+        }
+
+        for field in unsafe { record.fields() } {
+            for (name, base, bytes) in field.unknown_regions() {
+                self.fields
+                    .entry(name)
+                    .or_insert_with(|| Correlated::new(base, bytes.len()))
+                    .observe(field.type_id(), bytes, &self.ranges);
+            }
+        }
+    }
+
+    /// Gets the statistics of the unknown record region `name`, e.g. `_08`.
+    pub fn record_region(&self, name: &str) -> Option<&RegionStats> {
+        self.records.get(name)
+    }
+
+    /// Gets the statistics of the unknown field region `name`, e.g. `_38`.
+    pub fn field_region(&self, name: &str) -> Option<&Correlated<TypeId>> {
+        self.fields.get(name)
+    }
+}
+
+impl fmt::Display for DmlSurvey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, stats) in &self.records {
+            write!(f, "Record::{}: {}", name, stats)?;
+        }
+        for (name, stats) in &self.fields {
+            write!(f, "Field::{}: {}", name, stats)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,474 @@
+//! Offline replay of captured events against the registered handlers.
+//!
+//! Events and the DML records passed to them can be captured from the
+//! live client into a file using a [`Writer`]. A [`Reader`] loads them
+//! back, and [`replay`] rebuilds ABI-compatible [`Record`] memory from
+//! each event before calling the matching [`oleaf_hook::event`] handler
+//! directly.
+//!
+//! While an event is being replayed, `call_original!` within handlers
+//! is routed to a stub that does not call into the client and returns
+//! a zeroed value. This makes it possible to test handler logic on any
+//! platform, without the game running.
+//!
+//! [`oleaf_hook::event`]: macro@crate::event
+
+use std::{
+    cell::Cell,
+    io::{self, Read, Write},
+    os::raw::c_void,
+    ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::dml::{FieldValue, Record, RecordBuf, RecordBuilder, TypeId};
+
+/// The magic bytes at the start of every capture file.
+pub const MAGIC: &[u8; 4] = b"OLRP";
+
+/// The version of the capture format.
+pub const VERSION: u8 = 1;
+
+/// The value of a captured DML field.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Gid(u64),
+    Int(i32),
+    UInt(u32),
+    Flt(f32),
+    Byt(i8),
+    UByt(u8),
+    UShrt(u16),
+    Dbl(f64),
+    Str(Vec<u8>),
+    WStr(Vec<u16>),
+}
+
+impl Value {
+    /// Gets the type of the field the value was captured from.
+    pub fn type_id(&self) -> TypeId {
+        match self {
+            Value::Gid(_) => TypeId::Gid,
+            Value::Int(_) => TypeId::Int,
+            Value::UInt(_) => TypeId::UInt,
+            Value::Flt(_) => TypeId::Flt,
+            Value::Byt(_) => TypeId::Byt,
+            Value::UByt(_) => TypeId::UByt,
+            Value::UShrt(_) => TypeId::UShrt,
+            Value::Dbl(_) => TypeId::Dbl,
+            Value::Str(_) => TypeId::Str,
+            Value::WStr(_) => TypeId::WStr,
+        }
+    }
+}
+
+/// A captured DML field with its raw name and value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CapturedField {
+    pub name: Vec<u8>,
+    pub value: Value,
+}
+
+/// A captured call to an event handler.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    /// The name of the event.
+    pub name: String,
+    /// The fields of the DML record passed to the handler, if any.
+    pub record: Option<Vec<CapturedField>>,
+}
+
+impl Event {
+    /// Captures the event `name` together with a copy of the DML `record`
+    /// that was passed to its handler.
+    ///
+    /// Fields with a type that cannot be determined are skipped.
+    ///
+    /// # Safety
+    ///
+    /// `record` must be a valid record obtained from the client.
+    pub unsafe fn capture(name: &str, record: &Record) -> Self {
+        let fields = unsafe { record.fields() }
+            .iter()
+            .filter_map(|field| unsafe {
+                let value = match field.value().ok()? {
+                    FieldValue::Gid(v) => Value::Gid(v),
+                    FieldValue::Int(v) => Value::Int(v),
+                    FieldValue::UInt(v) => Value::UInt(v),
+                    FieldValue::Flt(v) => Value::Flt(v),
+                    FieldValue::Byt(v) => Value::Byt(v),
+                    FieldValue::UByt(v) => Value::UByt(v),
+                    FieldValue::UShrt(v) => Value::UShrt(v),
+                    FieldValue::Dbl(v) => Value::Dbl(v),
+                    FieldValue::Str(v) => Value::Str(v.as_bytes().to_vec()),
+                    FieldValue::WStr(v) => Value::WStr(v.as_utf16().to_vec()),
+                    FieldValue::Blob(_) | FieldValue::Long(_) | FieldValue::ULong(_) => {
+                        return None
+                    }
+                };
+
+                Some(CapturedField {
+                    name: field.name().as_bytes().to_vec(),
+                    value,
+                })
+            })
+            .collect();
+
+        Self {
+            name: name.to_string(),
+            record: Some(fields),
+        }
+    }
+}
+
+/// Writes captured [`Event`]s in the binary capture format.
+///
+/// The format starts with [`MAGIC`] and a [`VERSION`] byte, followed by
+/// the encoded events. All integers are stored in little-endian byte order.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a new writer and emits the capture header to `inner`.
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        inner.write_all(MAGIC)?;
+        inner.write_all(&[VERSION])?;
+
+        Ok(Self { inner })
+    }
+
+    /// Writes a single event.
+    pub fn write(&mut self, event: &Event) -> io::Result<()> {
+        write_bytes(&mut self.inner, event.name.as_bytes())?;
+
+        match &event.record {
+            Some(fields) => {
+                self.inner.write_all(&[1])?;
+                self.inner.write_all(&(fields.len() as u32).to_le_bytes())?;
+                for field in fields {
+                    write_field(&mut self.inner, field)?;
+                }
+            }
+            None => self.inner.write_all(&[0])?,
+        }
+
+        Ok(())
+    }
+
+    /// Consumes the writer and returns the underlying output.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+fn write_bytes<W: Write>(w: &mut W, data: &[u8]) -> io::Result<()> {
+    w.write_all(&(data.len() as u32).to_le_bytes())?;
+    w.write_all(data)
+}
+
+fn write_field<W: Write>(w: &mut W, field: &CapturedField) -> io::Result<()> {
+    write_bytes(w, &field.name)?;
+    // Captured values only have types with known raw IDs.
+    let raw = field.value.type_id().to_raw().unwrap();
+    w.write_all(&[raw])?;
+
+    match &field.value {
+        Value::Gid(v) => w.write_all(&v.to_le_bytes()),
+        Value::Int(v) => w.write_all(&v.to_le_bytes()),
+        Value::UInt(v) => w.write_all(&v.to_le_bytes()),
+        Value::Flt(v) => w.write_all(&v.to_le_bytes()),
+        Value::Byt(v) => w.write_all(&v.to_le_bytes()),
+        Value::UByt(v) => w.write_all(&v.to_le_bytes()),
+        Value::UShrt(v) => w.write_all(&v.to_le_bytes()),
+        Value::Dbl(v) => w.write_all(&v.to_le_bytes()),
+        Value::Str(v) => write_bytes(w, v),
+        Value::WStr(v) => {
+            w.write_all(&(v.len() as u32).to_le_bytes())?;
+            v.iter()
+                .try_for_each(|unit| w.write_all(&unit.to_le_bytes()))
+        }
+    }
+}
+
+/// Reads captured [`Event`]s from the binary capture format.
+///
+/// See [`Writer`] for details on the format.
+#[derive(Debug)]
+pub struct Reader<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> Reader<R> {
+    /// Creates a new reader and validates the capture header from `inner`.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut header = [0; 5];
+        inner.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(invalid_data("not a supported event capture"));
+        }
+
+        Ok(Self { inner })
+    }
+
+    /// Reads the next event, or `None` when the end of the capture
+    /// has been reached.
+    pub fn read(&mut self) -> io::Result<Option<Event>> {
+        let name = match read_bytes(&mut self.inner) {
+            Ok(name) => String::from_utf8(name).map_err(|_| invalid_data("invalid event name"))?,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let record = match read_array::<_, 1>(&mut self.inner)?[0] {
+            0 => None,
+            1 => {
+                let count = u32::from_le_bytes(read_array(&mut self.inner)?);
+                let fields = (0..count)
+                    .map(|_| read_field(&mut self.inner))
+                    .collect::<io::Result<_>>()?;
+                Some(fields)
+            }
+            _ => return Err(invalid_data("invalid record marker")),
+        };
+
+        Ok(Some(Event { name, record }))
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_array<R: Read, const N: usize>(r: &mut R) -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_bytes<R: Read>(r: &mut R) -> io::Result<Vec<u8>> {
+    let len = u32::from_le_bytes(read_array(r)?) as usize;
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(buf)
+}
+
+fn read_field<R: Read>(r: &mut R) -> io::Result<CapturedField> {
+    let name = read_bytes(r)?;
+    let value = match TypeId::from_raw(read_array::<_, 1>(r)?[0]) {
+        TypeId::Gid => Value::Gid(u64::from_le_bytes(read_array(r)?)),
+        TypeId::Int => Value::Int(i32::from_le_bytes(read_array(r)?)),
+        TypeId::UInt => Value::UInt(u32::from_le_bytes(read_array(r)?)),
+        TypeId::Flt => Value::Flt(f32::from_le_bytes(read_array(r)?)),
+        TypeId::Byt => Value::Byt(i8::from_le_bytes(read_array(r)?)),
+        TypeId::UByt => Value::UByt(u8::from_le_bytes(read_array(r)?)),
+        TypeId::UShrt => Value::UShrt(u16::from_le_bytes(read_array(r)?)),
+        TypeId::Dbl => Value::Dbl(f64::from_le_bytes(read_array(r)?)),
+        TypeId::Str => Value::Str(read_bytes(r)?),
+        TypeId::WStr => {
+            let len = u32::from_le_bytes(read_array(r)?);
+            let units = (0..len)
+                .map(|_| Ok(u16::from_le_bytes(read_array(r)?)))
+                .collect::<io::Result<_>>()?;
+            Value::WStr(units)
+        }
+        TypeId::Blob | TypeId::Long | TypeId::ULong | TypeId::Unknown(_) => {
+            return Err(invalid_data("unknown field type"))
+        }
+    };
+
+    Ok(CapturedField { name, value })
+}
+
+/// A DML [`Record`] rebuilt in Rust-owned memory from captured fields.
+///
+/// The memory layout is fully compatible with records created by the
+/// client, except that the record has no vtable.
+pub struct ReplayRecord {
+    inner: RecordBuf,
+}
+
+impl ReplayRecord {
+    /// Rebuilds a record from the given captured fields.
+    ///
+    /// This function will error if a field name contains interior null
+    /// bytes, see [`RecordBuilder::build`].
+    pub fn new(captured: &[CapturedField]) -> io::Result<Self> {
+        let builder = captured
+            .iter()
+            .fold(RecordBuilder::new(), |builder, field| {
+                let name = field.name.clone();
+                match &field.value {
+                    Value::Gid(v) => builder.gid(name, *v),
+                    Value::Int(v) => builder.int(name, *v),
+                    Value::UInt(v) => builder.uint(name, *v),
+                    Value::Flt(v) => builder.flt(name, *v),
+                    Value::Byt(v) => builder.byt(name, *v),
+                    Value::UByt(v) => builder.ubyt(name, *v),
+                    Value::UShrt(v) => builder.ushrt(name, *v),
+                    Value::Dbl(v) => builder.dbl(name, *v),
+                    Value::Str(v) => builder.str(name, v.clone()),
+                    Value::WStr(v) => builder.wstr_units(name, v.clone()),
+                }
+            });
+
+        Ok(Self {
+            inner: builder.build()?,
+        })
+    }
+
+    /// Gets a raw pointer to the record for passing it to handlers.
+    pub fn as_mut_ptr(&mut self) -> *mut Record {
+        self.inner.as_mut_ptr()
+    }
+}
+
+// Not part of the public API. Used by generated code.
+#[doc(hidden)]
+pub struct Handler {
+    pub name: &'static str,
+    pub invoke: unsafe fn(*mut c_void, *mut Record),
+}
+
+// Not part of the public API. Used by generated code.
+#[doc(hidden)]
+#[linkme::distributed_slice]
+pub static REPLAY_HANDLERS: [Handler] = [..];
+
+// The number of threads that are currently replaying an event. While it
+// is zero, `is_active` never needs to look at the thread-local flag.
+static REPLAYING: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static ACTIVE: Cell<bool> = Cell::new(false);
+    static ORIGINAL_CALLS: Cell<usize> = Cell::new(0);
+}
+
+struct ActiveGuard;
+
+impl ActiveGuard {
+    fn new() -> Self {
+        REPLAYING.fetch_add(1, Ordering::SeqCst);
+        ACTIVE.with(|active| active.set(true));
+        ORIGINAL_CALLS.with(|calls| calls.set(0));
+        Self
+    }
+}
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        ACTIVE.with(|active| active.set(false));
+        REPLAYING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Checks if the current thread is replaying an event.
+#[inline]
+pub fn is_active() -> bool {
+    REPLAYING.load(Ordering::Relaxed) != 0 && ACTIVE.with(|active| active.get())
+}
+
+// Not part of the public API. The return types that
+// `call_original_stub` can make up a value for.
+#[doc(hidden)]
+pub trait StubReturn {
+    fn stub() -> Self;
+}
+
+impl StubReturn for () {
+    fn stub() -> Self {}
+}
+
+impl<T> StubReturn for *mut T {
+    fn stub() -> Self {
+        ptr::null_mut()
+    }
+}
+
+impl<T> StubReturn for *const T {
+    fn stub() -> Self {
+        ptr::null()
+    }
+}
+
+macro_rules! impl_stub_return {
+    ($($ty:ty),*) => {
+        $(
+            impl StubReturn for $ty {
+                fn stub() -> Self {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+impl_stub_return!(i8, u8, i16, u16, i32, u32, i64, u64, isize, usize);
+
+// Not part of the public API. Used by generated code in place of
+// the original function while replaying.
+#[doc(hidden)]
+pub unsafe fn call_original_stub<A, R: StubReturn>(_args: A) -> R {
+    ORIGINAL_CALLS.with(|calls| calls.set(calls.get() + 1));
+    R::stub()
+}
+
+/// The result of replaying a single [`Event`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    /// Whether a handler was registered for the event.
+    pub handled: bool,
+    /// How often the handler invoked `call_original!`.
+    pub original_calls: usize,
+}
+
+/// Replays a single captured event by calling its registered handler.
+///
+/// The handler receives a null pointer for its `this` argument and a
+/// pointer to the rebuilt [`ReplayRecord`], or null if no record was
+/// captured.
+///
+/// Only handlers taking exactly a `this` pointer and a record pointer
+/// can be replayed.
+///
+/// # Safety
+///
+/// Handlers will run with rebuilt records as if they were called by
+/// the client. They must not rely on the `this` object or on parts of
+/// the record that are not captured, such as its vtable.
+pub unsafe fn replay(event: &Event) -> io::Result<Outcome> {
+    let handler = match REPLAY_HANDLERS.iter().find(|h| h.name == event.name) {
+        Some(handler) => handler,
+        None => {
+            return Ok(Outcome {
+                handled: false,
+                original_calls: 0,
+            })
+        }
+    };
+
+    let mut record = event.record.as_deref().map(ReplayRecord::new).transpose()?;
+    let record_ptr = record
+        .as_mut()
+        .map_or(ptr::null_mut(), ReplayRecord::as_mut_ptr);
+
+    let _guard = ActiveGuard::new();
+    unsafe { (handler.invoke)(ptr::null_mut(), record_ptr) };
+
+    Ok(Outcome {
+        handled: true,
+        original_calls: ORIGINAL_CALLS.with(Cell::get),
+    })
+}
@@ -0,0 +1,339 @@
+//! Opt-in recording of every event that passes through [`SendEventHook`].
+//!
+//! Once started, the recorder logs each call to [`send_event_detour`]
+//! into a trace file on disk. Traces can be written either in a compact
+//! binary format or as JSON Lines and are rotated when they grow too large.
+//!
+//! [`SendEventHook`]: crate::event::SendEventHook
+//! [`send_event_detour`]: crate::event::send_event_detour
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    lazy::SyncLazy,
+    os::raw::c_void,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(windows)]
+use windows::Win32::System::Threading::GetCurrentThreadId;
+
+use crate::event;
+
+/// The magic bytes at the start of every binary trace file.
+pub const BINARY_MAGIC: &[u8; 4] = b"OLTR";
+
+/// The version of the binary trace format.
+pub const BINARY_VERSION: u8 = 1;
+
+/// The on-disk format of a trace file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// A compact binary format.
+    ///
+    /// Every file starts with [`BINARY_MAGIC`] followed by a single
+    /// [`BINARY_VERSION`] byte. Each entry is then laid out as:
+    ///
+    /// | Type  | Description                          |
+    /// |-------|--------------------------------------|
+    /// | `u64` | Microseconds since the UNIX epoch    |
+    /// | `u32` | Thread ID                            |
+    /// | `u64` | Dispatcher pointer                   |
+    /// | `u8`  | `1` if a Rust handler exists         |
+    /// | `u16` | Length of the event name in bytes    |
+    /// | `[u8]`| The event name                       |
+    ///
+    /// All integers are stored in little-endian byte order.
+    Binary,
+    /// One JSON object per line.
+    JsonLines,
+}
+
+/// Configuration for the trace recorder.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The path to the trace file that is currently being written.
+    ///
+    /// Rotated files get a numeric suffix appended to this path,
+    /// with `.1` being the most recent one.
+    pub path: PathBuf,
+    /// The format in which entries are written.
+    pub format: Format,
+    /// The size in bytes after which the trace file is rotated.
+    pub max_file_size: u64,
+    /// The number of rotated files to keep around in addition to
+    /// the current one.
+    pub max_files: usize,
+    /// The names of events to record.
+    ///
+    /// An empty filter records every event. Entries ending in `*` match
+    /// every event name that starts with the given prefix.
+    pub filter: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("oleaf.trace"),
+            format: Format::Binary,
+            max_file_size: 64 * 1024 * 1024,
+            max_files: 4,
+            filter: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Checks if events called `name` pass the [`Config::filter`].
+    pub fn accepts(&self, name: &[u8]) -> bool {
+        self.filter.is_empty()
+            || self
+                .filter
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => name.starts_with(prefix.as_bytes()),
+                    None => name == pattern.as_bytes(),
+                })
+    }
+}
+
+/// A single call to [`SendEventHook`] as captured by the recorder.
+///
+/// [`SendEventHook`]: crate::event::SendEventHook
+#[derive(Clone, Copy, Debug)]
+pub struct Entry<'a> {
+    /// The time of the call, relative to the UNIX epoch.
+    pub timestamp: Duration,
+    /// The ID of the thread that fired the event.
+    pub thread_id: u32,
+    /// The address of the dispatcher object.
+    pub dispatcher: usize,
+    /// The raw name of the event.
+    pub name: &'a [u8],
+    /// Whether a Rust handler exists for this event.
+    pub handled: bool,
+}
+
+impl<'a> Entry<'a> {
+    /// Writes this entry to `w` in the given `format`.
+    pub fn write_to<W: Write>(&self, w: &mut W, format: Format) -> io::Result<()> {
+        match format {
+            Format::Binary => {
+                let name = &self.name[..self.name.len().min(u16::MAX as usize)];
+
+                w.write_all(&(self.timestamp.as_micros() as u64).to_le_bytes())?;
+                w.write_all(&self.thread_id.to_le_bytes())?;
+                w.write_all(&(self.dispatcher as u64).to_le_bytes())?;
+                w.write_all(&[self.handled as u8])?;
+                w.write_all(&(name.len() as u16).to_le_bytes())?;
+                w.write_all(name)
+            }
+            Format::JsonLines => {
+                write!(
+                    w,
+                    "{{\"ts\":{},\"tid\":{},\"dispatcher\":\"{:#x}\",\"name\":\"",
+                    self.timestamp.as_micros(),
+                    self.thread_id,
+                    self.dispatcher
+                )?;
+                write_json_escaped(w, &String::from_utf8_lossy(self.name))?;
+                writeln!(w, "\",\"handled\":{}}}", self.handled)
+            }
+        }
+    }
+}
+
+fn write_json_escaped<W: Write>(w: &mut W, s: &str) -> io::Result<()> {
+    for c in s.chars() {
+        match c {
+            '"' => w.write_all(b"\\\"")?,
+            '\\' => w.write_all(b"\\\\")?,
+            '\n' => w.write_all(b"\\n")?,
+            '\r' => w.write_all(b"\\r")?,
+            '\t' => w.write_all(b"\\t")?,
+            c if (c as u32) < 0x20 => write!(w, "\\u{:04x}", c as u32)?,
+            c => write!(w, "{}", c)?,
+        }
+    }
+    Ok(())
+}
+
+/// A writer for trace files which takes care of their rotation.
+///
+/// The recorder behind [`start`] and [`stop`] uses this internally,
+/// but it can also be used to produce trace files by hand.
+pub struct Recorder {
+    config: Config,
+    file: BufWriter<File>,
+    written: u64,
+    entries: u64,
+}
+
+impl Recorder {
+    /// Creates the trace file at [`Config::path`] and writes the header
+    /// of the configured format to it.
+    pub fn open(config: Config) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(&config.path)?);
+        let written = write_header(&mut file, config.format)?;
+
+        Ok(Self {
+            config,
+            file,
+            written,
+            entries: 0,
+        })
+    }
+
+    /// Writes `entry` to the trace file, rotating it first when the
+    /// entry would exceed [`Config::max_file_size`].
+    ///
+    /// This does not consult [`Config::filter`].
+    pub fn write(&mut self, entry: &Entry<'_>) -> io::Result<()> {
+        let mut buf = Vec::with_capacity(64);
+        entry.write_to(&mut buf, self.config.format)?;
+
+        // Every file holds at least one entry, no matter how small the limit.
+        if self.entries > 0 && self.written + buf.len() as u64 > self.config.max_file_size {
+            self.rotate()?;
+        }
+
+        self.file.write_all(&buf)?;
+        self.written += buf.len() as u64;
+        self.entries += 1;
+
+        Ok(())
+    }
+
+    /// Flushes all buffered entries to disk.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let path = &self.config.path;
+        if self.config.max_files == 0 {
+            fs::remove_file(path)?;
+        } else {
+            // Shift every rotated file one slot back, dropping the oldest one.
+            let _ = fs::remove_file(rotated_path(path, self.config.max_files));
+            for n in (1..self.config.max_files).rev() {
+                let from = rotated_path(path, n);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(path, n + 1))?;
+                }
+            }
+            fs::rename(path, rotated_path(path, 1))?;
+        }
+
+        self.file = BufWriter::new(File::create(path)?);
+        self.written = write_header(&mut self.file, self.config.format)?;
+        self.entries = 0;
+
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+fn write_header<W: Write>(w: &mut W, format: Format) -> io::Result<u64> {
+    match format {
+        Format::Binary => {
+            w.write_all(BINARY_MAGIC)?;
+            w.write_all(&[BINARY_VERSION])?;
+            Ok(BINARY_MAGIC.len() as u64 + 1)
+        }
+        Format::JsonLines => Ok(0),
+    }
+}
+
+// Checked before taking the lock so that a disabled recorder costs
+// nothing more than an atomic load on the game thread.
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+static RECORDER: SyncLazy<Mutex<Option<Recorder>>> = SyncLazy::new(|| Mutex::new(None));
+
+/// Starts recording events with the given configuration.
+///
+/// A recorder that is already running will be flushed and replaced.
+pub fn start(config: Config) -> io::Result<()> {
+    let recorder = Recorder::open(config)?;
+
+    let mut guard = RECORDER.lock().unwrap();
+    if let Some(mut previous) = guard.replace(recorder) {
+        previous.flush()?;
+    }
+    RECORDING.store(true, Ordering::Release);
+
+    Ok(())
+}
+
+/// Stops recording events and flushes all pending entries to disk.
+///
+/// This is a no-op when no recorder is running.
+pub fn stop() -> io::Result<()> {
+    RECORDING.store(false, Ordering::Release);
+
+    match RECORDER.lock().unwrap().take() {
+        Some(mut recorder) => recorder.flush(),
+        None => Ok(()),
+    }
+}
+
+/// Checks if a recorder is currently running.
+#[inline]
+pub fn is_recording() -> bool {
+    RECORDING.load(Ordering::Acquire)
+}
+
+#[cfg(windows)]
+fn current_thread_id() -> u32 {
+    unsafe { GetCurrentThreadId() }
+}
+
+// There is no numeric thread ID we could get hold of on other platforms.
+#[cfg(not(windows))]
+fn current_thread_id() -> u32 {
+    0
+}
+
+// Records a single call of the event `name` fired by `dispatcher`.
+//
+// Errors cannot be propagated back into C++ code, so the recorder is
+// shut down instead when writing to the trace fails.
+pub(crate) fn record(dispatcher: *mut c_void, name: &[u8]) {
+    let mut guard = match RECORDER.lock() {
+        Ok(guard) => guard,
+        Err(_) => return,
+    };
+    let recorder = match guard.as_mut() {
+        Some(recorder) if recorder.config.accepts(name) => recorder,
+        _ => return,
+    };
+
+    let entry = Entry {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default(),
+        thread_id: current_thread_id(),
+        dispatcher: dispatcher as usize,
+        name,
+        handled: event::has_handler(name),
+    };
+
+    if let Err(e) = recorder.write(&entry) {
+        println!("Failed to record event trace, stopping recorder: {}", e);
+        RECORDING.store(false, Ordering::Release);
+        *guard = None;
+    }
+}
@@ -0,0 +1,113 @@
+//! Orderly shutdown of all hooks so the library can be unloaded at runtime.
+//!
+//! Before the library may be freed, no thread must be executing any of
+//! its code. Every detour therefore counts itself as in flight for the
+//! duration of a call, and [`shutdown`] waits for that count to drop
+//! to zero after all hooks were disabled.
+
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{event, metrics, trace};
+
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static SHUT_DOWN: AtomicBool = AtomicBool::new(false);
+
+// The time we give threads which already jumped into a detour but did not
+// yet register themselves as in flight.
+const GRACE_PERIOD: Duration = Duration::from_millis(50);
+
+// Not part of the public API. Used by generated code.
+//
+// Marks the current thread as executing a detour until dropped.
+#[doc(hidden)]
+pub struct InFlight(());
+
+impl InFlight {
+    #[inline]
+    pub fn enter() -> Self {
+        IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+        Self(())
+    }
+}
+
+impl Drop for InFlight {
+    #[inline]
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Gets the number of threads that are currently executing a detour.
+pub fn in_flight() -> usize {
+    IN_FLIGHT.load(Ordering::SeqCst)
+}
+
+/// Signals that the library should be unloaded.
+///
+/// This does not do anything by itself. Worker threads are expected to
+/// check [`is_requested`] and to start the [`shutdown`] sequence.
+pub fn request() {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Checks if unloading the library was requested.
+pub fn is_requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Checks if [`shutdown`] was already performed.
+pub fn is_shut_down() -> bool {
+    SHUT_DOWN.load(Ordering::SeqCst)
+}
+
+/// Shuts down all hooks and background work of this crate.
+///
+/// This stops the [`trace`] recorder and the periodic [`metrics`] dump,
+/// disables all event detours as well as [`SendEventHook`] to revert
+/// their patches and then waits up to `timeout` for all threads to
+/// leave the detours.
+///
+/// Returns `true` when no thread is executing a detour anymore, after
+/// which it is safe to unload the library. Subsequent calls only wait
+/// for in-flight detours again.
+///
+/// This must not be called from a detour, which would wait for itself,
+/// nor from `DllMain` where joining threads would deadlock.
+///
+/// # Safety
+///
+/// C++ land. See [`event::unhook_all`].
+///
+/// [`SendEventHook`]: crate::event::SendEventHook
+pub unsafe fn shutdown(timeout: Duration) -> bool {
+    if !SHUT_DOWN.swap(true, Ordering::SeqCst) {
+        request();
+
+        if let Err(e) = trace::stop() {
+            println!("Failed to flush event trace: {}", e);
+        }
+        metrics::stop_periodic_dump();
+
+        unsafe { event::unhook_all() };
+    }
+
+    let start = Instant::now();
+    loop {
+        if in_flight() == 0 {
+            thread::sleep(GRACE_PERIOD);
+            if in_flight() == 0 {
+                return true;
+            }
+        }
+
+        if start.elapsed() >= timeout {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,130 @@
+//! Containment of panics in hook code.
+//!
+//! Unwinding from a Rust panic into the MSVC C++ frames of the client is
+//! undefined behavior. Therefore every detour generated by the
+//! [`oleaf_hook::event`] macro, as well as [`send_event_detour`], catch
+//! panics at the boundary. They report the payload, fall back to the
+//! original function and optionally disable the offending hook.
+//!
+//! [`oleaf_hook::event`]: macro@crate::event
+//! [`send_event_detour`]: crate::event::send_event_detour
+
+use std::{
+    any::Any,
+    cell::Cell,
+    panic::{self, AssertUnwindSafe},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::replay::StubReturn;
+
+static DISABLE_ON_PANIC: AtomicBool = AtomicBool::new(false);
+
+/// Configures whether event handler hooks should be disabled after
+/// they panicked once.
+///
+/// This is off by default, in which case the handler will keep being
+/// called for subsequent events.
+pub fn set_disable_on_panic(disable: bool) {
+    DISABLE_ON_PANIC.store(disable, Ordering::SeqCst);
+}
+
+/// Checks whether event handler hooks are disabled after they panicked.
+pub fn disable_on_panic() -> bool {
+    DISABLE_ON_PANIC.load(Ordering::SeqCst)
+}
+
+/// Extracts a printable message from a panic payload.
+pub fn payload_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(msg) = payload.downcast_ref::<&'static str>() {
+        msg
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+// Not part of the public API. Used by generated code.
+#[doc(hidden)]
+pub fn report(event: &str, payload: &(dyn Any + Send)) {
+    println!(
+        "Caught panic in hook for {}: {}",
+        event,
+        payload_message(payload)
+    );
+}
+
+thread_local! {
+    static ORIGINAL_CALLED: Cell<bool> = Cell::new(false);
+}
+
+// Not part of the public API. Used by generated code.
+//
+// Tracks whether the original function was called during a single hook
+// invocation so that it is not called twice when falling back after a
+// panic. Hooks may nest, so the state of the outer hook is restored
+// when the frame is dropped.
+#[doc(hidden)]
+pub struct Frame {
+    previous: bool,
+}
+
+impl Frame {
+    #[inline]
+    pub fn enter() -> Self {
+        Self {
+            previous: ORIGINAL_CALLED.with(|called| called.replace(false)),
+        }
+    }
+
+    #[inline]
+    pub fn original_called(&self) -> bool {
+        ORIGINAL_CALLED.with(Cell::get)
+    }
+}
+
+impl Drop for Frame {
+    #[inline]
+    fn drop(&mut self) {
+        ORIGINAL_CALLED.with(|called| called.set(self.previous));
+    }
+}
+
+// Not part of the public API. Used by generated code.
+#[doc(hidden)]
+#[inline]
+pub fn mark_original_called() {
+    ORIGINAL_CALLED.with(|called| called.set(true));
+}
+
+// Not part of the public API. Used by generated code.
+//
+// Runs a hook's `handler` and contains any panic it raises. After the
+// panic is reported and `disable` was called if so configured, the
+// `original` function runs in place of the handler. If the handler
+// already called it, a stub value is returned instead.
+#[doc(hidden)]
+pub fn contain<R: StubReturn>(
+    event: &str,
+    handler: impl FnOnce() -> R,
+    original: impl FnOnce() -> R,
+    disable: impl FnOnce(),
+) -> R {
+    let frame = Frame::enter();
+    match panic::catch_unwind(AssertUnwindSafe(handler)) {
+        Ok(ret) => ret,
+        Err(payload) => {
+            report(event, &*payload);
+            if disable_on_panic() {
+                disable();
+            }
+
+            if frame.original_called() {
+                R::stub()
+            } else {
+                original()
+            }
+        }
+    }
+}
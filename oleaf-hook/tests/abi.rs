@@ -0,0 +1,347 @@
+use std::{env, ffi::CString, fs, mem, path::PathBuf, ptr};
+
+use oleaf_hook::cxx::{self, Str, Vector, VectorBuf, WStr};
+
+// Golden images of `std::string`, `std::wstring` and `std::vector`
+// objects in MSVC x64 release builds, in the notation of
+// `Module::find_signature`. `??` marks bytes which hold pointers or
+// which MSVC leaves unspecified, like the SSO buffer past the null
+// terminator.
+//
+// The images follow the layouts in the MSVC STL's `<xstring>` and
+// `<vector>`. `fixtures/abi/dump_abi.cpp` dumps the same objects from a
+// real MSVC build into `fixtures/abi/msvc_x64.txt`, which `msvc_dump`
+// checks them against. That dump has yet to be generated on Windows.
+
+const TEXT: &str = "abcdefghijklmnopqrstuvwxyz0123456789";
+
+const STRINGS: &[(usize, &str)] = &[
+    (
+        0,
+        "00 ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? \
+         00 00 00 00 00 00 00 00 0f 00 00 00 00 00 00 00",
+    ),
+    (
+        1,
+        "61 00 ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? \
+         01 00 00 00 00 00 00 00 0f 00 00 00 00 00 00 00",
+    ),
+    (
+        15,
+        "61 62 63 64 65 66 67 68 69 6a 6b 6c 6d 6e 6f 00 \
+         0f 00 00 00 00 00 00 00 0f 00 00 00 00 00 00 00",
+    ),
+    (
+        16,
+        "?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? \
+         10 00 00 00 00 00 00 00 1f 00 00 00 00 00 00 00",
+    ),
+    (
+        32,
+        "?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? \
+         20 00 00 00 00 00 00 00 2f 00 00 00 00 00 00 00",
+    ),
+];
+
+const WSTRINGS: &[(usize, &str)] = &[
+    (
+        0,
+        "00 00 ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? \
+         00 00 00 00 00 00 00 00 07 00 00 00 00 00 00 00",
+    ),
+    (
+        1,
+        "61 00 00 00 ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? \
+         01 00 00 00 00 00 00 00 07 00 00 00 00 00 00 00",
+    ),
+    (
+        7,
+        "61 00 62 00 63 00 64 00 65 00 66 00 67 00 00 00 \
+         07 00 00 00 00 00 00 00 07 00 00 00 00 00 00 00",
+    ),
+    (
+        8,
+        "?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? \
+         08 00 00 00 00 00 00 00 0f 00 00 00 00 00 00 00",
+    ),
+    (
+        16,
+        "?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? \
+         10 00 00 00 00 00 00 00 17 00 00 00 00 00 00 00",
+    ),
+];
+
+// `std::vector<int>{}`.
+const VECTOR_EMPTY: &str = "00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 \
+                            00 00 00 00 00 00 00 00";
+
+// `std::vector<int>{1, 2, 3}`, holding three pointers.
+const VECTOR_3: &str = "?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? \
+                        ?? ?? ?? ?? ?? ?? ?? ??";
+
+// Strings with a capacity of at least this are stored on the heap.
+const STRING_HEAP: usize = 16;
+const WSTRING_HEAP: usize = 8;
+
+fn parse(image: &str) -> Vec<Option<u8>> {
+    image
+        .split_whitespace()
+        .map(|byte| u8::from_str_radix(byte, 16).ok())
+        .collect()
+}
+
+fn bytes_of<T>(object: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(object as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+fn assert_image<T>(object: &T, image: &str) {
+    let image = parse(image);
+    let bytes = bytes_of(object);
+    assert_eq!(bytes.len(), image.len());
+
+    for (offset, (&actual, expected)) in bytes.iter().zip(image).enumerate() {
+        if let Some(expected) = expected {
+            assert_eq!(actual, expected, "byte {:#x} differs", offset);
+        }
+    }
+}
+
+// Builds an object from `image`, filling unspecified bytes with garbage
+// and storing `data` as the pointer at the start of the object.
+fn from_image<T>(image: &str, data: Option<*const u8>) -> T {
+    let mut bytes: Vec<_> = parse(image)
+        .into_iter()
+        .map(|byte| byte.unwrap_or(0xCC))
+        .collect();
+    assert_eq!(bytes.len(), mem::size_of::<T>());
+
+    if let Some(data) = data {
+        bytes[..8].copy_from_slice(&(data as usize).to_le_bytes());
+    }
+    unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) }
+}
+
+fn read_word(bytes: &[u8], index: usize) -> usize {
+    usize::from_le_bytes(bytes[index * 8..index * 8 + 8].try_into().unwrap())
+}
+
+fn utf16_with_null(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(Some(0)).collect()
+}
+
+#[test]
+fn layouts() {
+    assert_eq!(mem::size_of::<cxx::String>(), 0x20);
+    assert_eq!(mem::size_of::<Str>(), 0x20);
+    assert_eq!(mem::size_of::<cxx::WString>(), 0x20);
+    assert_eq!(mem::size_of::<WStr>(), 0x20);
+    assert_eq!(mem::size_of::<Vector<i32>>(), 0x18);
+    assert_eq!(mem::size_of::<VectorBuf<i32>>(), 0x18);
+}
+
+#[test]
+fn string_matches_images() {
+    for &(len, image) in STRINGS {
+        let text = &TEXT[..len];
+        let mut string = unsafe { cxx::String::new(text) }.unwrap();
+
+        assert_image(&string, image);
+        assert_eq!(string.view().to_bytes(), text.as_bytes());
+    }
+}
+
+#[test]
+fn str_reads_images() {
+    for &(len, image) in STRINGS {
+        let text = &TEXT[..len];
+        let heap = CString::new(text).unwrap();
+        let data = (len >= STRING_HEAP).then(|| heap.as_ptr() as *const u8);
+        let mut string: Str = from_image(image, data);
+
+        assert_eq!(string.to_string(), text);
+        assert_eq!(unsafe { string.view() }.to_bytes(), text.as_bytes());
+        assert_eq!(string.as_bytes(), text.as_bytes());
+        assert_eq!(string.to_str(), Ok(text));
+        assert_eq!(string.len(), len);
+        assert!(string == text);
+    }
+}
+
+#[test]
+fn str_reads_interior_nulls() {
+    // The SSO image for `a\0b`, which `CStr` would cut off.
+    let string: Str = from_image(
+        "61 00 62 00 ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? \
+         03 00 00 00 00 00 00 00 0f 00 00 00 00 00 00 00",
+        None,
+    );
+
+    assert_eq!(string.as_bytes(), b"a\0b");
+    assert_eq!(string.capacity(), 15);
+    assert!(string == "a\0b");
+    assert!(string != "a");
+}
+
+#[test]
+fn wstring_matches_images() {
+    for &(len, image) in WSTRINGS {
+        let text = &TEXT[..len];
+        let string = unsafe { cxx::WString::new(text) }.unwrap();
+
+        assert_image(&string, image);
+        assert_eq!(string.decode_utf16(), text);
+
+        // Heap storage must be null-terminated, just like the buffer.
+        let bytes = bytes_of(&string);
+        if len >= WSTRING_HEAP {
+            let units =
+                unsafe { std::slice::from_raw_parts(read_word(bytes, 0) as *const u16, len + 1) };
+            assert_eq!(units, utf16_with_null(text));
+        }
+    }
+}
+
+#[test]
+fn wstr_reads_images() {
+    for &(len, image) in WSTRINGS {
+        let text = &TEXT[..len];
+        let heap = utf16_with_null(text);
+        let data = (len >= WSTRING_HEAP).then(|| heap.as_ptr() as *const u8);
+        let string: WStr = from_image(image, data);
+
+        assert_eq!(string.to_string(), text);
+        assert_eq!(unsafe { string.decode_utf16() }, text);
+        assert_eq!(string.as_utf16(), &heap[..len]);
+        assert_eq!(string.len(), len);
+        assert!(string == text);
+    }
+}
+
+#[test]
+fn vector_matches_images() {
+    let empty = VectorBuf::<i32>::new();
+    assert_image(&empty, VECTOR_EMPTY);
+    assert!(empty.as_vector().is_empty());
+
+    let vector = VectorBuf::from(vec![1i32, 2, 3]);
+    assert_image(&vector, VECTOR_3);
+
+    // The pointers delimit exactly the three elements, like for a vector
+    // constructed from an initializer list.
+    let bytes = bytes_of(&vector);
+    let first = read_word(bytes, 0);
+    assert_eq!(read_word(bytes, 1) - first, 12);
+    assert_eq!(read_word(bytes, 2) - first, 12);
+}
+
+#[test]
+fn vector_reads_images() {
+    let empty: Vector<i32> = from_image(VECTOR_EMPTY, None);
+    assert_eq!(empty.len(), 0);
+    assert_eq!(unsafe { empty.as_slice() }, &[] as &[i32]);
+
+    let mut storage = [1i32, 2, 3, 0];
+    let head = storage.as_mut_ptr();
+    let mut bytes = Vec::new();
+    for word in [head, unsafe { head.add(3) }, unsafe { head.add(4) }] {
+        bytes.extend_from_slice(&(word as usize).to_le_bytes());
+    }
+    let image: String = bytes.iter().map(|byte| format!("{:02x} ", byte)).collect();
+    assert_eq!(parse(&image).len(), parse(VECTOR_3).len());
+
+    let vector: Vector<i32> = from_image(&image, None);
+    assert_eq!(vector.len(), 3);
+    assert_eq!(vector.capacity(), 4);
+    assert_eq!(unsafe { vector.as_slice() }, &[1, 2, 3]);
+}
+
+#[test]
+fn string_grows_like_msvc() {
+    let mut string = cxx::String::default();
+    assert_image(&string, STRINGS[0].1);
+
+    // Appending one byte at a time moves to the heap at 16 bytes and
+    // then grows by half of the capacity, rounded to the MSVC mask.
+    let mut capacities = Vec::new();
+    for (i, c) in TEXT.chars().enumerate() {
+        string.push_str(c.encode_utf8(&mut [0; 4]));
+        if capacities.last() != Some(&string.capacity()) {
+            capacities.push(string.capacity());
+        }
+        assert_eq!(string.as_bytes(), TEXT[..=i].as_bytes());
+    }
+    assert_eq!(capacities, [15, 31, 47]);
+
+    // Clearing keeps the storage, shrinking moves back into the buffer.
+    string.clear();
+    assert!(string.is_empty());
+    assert_eq!(string.capacity(), 47);
+    string.push_str("a");
+    string.shrink_to_fit();
+    assert_image(&string, STRINGS[1].1);
+
+    let mut string = cxx::String::from(TEXT);
+    string.reserve(100);
+    assert!(string.capacity() >= TEXT.len() + 100);
+    string.shrink_to_fit();
+    assert_eq!(string.capacity(), TEXT.len() | 0xf);
+    assert_eq!(string.view().to_bytes(), TEXT.as_bytes());
+}
+
+#[test]
+fn string_keeps_interior_nulls() {
+    let mut string = cxx::String::from_bytes(b"a\0b");
+    assert_eq!(string.as_bytes(), b"a\0b");
+    assert_eq!(string.view().to_bytes(), b"a");
+
+    string.push_str(&TEXT[..16]);
+    assert_eq!(string.len(), 19);
+    assert_eq!(&string.as_bytes()[..3], b"a\0b");
+    assert!(unsafe { cxx::String::new("a\0b") }.is_err());
+}
+
+// Gets the reference image of the object dumped as `kind` and `key`.
+fn reference_image(kind: &str, key: &str) -> Option<&'static str> {
+    let find = |images: &[(usize, &'static str)]| {
+        let len: usize = key.parse().ok()?;
+        images
+            .iter()
+            .find(|&&(l, _)| l == len)
+            .map(|&(_, image)| image)
+    };
+
+    match (kind, key) {
+        ("string", _) => find(STRINGS),
+        ("wstring", _) => find(WSTRINGS),
+        ("vector", "empty") => Some(VECTOR_EMPTY),
+        ("vector", "3") => Some(VECTOR_3),
+        _ => None,
+    }
+}
+
+#[test]
+fn msvc_dump() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/abi/msvc_x64.txt");
+    let dump = match fs::read_to_string(&path) {
+        Ok(dump) => dump,
+        Err(_) => {
+            eprintln!("{} is missing, see dump_abi.cpp", path.display());
+            return;
+        }
+    };
+
+    let mut checked = 0;
+    for line in dump
+        .lines()
+        .filter(|l| !l.starts_with('#') && !l.is_empty())
+    {
+        let (name, image) = line.split_once(':').expect("malformed dump line");
+        let (kind, key) = name.split_once(' ').expect("malformed dump name");
+
+        let reference =
+            reference_image(kind, key).unwrap_or_else(|| panic!("no reference image for {}", name));
+        assert_eq!(parse(image), parse(reference), "{} differs", name);
+        checked += 1;
+    }
+    assert_eq!(checked, STRINGS.len() + WSTRINGS.len() + 2);
+}
@@ -0,0 +1,112 @@
+use oleaf_hook::dml::{FieldError, RecordBuf, RecordBuilder, TypeId};
+
+fn quest_dialog() -> RecordBuf {
+    RecordBuilder::new()
+        .uint("QuestID", 42)
+        .int("Delta", -3)
+        .gid("Mob", 0x1122_3344_5566_7788)
+        .dbl("Scale", 0.25)
+        .str("Speaker", b"Gamma\xff".to_vec())
+        .wstr("Title", "Über")
+        .uint("QuestID", 7)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn typed_getters() {
+    let buf = quest_dialog();
+    let record = buf.record();
+
+    unsafe {
+        assert_eq!(record.get_u32("QuestID"), Ok(42));
+        assert_eq!(record.get_i32("Delta"), Ok(-3));
+        assert_eq!(record.get_gid("Mob"), Ok(0x1122_3344_5566_7788));
+        assert_eq!(record.get_f64("Scale"), Ok(0.25));
+        assert_eq!(record.get_str("Speaker").unwrap().as_bytes(), b"Gamma\xff");
+        assert_eq!(record.get_wstr("Title").unwrap().decode_utf16(), "Über");
+    }
+}
+
+#[test]
+fn typed_getter_errors() {
+    let buf = quest_dialog();
+    let record = buf.record();
+
+    unsafe {
+        assert_eq!(
+            record.get_u32("GoalID"),
+            Err(FieldError::Missing {
+                name: "GoalID".to_string()
+            })
+        );
+        assert_eq!(
+            record.get_i32("QuestID"),
+            Err(FieldError::TypeMismatch {
+                name: "QuestID".to_string(),
+                expected: TypeId::Int,
+                found: TypeId::UInt,
+            })
+        );
+        assert!(matches!(
+            record.get_str("Title"),
+            Err(FieldError::TypeMismatch {
+                expected: TypeId::Str,
+                found: TypeId::WStr,
+                ..
+            })
+        ));
+    }
+}
+
+#[test]
+fn get_as_conversions() {
+    let buf = quest_dialog();
+    let record = buf.record();
+
+    unsafe {
+        assert_eq!(record.get_as::<u32>("QuestID"), Ok(42));
+        assert_eq!(record.get_as::<String>("Title").unwrap(), "Über");
+        assert_eq!(record.get_as::<String>("Speaker").unwrap(), "Gamma\u{fffd}");
+        assert_eq!(
+            record.get_as::<Vec<u8>>("Speaker").unwrap(),
+            b"Gamma\xff".to_vec()
+        );
+        assert_eq!(
+            record.get_as::<Vec<u8>>("Title"),
+            Err(FieldError::TypeMismatch {
+                name: "Title".to_string(),
+                expected: TypeId::Str,
+                found: TypeId::WStr,
+            })
+        );
+        assert!(matches!(
+            record.get_as::<f32>("Delta"),
+            Err(FieldError::TypeMismatch { .. })
+        ));
+    }
+}
+
+#[test]
+fn field_index_matches_linear_lookup() {
+    let buf = quest_dialog();
+    let record = buf.record();
+
+    unsafe {
+        let index = record.index();
+        for name in ["QuestID", "Delta", "Mob", "Scale", "Speaker", "Title"] {
+            let indexed = index.get(name).unwrap() as *const _;
+            let linear = record.get(name).unwrap() as *const _;
+            assert_eq!(indexed, linear, "{}", name);
+        }
+        assert!(index.get("GoalID").is_none());
+
+        // Duplicate names resolve to the first field.
+        assert_eq!(index.get_u32("QuestID"), Ok(42));
+        assert_eq!(index.get_i32("Delta"), Ok(-3));
+        assert!(matches!(
+            index.get_u8("Mob"),
+            Err(FieldError::TypeMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    alloc::Layout,
+    cell::{Cell, RefCell},
+    mem,
+    os::raw::c_void,
+    ptr,
+};
+
+use oleaf_hook::cxx::alloc::{Allocator, GameHeap};
+
+extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+}
+
+thread_local! {
+    // The sizes passed to `mock_malloc` and the blocks it returned.
+    static MALLOCS: RefCell<Vec<(usize, usize)>> = RefCell::new(Vec::new());
+    // The pointers passed to `mock_free`.
+    static FREES: RefCell<Vec<usize>> = RefCell::new(Vec::new());
+    static FAIL: Cell<bool> = Cell::new(false);
+}
+
+// Stand-ins for the client's `malloc` and `free` that record their calls.
+unsafe extern "C" fn mock_malloc(size: usize) -> *mut c_void {
+    let block = if FAIL.with(Cell::get) {
+        ptr::null_mut()
+    } else {
+        malloc(size)
+    };
+    MALLOCS.with(|m| m.borrow_mut().push((size, block as usize)));
+    block
+}
+
+unsafe extern "C" fn mock_free(ptr: *mut c_void) {
+    FREES.with(|f| f.borrow_mut().push(ptr as usize));
+    free(ptr)
+}
+
+// Tests may share a thread, so every heap starts with a clean record.
+fn heap() -> GameHeap {
+    MALLOCS.with(|m| m.borrow_mut().clear());
+    FREES.with(|f| f.borrow_mut().clear());
+    FAIL.with(|f| f.set(false));
+    unsafe { GameHeap::new(mock_malloc, mock_free) }
+}
+
+fn mallocs() -> Vec<(usize, usize)> {
+    MALLOCS.with(|m| m.borrow().clone())
+}
+
+fn frees() -> Vec<usize> {
+    FREES.with(|f| f.borrow().clone())
+}
+
+// The bytes MSVC's `std::allocator` reserves in front of big blocks:
+// the back pointer, a debug sentinel and the slack for aligning.
+const NON_USER_SIZE: usize = 2 * mem::size_of::<usize>() + 31;
+
+#[test]
+fn small_allocation() {
+    let heap = heap();
+    let layout = Layout::from_size_align(4095, 8).unwrap();
+
+    let ptr = heap.allocate(layout);
+    assert_eq!(mallocs(), [(4095, ptr as usize)]);
+
+    unsafe { heap.deallocate(ptr, layout) };
+    assert_eq!(frees(), [ptr as usize]);
+}
+
+#[test]
+fn big_allocation() {
+    let heap = heap();
+
+    for size in [4096, 4097, 0x10000] {
+        let layout = Layout::from_size_align(size, 16).unwrap();
+        let ptr = heap.allocate(layout) as usize;
+
+        let (requested, container) = *mallocs().last().unwrap();
+        assert_eq!(requested, size + NON_USER_SIZE);
+
+        // The user block is aligned to 32 bytes, fits into the container
+        // and leaves room for the bookkeeping in front of it.
+        assert_eq!(ptr % 32, 0);
+        assert!(ptr >= container + 2 * mem::size_of::<usize>());
+        assert!(ptr + size <= container + requested);
+        assert_eq!(unsafe { *(ptr as *const usize).sub(1) }, container);
+
+        // The block is usable in full.
+        unsafe { ptr::write_bytes(ptr as *mut u8, 0xcc, size) };
+
+        unsafe { heap.deallocate(ptr as *mut u8, layout) };
+        assert_eq!(frees().last(), Some(&container));
+    }
+    assert_eq!(mallocs().len(), 3);
+    assert_eq!(frees().len(), 3);
+}
+
+#[test]
+fn zero_sized_allocation() {
+    let heap = heap();
+    let layout = Layout::from_size_align(0, 8).unwrap();
+
+    let ptr = heap.allocate(layout);
+    assert_eq!(ptr as usize, 8);
+
+    unsafe { heap.deallocate(ptr, layout) };
+    assert!(mallocs().is_empty());
+    assert!(frees().is_empty());
+}
+
+#[test]
+fn overaligned_allocation() {
+    let heap = heap();
+
+    let ptr = heap.allocate(Layout::from_size_align(64, 32).unwrap());
+    assert!(ptr.is_null());
+    assert!(mallocs().is_empty());
+}
+
+#[test]
+fn failed_allocation() {
+    let heap = heap();
+    FAIL.with(|f| f.set(true));
+
+    for size in [16, 8192] {
+        let ptr = heap.allocate(Layout::from_size_align(size, 8).unwrap());
+        assert!(ptr.is_null());
+    }
+    assert_eq!(mallocs().len(), 2);
+}
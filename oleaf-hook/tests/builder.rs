@@ -0,0 +1,197 @@
+use std::{io, os::raw::c_void};
+
+use oleaf_hook::{
+    cxx,
+    dml::{Field, OwnedField, OwnedRecord, OwnedValue, Record, RecordBuilder, TypeId},
+};
+
+// Both records and fields start with their vtable pointer.
+fn vtable<T>(object: &T) -> *mut c_void {
+    unsafe { *(object as *const T as *const *mut c_void) }
+}
+
+fn field(name: &str, value: OwnedValue) -> OwnedField {
+    OwnedField {
+        name: name.to_string(),
+        value,
+    }
+}
+
+fn owned() -> OwnedRecord {
+    OwnedRecord {
+        fields: vec![
+            field("Offset", OwnedValue::Byt(-1)),
+            field("Flags", OwnedValue::UByt(0x80)),
+            field("Port", OwnedValue::UShrt(12000)),
+            field("Delta", OwnedValue::Int(-2)),
+            field("QuestID", OwnedValue::UInt(42)),
+            field("GlobalID", OwnedValue::Gid(0x0123_4567_89ab_cdef)),
+            field("Scale", OwnedValue::Flt(1.5)),
+            field("Time", OwnedValue::Dbl(-0.25)),
+            field(
+                "Speaker",
+                OwnedValue::Str(b"Headmaster Merle Ambrose".to_vec()),
+            ),
+            field("Title", OwnedValue::WStr("Über".to_string())),
+        ],
+    }
+}
+
+#[test]
+fn build_all_types() {
+    let buf = RecordBuilder::new()
+        .byt("Offset", -1)
+        .ubyt("Flags", 0x80)
+        .ushrt("Port", 12000)
+        .int("Delta", -2)
+        .uint("QuestID", 42)
+        .gid("GlobalID", 0x0123_4567_89ab_cdef)
+        .flt("Scale", 1.5)
+        .dbl("Time", -0.25)
+        .str("Speaker", "Headmaster Merle Ambrose")
+        .wstr("Title", "Über")
+        .build()
+        .unwrap();
+
+    let record = buf.record();
+    assert_eq!(unsafe { record.to_owned() }.unwrap(), owned());
+
+    let types: Vec<_> = unsafe { record.fields() }
+        .iter()
+        .map(Field::type_id)
+        .collect();
+    assert_eq!(
+        types,
+        [
+            TypeId::Byt,
+            TypeId::UByt,
+            TypeId::UShrt,
+            TypeId::Int,
+            TypeId::UInt,
+            TypeId::Gid,
+            TypeId::Flt,
+            TypeId::Dbl,
+            TypeId::Str,
+            TypeId::WStr,
+        ]
+    );
+}
+
+#[test]
+fn from_owned_round_trip() {
+    let buf = RecordBuilder::from_owned(&owned()).build().unwrap();
+
+    assert_eq!(unsafe { buf.record().to_owned() }.unwrap(), owned());
+}
+
+#[test]
+fn empty_record() {
+    let buf = RecordBuilder::default().build().unwrap();
+    let record = buf.record();
+
+    assert!(unsafe { record.fields() }.is_empty());
+    assert!(vtable(record).is_null());
+    assert_eq!(record.ref_count(), 1);
+}
+
+#[test]
+fn wstr_units() {
+    // An unpaired surrogate survives in the raw code units.
+    let buf = RecordBuilder::new()
+        .wstr_units("Title", vec![0x48, 0xd800, 0x69])
+        .build()
+        .unwrap();
+
+    let title = unsafe { buf.record().get_wstr("Title") }.unwrap();
+    assert_eq!(title.as_utf16(), [0x48, 0xd800, 0x69]);
+}
+
+#[test]
+fn vtables() {
+    let (record_vtable, field_vtable) = (0x1000 as *mut c_void, 0x2000 as *mut c_void);
+    let template = RecordBuilder::new()
+        .vtable(record_vtable)
+        .field_vtable(field_vtable)
+        .uint("QuestID", 42)
+        .str("Speaker", "Gamma")
+        .build()
+        .unwrap();
+
+    let check = |record: &Record| {
+        assert_eq!(vtable(record), record_vtable);
+        for field in unsafe { record.fields() } {
+            assert_eq!(vtable(field), field_vtable);
+        }
+    };
+    check(template.record());
+
+    let copy = unsafe { RecordBuilder::new().vtables_from(template.record()) }
+        .wstr("Title", "Über")
+        .build()
+        .unwrap();
+    check(copy.record());
+
+    // Templates without fields only provide the record vtable.
+    let empty = RecordBuilder::new().vtable(record_vtable).build().unwrap();
+    let copy = unsafe { RecordBuilder::new().vtables_from(empty.record()) }
+        .int("Delta", 1)
+        .build()
+        .unwrap();
+    assert_eq!(vtable(copy.record()), record_vtable);
+    assert!(vtable(&unsafe { copy.record().fields() }[0]).is_null());
+}
+
+#[test]
+fn stable_record_pointer() {
+    let mut buf = RecordBuilder::new().uint("QuestID", 42).build().unwrap();
+    let ptr = buf.as_mut_ptr();
+
+    // Moving the buffer does not move the record handed out to C++ code.
+    let mut moved = Box::new(buf);
+    assert_eq!(moved.as_mut_ptr(), ptr);
+    assert_eq!(moved.record() as *const Record, ptr as *const Record);
+    assert_eq!(unsafe { (*ptr).get_u32("QuestID") }, Ok(42));
+}
+
+#[test]
+fn interior_null_in_name() {
+    let err = RecordBuilder::new()
+        .uint("Quest\0ID", 42)
+        .build()
+        .err()
+        .unwrap();
+
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("Quest\0ID"));
+}
+
+#[test]
+fn interior_null_in_str() {
+    let long = [&b"Gamma\0"[..], &[b'x'; 32]].concat();
+    let buf = RecordBuilder::new()
+        .str("Speaker", "Gam\0ma")
+        .str("Long", long.clone())
+        .build()
+        .unwrap();
+    let record = buf.record();
+
+    unsafe {
+        assert_eq!(record.get_str("Speaker").unwrap().as_bytes(), b"Gam\0ma");
+        assert_eq!(record.get_str("Long").unwrap().as_bytes(), long);
+    }
+}
+
+#[test]
+fn interior_null_in_wstr() {
+    let buf = RecordBuilder::new()
+        .wstr("Title", "Gam\0ma")
+        .build()
+        .unwrap();
+
+    let title = unsafe { buf.record().get_wstr("Title") }.unwrap();
+    assert_eq!(
+        title.as_utf16(),
+        "Gam\0ma".encode_utf16().collect::<Vec<_>>()
+    );
+    assert!(unsafe { cxx::WString::new("Gam\0ma") }.is_err());
+}
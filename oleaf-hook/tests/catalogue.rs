@@ -0,0 +1,138 @@
+use std::{fs, io, path::PathBuf};
+
+use oleaf_hook::dml::{wire::FieldDef, Catalogue, Service, TypeId};
+
+const TEST_MESSAGES: &str = include_str!("fixtures/protocols/TestMessages.xml");
+const ORDERED_MESSAGES: &str = include_str!("fixtures/protocols/OrderedMessages.xml");
+
+fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/protocols")
+}
+
+fn numbers(service: &Service) -> Vec<(u8, &str)> {
+    service
+        .messages()
+        .iter()
+        .map(|m| (m.number, m.name.as_str()))
+        .collect()
+}
+
+#[test]
+fn parse_protocol_info() {
+    let service = Service::parse(TEST_MESSAGES).unwrap();
+
+    assert_eq!(service.id, 42);
+    assert_eq!(service.protocol_type, "TEST");
+    assert_eq!(service.version, 3);
+    assert_eq!(service.description, "Test Messages");
+}
+
+#[test]
+fn alphabetical_numbering() {
+    let service = Service::parse(TEST_MESSAGES).unwrap();
+
+    assert_eq!(
+        numbers(&service),
+        [(1, "MSG_ATTACK"), (2, "MSG_PING"), (3, "MSG_QUESTDIALOG")]
+    );
+    assert_eq!(service.message(2).unwrap().name, "MSG_PING");
+    assert!(service.message(4).is_none());
+}
+
+#[test]
+fn explicit_numbering() {
+    let service = Service::parse(ORDERED_MESSAGES).unwrap();
+
+    assert_eq!(numbers(&service), [(1, "MSG_ZETA"), (5, "MSG_ALPHA")]);
+    assert_eq!(service.version, 0);
+    assert_eq!(service.description, "");
+}
+
+#[test]
+fn metadata_and_fields() {
+    let service = Service::parse(TEST_MESSAGES).unwrap();
+    let dialog = service.message_by_name("MSG_QUESTDIALOG").unwrap();
+
+    assert_eq!(dialog.description, "Shows a quest dialog.");
+    assert_eq!(dialog.handler, "MSG_QuestDialog");
+    assert_eq!(dialog.access_level, 1);
+    // Neither the `_` metadata nor the `NOXFER` field are transferred.
+    assert_eq!(
+        dialog.fields,
+        [
+            FieldDef::new("QuestID", TypeId::UInt),
+            FieldDef::new("Title", TypeId::WStr),
+            FieldDef::new("Speaker", TypeId::Str),
+        ]
+    );
+
+    assert!(service
+        .message_by_name("MSG_PING")
+        .unwrap()
+        .fields
+        .is_empty());
+}
+
+#[test]
+fn unknown_type_is_rejected() {
+    let xml = TEST_MESSAGES.replace(r#"<Damage TYPE="INT">"#, r#"<Damage TYPE="QWORD">"#);
+
+    let err = Service::parse(&xml).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err
+        .to_string()
+        .contains("MSG_ATTACK.Damage has unknown TYPE QWORD"));
+}
+
+#[test]
+fn partial_order_is_rejected() {
+    let xml = ORDERED_MESSAGES.replace(r#"<_MsgOrder TYPE="UBYT" NOXFER="TRUE">5</_MsgOrder>"#, "");
+
+    let err = Service::parse(&xml).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("MSG_ALPHA"));
+}
+
+#[test]
+fn duplicate_numbers_are_rejected() {
+    let xml = ORDERED_MESSAGES.replace(">5</_MsgOrder>", ">1</_MsgOrder>");
+
+    let err = Service::parse(&xml).unwrap_err();
+    assert!(err.to_string().contains("share message number 1"));
+}
+
+#[test]
+fn load_dir() {
+    let catalogue = Catalogue::load_dir(fixtures()).unwrap();
+
+    let ids: Vec<_> = catalogue.services().map(|s| s.id).collect();
+    assert_eq!(ids, [7, 42]);
+    assert_eq!(catalogue.message(42, 1).unwrap().name, "MSG_ATTACK");
+
+    let (service, message) = catalogue.find_message("MSG_ALPHA").unwrap();
+    assert_eq!((service.id, message.number), (7, 5));
+}
+
+#[test]
+fn duplicate_services_are_rejected() {
+    let mut catalogue = Catalogue::new();
+    catalogue
+        .insert(Service::parse(TEST_MESSAGES).unwrap())
+        .unwrap();
+
+    let err = catalogue
+        .insert(Service::parse(TEST_MESSAGES).unwrap())
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+    // The same goes for services loaded from a directory.
+    let dir = std::env::temp_dir().join(format!("oleaf-catalogue-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("TestMessages.xml"), TEST_MESSAGES).unwrap();
+    fs::write(dir.join("CopyMessages.xml"), TEST_MESSAGES).unwrap();
+
+    let err = Catalogue::load_dir(&dir).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,85 @@
+use std::{env, fs, path::PathBuf};
+
+use oleaf_hook::dml::{codegen, Service};
+
+// Generated from `fixtures/codegen/GenMessages.xml`. Run the tests with
+// `OLEAF_BLESS=1` to regenerate it after changing the generator.
+#[allow(dead_code)]
+mod gen {
+    include!("fixtures/codegen/gen_messages.rs");
+}
+
+use gen::*;
+
+const XML: &str = include_str!("fixtures/codegen/GenMessages.xml");
+
+#[test]
+fn fixture_is_current() {
+    let path =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/codegen/gen_messages.rs");
+    let generated = codegen::generate(&Service::parse(XML).unwrap()).unwrap();
+
+    if env::var_os("OLEAF_BLESS").is_some() {
+        fs::write(&path, &generated).unwrap();
+    }
+    assert_eq!(fs::read_to_string(&path).unwrap(), generated);
+}
+
+#[test]
+fn constants() {
+    assert_eq!(SERVICE_ID, 51);
+    assert_eq!(AllTypes::SERVICE_ID, 51);
+    assert_eq!(AllTypes::NUMBER, 1);
+    assert_eq!(AllTypes::NAME, "MSG_ALLTYPES");
+    assert_eq!(Empty::NUMBER, 2);
+    assert_eq!(OddNames::NUMBER, 3);
+}
+
+#[test]
+fn round_trip() {
+    let message = AllTypes {
+        global_id: 0x0123_4567_89ab_cdef,
+        delta: -2,
+        quest_id: 42,
+        scale: 1.5,
+        offset: -1,
+        flags: 0x80,
+        port: 12000,
+        time: -0.25,
+        speaker: "Gamma".to_string(),
+        title: "Über".to_string(),
+    };
+
+    let encoded = message.encode().unwrap();
+    assert_eq!(
+        encoded.len(),
+        8 + 4 + 4 + 4 + 1 + 1 + 2 + 8 + (2 + 5) + (2 + 8)
+    );
+    assert_eq!(AllTypes::decode(&encoded).unwrap(), message);
+
+    let mut data = &encoded[..];
+    assert_eq!(AllTypes::read_from(&mut data).unwrap(), message);
+    assert!(data.is_empty());
+
+    assert_eq!(Empty::decode(&[]).unwrap(), Empty {});
+    assert!(Empty::decode(&[0]).is_err());
+}
+
+#[test]
+fn odd_names() {
+    // Keywords, names without any ASCII characters and duplicates all
+    // map to valid and distinct identifiers.
+    let message = OddNames {
+        type_: 1,
+        type__: 2,
+        __: 3,
+        ___: 4,
+        http_port: 5,
+        zone_name: "Zone".to_string(),
+    };
+
+    assert_eq!(
+        OddNames::decode(&message.encode().unwrap()).unwrap(),
+        message
+    );
+}
@@ -0,0 +1,276 @@
+use std::{mem, ptr};
+
+use oleaf_hook::cxx::{hash_bytes, List, Map, UnorderedMap};
+
+// Mirrors of the MSVC node layouts, built by hand so that the views can
+// be exercised without any C++ code.
+
+#[repr(C)]
+struct RawListNode<T> {
+    next: *mut RawListNode<T>,
+    prev: *mut RawListNode<T>,
+    value: T,
+}
+
+#[repr(C)]
+struct RawList<T> {
+    head: *mut RawListNode<T>,
+    size: usize,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct RawPair {
+    key: u32,
+    value: u64,
+}
+
+#[repr(C)]
+struct RawTreeNode {
+    left: *mut RawTreeNode,
+    parent: *mut RawTreeNode,
+    right: *mut RawTreeNode,
+    color: u8,
+    is_nil: u8,
+    pair: RawPair,
+}
+
+#[repr(C)]
+struct RawMap {
+    head: *mut RawTreeNode,
+    size: usize,
+}
+
+#[repr(C)]
+struct RawVector<T> {
+    first: *mut T,
+    last: *mut T,
+    end: *mut T,
+}
+
+#[repr(C)]
+struct RawUnorderedMap {
+    max_load_factor: f32,
+    list: RawList<RawPair>,
+    buckets: RawVector<*mut RawListNode<RawPair>>,
+    mask: usize,
+    max_index: usize,
+}
+
+// Allocates a node whose address stays stable until it is leaked.
+fn node<T>(value: T) -> *mut T {
+    Box::into_raw(Box::new(value))
+}
+
+// Builds a well-formed list ring holding `values`.
+fn raw_list<T: Default>(values: Vec<T>) -> (RawList<T>, Vec<*mut RawListNode<T>>) {
+    let head = node(RawListNode {
+        next: ptr::null_mut(),
+        prev: ptr::null_mut(),
+        value: T::default(),
+    });
+    let nodes: Vec<_> = values
+        .into_iter()
+        .map(|value| {
+            node(RawListNode {
+                next: ptr::null_mut(),
+                prev: ptr::null_mut(),
+                value,
+            })
+        })
+        .collect();
+
+    let ring: Vec<_> = Some(head).into_iter().chain(nodes.clone()).collect();
+    for (i, &node) in ring.iter().enumerate() {
+        unsafe {
+            (*node).next = ring[(i + 1) % ring.len()];
+            (*node).prev = ring[(i + ring.len() - 1) % ring.len()];
+        }
+    }
+
+    let size = nodes.len();
+    (RawList { head, size }, nodes)
+}
+
+fn tree_node(key: u32, head: *mut RawTreeNode) -> *mut RawTreeNode {
+    node(RawTreeNode {
+        left: head,
+        parent: head,
+        right: head,
+        color: 1,
+        is_nil: 0,
+        pair: RawPair {
+            key,
+            value: key as u64 * 100,
+        },
+    })
+}
+
+// Builds the tree
+//
+//        30
+//      /    \
+//     20    40
+//    /        \
+//   10        50
+fn raw_map() -> RawMap {
+    let head = node(RawTreeNode {
+        left: ptr::null_mut(),
+        parent: ptr::null_mut(),
+        right: ptr::null_mut(),
+        color: 1,
+        is_nil: 1,
+        pair: RawPair::default(),
+    });
+    let [n10, n20, n30, n40, n50] = [10, 20, 30, 40, 50].map(|key| tree_node(key, head));
+
+    unsafe {
+        (*head).left = n10;
+        (*head).parent = n30;
+        (*head).right = n50;
+
+        (*n30).left = n20;
+        (*n30).right = n40;
+        (*n20).parent = n30;
+        (*n20).left = n10;
+        (*n10).parent = n20;
+        (*n40).parent = n30;
+        (*n40).right = n50;
+        (*n50).parent = n40;
+    }
+
+    RawMap { head, size: 5 }
+}
+
+#[test]
+fn layouts() {
+    assert_eq!(mem::size_of::<List<u32>>(), 0x10);
+    assert_eq!(mem::size_of::<Map<u32, u64>>(), 0x10);
+    assert_eq!(mem::size_of::<UnorderedMap<u32, u64>>(), 0x40);
+}
+
+#[test]
+fn list_iterates_both_ways() {
+    let (raw, _) = raw_list(vec![1u32, 2, 3]);
+    let list = unsafe { &*(&raw as *const RawList<u32> as *const List<u32>) };
+
+    assert_eq!(list.len(), 3);
+    assert_eq!(
+        unsafe { list.iter() }.copied().collect::<Vec<_>>(),
+        [1, 2, 3]
+    );
+    assert_eq!(
+        unsafe { list.iter() }.rev().copied().collect::<Vec<_>>(),
+        [3, 2, 1]
+    );
+
+    let mut iter = unsafe { list.iter() };
+    assert_eq!(iter.next(), Some(&1));
+    assert_eq!(iter.next_back(), Some(&3));
+    assert_eq!(iter.next(), Some(&2));
+    assert_eq!(iter.next_back(), None);
+}
+
+#[test]
+fn list_survives_cycles() {
+    let (raw, nodes) = raw_list(vec![1u32, 2, 3]);
+    // The last node links back to the first one, bypassing the head.
+    let (first, last) = (nodes[0], nodes[2]);
+    unsafe { (*last).next = first };
+    let list = unsafe { &*(&raw as *const RawList<u32> as *const List<u32>) };
+
+    assert_eq!(unsafe { list.iter() }.count(), 3);
+}
+
+#[test]
+fn map_iterates_in_order() {
+    let raw = raw_map();
+    let map = unsafe { &*(&raw as *const RawMap as *const Map<u32, u64>) };
+
+    let entries: Vec<_> = unsafe { map.iter() }.map(|(&k, &v)| (k, v)).collect();
+    assert_eq!(
+        entries,
+        [(10, 1000), (20, 2000), (30, 3000), (40, 4000), (50, 5000)]
+    );
+}
+
+#[test]
+fn map_finds_keys() {
+    let raw = raw_map();
+    let map = unsafe { &*(&raw as *const RawMap as *const Map<u32, u64>) };
+
+    assert_eq!(unsafe { map.get(&10) }, Some(&1000));
+    assert_eq!(unsafe { map.get(&50) }, Some(&5000));
+    assert_eq!(unsafe { map.get(&35) }, None);
+}
+
+#[test]
+fn map_survives_cycles() {
+    let raw = raw_map();
+    // Make the root its own parent, so that climbing up never ends.
+    unsafe { (*(*raw.head).parent).parent = (*raw.head).parent };
+    let map = unsafe { &*(&raw as *const RawMap as *const Map<u32, u64>) };
+
+    assert!(unsafe { map.iter() }.count() <= map.len());
+
+    // Make a leaf its own right child.
+    let raw = raw_map();
+    unsafe { (*(*raw.head).right).right = (*raw.head).right };
+    let map = unsafe { &*(&raw as *const RawMap as *const Map<u32, u64>) };
+
+    assert!(unsafe { map.iter() }.count() <= map.len());
+}
+
+#[test]
+fn unordered_map_finds_keys() {
+    const BUCKETS: usize = 8;
+    let hash = |key: u32| hash_bytes(&key.to_le_bytes()) as usize & (BUCKETS - 1);
+
+    // Entries of a bucket are adjacent in the list.
+    let mut keys = vec![1u32, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+    keys.sort_by_key(|&key| hash(key));
+    let pairs = keys
+        .iter()
+        .map(|&key| RawPair {
+            key,
+            value: key as u64 * 100,
+        })
+        .collect();
+    let (raw_list, nodes) = raw_list(pairs);
+
+    let mut buckets = vec![raw_list.head; 2 * BUCKETS];
+    for (&key, &node) in keys.iter().zip(&nodes) {
+        let bucket = hash(key);
+        if buckets[2 * bucket] == raw_list.head {
+            buckets[2 * bucket] = node;
+        }
+        buckets[2 * bucket + 1] = node;
+    }
+
+    let raw = RawUnorderedMap {
+        max_load_factor: 1.0,
+        list: raw_list,
+        buckets: RawVector {
+            first: buckets.as_mut_ptr(),
+            last: unsafe { buckets.as_mut_ptr().add(buckets.len()) },
+            end: unsafe { buckets.as_mut_ptr().add(buckets.len()) },
+        },
+        mask: BUCKETS - 1,
+        max_index: BUCKETS,
+    };
+    let map = unsafe { &*(&raw as *const RawUnorderedMap as *const UnorderedMap<u32, u64>) };
+
+    assert_eq!(map.len(), 10);
+    assert_eq!(map.bucket_count(), BUCKETS);
+    assert_eq!(unsafe { map.iter() }.count(), 10);
+
+    for key in 1..=10u32 {
+        let found = unsafe { map.find_by_hash(hash_bytes(&key.to_le_bytes()), |&k| k == key) };
+        assert_eq!(found, Some((&key, &(key as u64 * 100))));
+    }
+    let missing = 11u32;
+    assert_eq!(
+        unsafe { map.find_by_hash(hash_bytes(&missing.to_le_bytes()), |&k| k == missing) },
+        None
+    );
+}
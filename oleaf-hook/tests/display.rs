@@ -0,0 +1,107 @@
+use oleaf_hook::dml::{self, RecordBuilder};
+
+#[test]
+fn write_hexdump() {
+    let bytes = b"Hello, hexdump!\n\0AB";
+    let mut out = String::new();
+    dml::write_hexdump(
+        &mut out,
+        bytes,
+        &[
+            ("greeting", 0),
+            ("comma", 5),
+            ("tail", 0x11),
+            ("beyond", 0x20),
+        ],
+    )
+    .unwrap();
+
+    let expected = concat!(
+        "0x0000: 48 65 6c 6c 6f 2c 20 68 65 78 64 75 6d 70 21 0a ",
+        "|Hello, hexdump!.| greeting@0x0 comma@0x5\n",
+        "0x0010: 00 41 42                                        ",
+        "|.AB             | tail@0x11\n",
+    );
+    assert_eq!(out, expected);
+
+    out.clear();
+    dml::write_hexdump(&mut out, &[], &[("empty", 0)]).unwrap();
+    assert!(out.is_empty());
+}
+
+#[test]
+fn field_hexdump() {
+    let buf = RecordBuilder::new().uint("QuestID", 42).build().unwrap();
+    let field = &unsafe { buf.record().fields() }[0];
+
+    let mut out = Vec::new();
+    unsafe { field.hexdump(&mut out) }.unwrap();
+    let out = String::from_utf8(out).unwrap();
+
+    let rows: Vec<_> = out.lines().collect();
+    assert_eq!(rows.len(), 8);
+    assert!(rows[0].ends_with("| vtable@0x0 double_storage@0x8"));
+    assert!(rows[1].ends_with("| float_storage@0x10 int_storage@0x14 str_storage@0x18"));
+    // The value 42 is stored little-endian in `int_storage`.
+    assert!(rows[1].starts_with("0x0010: 00 00 00 00 2a 00 00 00 "));
+}
+
+#[test]
+fn record_table() {
+    let buf = RecordBuilder::new()
+        .uint("QuestID", 42)
+        .flt("Scale", 1.5)
+        .str("Speaker", "Merle \"The Headmaster\"")
+        .wstr("Title", " Über ")
+        .gid("GlobalID", 1)
+        .build()
+        .unwrap();
+    let record = buf.record();
+
+    let expected = concat!(
+        "NAME      TYPE  VALUE\n",
+        "QuestID   UINT  42\n",
+        "Scale     FLT   1.5\n",
+        "Speaker   STR   \"Merle \\\"The Headmaster\\\"\"\n",
+        "Title     WSTR  \" Über \"\n",
+        "GlobalID  GID   1\n",
+    );
+    assert_eq!(record.to_string(), expected);
+
+    let mut out = Vec::new();
+    unsafe { record.dump(&mut out) }.unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+}
+
+#[test]
+fn empty_record_table() {
+    let buf = RecordBuilder::new().build().unwrap();
+
+    assert_eq!(buf.record().to_string(), "NAME  TYPE  VALUE\n");
+}
+
+#[test]
+fn field_display() {
+    let buf = RecordBuilder::new()
+        .int("Delta", -3)
+        .wstr("Title", "Über")
+        .build()
+        .unwrap();
+    let fields = unsafe { buf.record().fields() };
+
+    assert_eq!(fields[0].to_string(), "Delta: INT = -3");
+    assert_eq!(fields[1].to_string(), "Title: WSTR = \"Über\"");
+    assert_eq!(
+        format!("{:?}", fields[0]),
+        "Field { name: \"Delta\", type_id: Int, value: Some(Int(-3)) }"
+    );
+}
+
+#[test]
+fn record_debug() {
+    let buf = RecordBuilder::new().ubyt("Flags", 7).build().unwrap();
+
+    let debug = format!("{:?}", buf.record());
+    assert!(debug.starts_with("Record { vtable: 0x0, ref_count: 1, fields: [Field {"));
+    assert!(debug.contains("value: Some(UByt(7))"));
+}
@@ -0,0 +1,89 @@
+// Dumps the golden images checked by `tests/abi.rs` from a real MSVC
+// x64 build.
+//
+//     cl /nologo /std:c++17 /EHsc /O2 dump_abi.cpp
+//     dump_abi.exe > msvc_x64.txt
+//
+// Every line holds one object as `<kind> <length>: <bytes>`, in the
+// notation of `Module::find_signature`. `??` marks bytes which hold
+// pointers or which MSVC leaves unspecified, like the SSO buffer past
+// the null terminator.
+
+#include <cstdio>
+#include <cstring>
+#include <string>
+#include <vector>
+
+static const char TEXT[] = "abcdefghijklmnopqrstuvwxyz0123456789";
+static const wchar_t WTEXT[] = L"abcdefghijklmnopqrstuvwxyz0123456789";
+
+// The size of the buffer shared by the SSO characters and the pointer.
+static const size_t BUFFER_SIZE = 16;
+
+static void print_image(const char *kind, const char *key, const unsigned char *bytes,
+                        size_t size, const bool *masked) {
+    std::printf("%s %s:", kind, key);
+    for (size_t i = 0; i < size; ++i) {
+        if (masked[i]) {
+            std::printf(" ??");
+        } else {
+            std::printf(" %02x", bytes[i]);
+        }
+    }
+    std::printf("\n");
+}
+
+template <class String>
+static void dump_string(const char *kind, const typename String::value_type *text, size_t len) {
+    using Char = typename String::value_type;
+    const String string(text, len);
+
+    unsigned char bytes[sizeof(String)];
+    std::memcpy(bytes, &string, sizeof(String));
+
+    // In SSO mode, only the characters and their null terminator are
+    // defined. Otherwise the buffer holds the pointer to the heap.
+    bool masked[sizeof(String)] = {};
+    const bool sso = string.capacity() < BUFFER_SIZE / sizeof(Char);
+    const size_t defined = sso ? (len + 1) * sizeof(Char) : 0;
+    for (size_t i = defined; i < BUFFER_SIZE; ++i) {
+        masked[i] = true;
+    }
+
+    char key[16];
+    std::snprintf(key, sizeof(key), "%zu", len);
+    print_image(kind, key, bytes, sizeof(String), masked);
+}
+
+static void dump_vector(const char *key, const std::vector<int> &vector) {
+    unsigned char bytes[sizeof(vector)];
+    std::memcpy(bytes, &vector, sizeof(vector));
+
+    // Null pointers are defined, all others depend on the heap.
+    bool masked[sizeof(vector)] = {};
+    for (size_t word = 0; word < sizeof(vector); word += sizeof(void *)) {
+        void *ptr;
+        std::memcpy(&ptr, bytes + word, sizeof(ptr));
+        for (size_t i = 0; i < sizeof(ptr); ++i) {
+            masked[word + i] = ptr != nullptr;
+        }
+    }
+
+    print_image("vector", key, bytes, sizeof(vector), masked);
+}
+
+int main() {
+    std::printf("# MSVC %d, dumped by dump_abi.cpp\n", _MSC_FULL_VER);
+
+    for (size_t len : {0, 1, 15, 16, 32}) {
+        dump_string<std::string>("string", TEXT, len);
+    }
+    for (size_t len : {0, 1, 7, 8, 16}) {
+        dump_string<std::wstring>("wstring", WTEXT, len);
+    }
+
+    dump_vector("empty", std::vector<int>{});
+    dump_vector("3", std::vector<int>{1, 2, 3});
+
+    return 0;
+}
@@ -0,0 +1,162 @@
+// Generated from the GEN protocol definition. Do not edit.
+
+pub const SERVICE_ID: u8 = 51;
+
+#[doc = "One field of every supported type."]
+#[derive(Clone, Debug, PartialEq, ::oleaf_hook::DmlRecord)]
+pub struct AllTypes {
+    #[dml(rename = "GlobalID")]
+    pub global_id: u64,
+    #[dml(rename = "Delta")]
+    pub delta: i32,
+    #[dml(rename = "QuestID")]
+    pub quest_id: u32,
+    #[dml(rename = "Scale")]
+    pub scale: f32,
+    #[dml(rename = "Offset")]
+    pub offset: i8,
+    #[dml(rename = "Flags")]
+    pub flags: u8,
+    #[dml(rename = "Port")]
+    pub port: u16,
+    #[dml(rename = "Time")]
+    pub time: f64,
+    #[dml(rename = "Speaker")]
+    pub speaker: ::std::string::String,
+    #[dml(rename = "Title")]
+    pub title: ::std::string::String,
+}
+
+impl AllTypes {
+    pub const SERVICE_ID: u8 = 51;
+    pub const NUMBER: u8 = 1;
+    pub const NAME: &'static str = "MSG_ALLTYPES";
+
+    pub fn read_from<R: ::std::io::Read>(r: &mut R) -> ::std::io::Result<Self> {
+        ::std::io::Result::Ok(Self {
+            global_id: ::oleaf_hook::dml::wire::WireType::read(r, "GlobalID", ::oleaf_hook::dml::TypeId::Gid)?,
+            delta: ::oleaf_hook::dml::wire::WireType::read(r, "Delta", ::oleaf_hook::dml::TypeId::Int)?,
+            quest_id: ::oleaf_hook::dml::wire::WireType::read(r, "QuestID", ::oleaf_hook::dml::TypeId::UInt)?,
+            scale: ::oleaf_hook::dml::wire::WireType::read(r, "Scale", ::oleaf_hook::dml::TypeId::Flt)?,
+            offset: ::oleaf_hook::dml::wire::WireType::read(r, "Offset", ::oleaf_hook::dml::TypeId::Byt)?,
+            flags: ::oleaf_hook::dml::wire::WireType::read(r, "Flags", ::oleaf_hook::dml::TypeId::UByt)?,
+            port: ::oleaf_hook::dml::wire::WireType::read(r, "Port", ::oleaf_hook::dml::TypeId::UShrt)?,
+            time: ::oleaf_hook::dml::wire::WireType::read(r, "Time", ::oleaf_hook::dml::TypeId::Dbl)?,
+            speaker: ::oleaf_hook::dml::wire::WireType::read(r, "Speaker", ::oleaf_hook::dml::TypeId::Str)?,
+            title: ::oleaf_hook::dml::wire::WireType::read(r, "Title", ::oleaf_hook::dml::TypeId::WStr)?,
+        })
+    }
+
+    pub fn write_to<W: ::std::io::Write>(&self, w: &mut W) -> ::std::io::Result<()> {
+        ::oleaf_hook::dml::wire::WireType::write(&self.global_id, w, "GlobalID", ::oleaf_hook::dml::TypeId::Gid)?;
+        ::oleaf_hook::dml::wire::WireType::write(&self.delta, w, "Delta", ::oleaf_hook::dml::TypeId::Int)?;
+        ::oleaf_hook::dml::wire::WireType::write(&self.quest_id, w, "QuestID", ::oleaf_hook::dml::TypeId::UInt)?;
+        ::oleaf_hook::dml::wire::WireType::write(&self.scale, w, "Scale", ::oleaf_hook::dml::TypeId::Flt)?;
+        ::oleaf_hook::dml::wire::WireType::write(&self.offset, w, "Offset", ::oleaf_hook::dml::TypeId::Byt)?;
+        ::oleaf_hook::dml::wire::WireType::write(&self.flags, w, "Flags", ::oleaf_hook::dml::TypeId::UByt)?;
+        ::oleaf_hook::dml::wire::WireType::write(&self.port, w, "Port", ::oleaf_hook::dml::TypeId::UShrt)?;
+        ::oleaf_hook::dml::wire::WireType::write(&self.time, w, "Time", ::oleaf_hook::dml::TypeId::Dbl)?;
+        ::oleaf_hook::dml::wire::WireType::write(&self.speaker, w, "Speaker", ::oleaf_hook::dml::TypeId::Str)?;
+        ::oleaf_hook::dml::wire::WireType::write(&self.title, w, "Title", ::oleaf_hook::dml::TypeId::WStr)?;
+        ::std::io::Result::Ok(())
+    }
+
+    pub fn decode(mut data: &[u8]) -> ::std::io::Result<Self> {
+        let message = Self::read_from(&mut data)?;
+        ::oleaf_hook::dml::wire::expect_end(data)?;
+        ::std::io::Result::Ok(message)
+    }
+
+    pub fn encode(&self) -> ::std::io::Result<::std::vec::Vec<u8>> {
+        let mut buf = ::std::vec::Vec::new();
+        self.write_to(&mut buf)?;
+        ::std::io::Result::Ok(buf)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, ::oleaf_hook::DmlRecord)]
+pub struct Empty {
+}
+
+impl Empty {
+    pub const SERVICE_ID: u8 = 51;
+    pub const NUMBER: u8 = 2;
+    pub const NAME: &'static str = "MSG_EMPTY";
+
+    pub fn read_from<R: ::std::io::Read>(_r: &mut R) -> ::std::io::Result<Self> {
+        ::std::io::Result::Ok(Self {
+        })
+    }
+
+    pub fn write_to<W: ::std::io::Write>(&self, _w: &mut W) -> ::std::io::Result<()> {
+        ::std::io::Result::Ok(())
+    }
+
+    pub fn decode(mut data: &[u8]) -> ::std::io::Result<Self> {
+        let message = Self::read_from(&mut data)?;
+        ::oleaf_hook::dml::wire::expect_end(data)?;
+        ::std::io::Result::Ok(message)
+    }
+
+    pub fn encode(&self) -> ::std::io::Result<::std::vec::Vec<u8>> {
+        let mut buf = ::std::vec::Vec::new();
+        self.write_to(&mut buf)?;
+        ::std::io::Result::Ok(buf)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, ::oleaf_hook::DmlRecord)]
+pub struct OddNames {
+    #[dml(rename = "Type")]
+    pub type_: i32,
+    #[dml(rename = "type")]
+    pub type__: i32,
+    #[dml(rename = "Ω")]
+    pub __: u8,
+    #[dml(rename = "Ψ")]
+    pub ___: u8,
+    #[dml(rename = "HTTPPort")]
+    pub http_port: u16,
+    #[dml(rename = "Zone.Name")]
+    pub zone_name: ::std::string::String,
+}
+
+impl OddNames {
+    pub const SERVICE_ID: u8 = 51;
+    pub const NUMBER: u8 = 3;
+    pub const NAME: &'static str = "MSG_ODD_NAMES";
+
+    pub fn read_from<R: ::std::io::Read>(r: &mut R) -> ::std::io::Result<Self> {
+        ::std::io::Result::Ok(Self {
+            type_: ::oleaf_hook::dml::wire::WireType::read(r, "Type", ::oleaf_hook::dml::TypeId::Int)?,
+            type__: ::oleaf_hook::dml::wire::WireType::read(r, "type", ::oleaf_hook::dml::TypeId::Int)?,
+            __: ::oleaf_hook::dml::wire::WireType::read(r, "Ω", ::oleaf_hook::dml::TypeId::UByt)?,
+            ___: ::oleaf_hook::dml::wire::WireType::read(r, "Ψ", ::oleaf_hook::dml::TypeId::UByt)?,
+            http_port: ::oleaf_hook::dml::wire::WireType::read(r, "HTTPPort", ::oleaf_hook::dml::TypeId::UShrt)?,
+            zone_name: ::oleaf_hook::dml::wire::WireType::read(r, "Zone.Name", ::oleaf_hook::dml::TypeId::Str)?,
+        })
+    }
+
+    pub fn write_to<W: ::std::io::Write>(&self, w: &mut W) -> ::std::io::Result<()> {
+        ::oleaf_hook::dml::wire::WireType::write(&self.type_, w, "Type", ::oleaf_hook::dml::TypeId::Int)?;
+        ::oleaf_hook::dml::wire::WireType::write(&self.type__, w, "type", ::oleaf_hook::dml::TypeId::Int)?;
+        ::oleaf_hook::dml::wire::WireType::write(&self.__, w, "Ω", ::oleaf_hook::dml::TypeId::UByt)?;
+        ::oleaf_hook::dml::wire::WireType::write(&self.___, w, "Ψ", ::oleaf_hook::dml::TypeId::UByt)?;
+        ::oleaf_hook::dml::wire::WireType::write(&self.http_port, w, "HTTPPort", ::oleaf_hook::dml::TypeId::UShrt)?;
+        ::oleaf_hook::dml::wire::WireType::write(&self.zone_name, w, "Zone.Name", ::oleaf_hook::dml::TypeId::Str)?;
+        ::std::io::Result::Ok(())
+    }
+
+    pub fn decode(mut data: &[u8]) -> ::std::io::Result<Self> {
+        let message = Self::read_from(&mut data)?;
+        ::oleaf_hook::dml::wire::expect_end(data)?;
+        ::std::io::Result::Ok(message)
+    }
+
+    pub fn encode(&self) -> ::std::io::Result<::std::vec::Vec<u8>> {
+        let mut buf = ::std::vec::Vec::new();
+        self.write_to(&mut buf)?;
+        ::std::io::Result::Ok(buf)
+    }
+}
+
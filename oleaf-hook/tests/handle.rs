@@ -0,0 +1,94 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Once,
+    },
+    thread,
+};
+
+use oleaf_hook::dml::{self, Record, RecordBuf, RecordBuilder, RecordRef};
+
+// Releases a reference like the client does, without ever freeing.
+unsafe extern "fastcall" fn release(record: *mut Record) {
+    // The reference count lives at offset 0x18 of every record.
+    let ref_count = &*((record as *mut u8).add(0x18) as *const AtomicU32);
+    ref_count.fetch_sub(1, Ordering::AcqRel);
+}
+
+fn setup() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| dml::initialize_release_function(release));
+}
+
+fn record() -> RecordBuf {
+    RecordBuilder::new().uint("QuestID", 42).build().unwrap()
+}
+
+#[test]
+fn null_record() {
+    setup();
+
+    assert!(unsafe { RecordRef::new(std::ptr::null_mut()) }.is_none());
+}
+
+#[test]
+fn counts_references() {
+    setup();
+    let mut buf = record();
+
+    let first = unsafe { RecordRef::new(buf.as_mut_ptr()) }.unwrap();
+    assert_eq!(first.as_ptr(), buf.as_mut_ptr());
+    assert_eq!(first.ref_count(), 2);
+
+    let second = first.clone();
+    assert_eq!(buf.record().ref_count(), 3);
+
+    drop(first);
+    assert_eq!(second.ref_count(), 2);
+    assert_eq!(unsafe { second.get().get_u32("QuestID") }, Ok(42));
+
+    drop(second);
+    assert_eq!(buf.record().ref_count(), 1);
+}
+
+#[test]
+fn outlives_record_buf() {
+    setup();
+    let mut buf = record();
+
+    let handle = unsafe { RecordRef::new(buf.as_mut_ptr()) }.unwrap();
+    // The buffer leaks its record instead of freeing it under the handle.
+    drop(buf);
+    assert_eq!(handle.ref_count(), 2);
+    assert_eq!(unsafe { handle.get().get_u32("QuestID") }, Ok(42));
+}
+
+#[test]
+fn release_reports_leak() {
+    setup();
+    let mut buf = record();
+    let ptr = buf.as_mut_ptr();
+
+    let handle = unsafe { RecordRef::new(ptr) }.unwrap();
+    assert_eq!(buf.release(), Some(ptr));
+    assert_eq!(handle.ref_count(), 2);
+    assert_eq!(unsafe { handle.get().get_u32("QuestID") }, Ok(42));
+
+    // Without other references, the record is freed.
+    assert_eq!(record().release(), None);
+}
+
+#[test]
+fn release_on_other_thread() {
+    setup();
+    let mut buf = record();
+
+    let handle = unsafe { RecordRef::new(buf.as_mut_ptr()) }.unwrap();
+    let clone = handle.clone();
+    thread::spawn(move || assert_eq!(clone.ref_count(), 3))
+        .join()
+        .unwrap();
+
+    assert_eq!(handle.ref_count(), 2);
+    assert!(format!("{:?}", handle).contains("ref_count: 2"));
+}
@@ -0,0 +1,40 @@
+use std::{
+    os::raw::{c_uint, c_void},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use oleaf_hook::dml::{self, Record, RecordBuilder, RecordRef};
+
+static FLAGS: AtomicU32 = AtomicU32::new(0);
+
+unsafe extern "fastcall" fn unused(_: *mut Record, _: c_uint) {
+    unreachable!();
+}
+
+unsafe extern "fastcall" fn release(record: *mut Record, flags: c_uint) {
+    FLAGS.store(flags, Ordering::Release);
+
+    // The reference count lives at offset 0x18 of every record.
+    let ref_count = &*((record as *mut u8).add(0x18) as *const AtomicU32);
+    ref_count.fetch_sub(1, Ordering::AcqRel);
+}
+
+static VTABLE: [unsafe extern "fastcall" fn(*mut Record, c_uint); 3] = [unused, unused, release];
+
+#[test]
+fn release_through_vtable() {
+    dml::initialize_release_slot(2, 0x10);
+
+    let mut buf = RecordBuilder::new()
+        .vtable(VTABLE.as_ptr() as *mut c_void)
+        .uint("QuestID", 42)
+        .build()
+        .unwrap();
+
+    let handle = unsafe { RecordRef::new(buf.as_mut_ptr()) }.unwrap();
+    assert_eq!(buf.record().ref_count(), 2);
+
+    drop(handle);
+    assert_eq!(buf.record().ref_count(), 1);
+    assert_eq!(FLAGS.load(Ordering::Acquire), 0x10);
+}
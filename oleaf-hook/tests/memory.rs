@@ -0,0 +1,194 @@
+use std::{
+    cell::RefCell,
+    ptr,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use oleaf_hook::cxx::{SharedPtr, WeakPtr};
+
+// A hand-built `_Ref_count_base` with a fake vtable.
+#[repr(C)]
+struct RefCount {
+    vtable: *const [FnRefCount; 2],
+    uses: AtomicU32,
+    weaks: AtomicU32,
+}
+
+type FnRefCount = unsafe extern "fastcall" fn(*mut RefCount);
+
+thread_local! {
+    // The virtual calls made on any control block, with the counts at
+    // the time of the call.
+    static CALLS: RefCell<Vec<(&'static str, u32, u32)>> = RefCell::new(Vec::new());
+}
+
+unsafe fn record(name: &'static str, rep: *mut RefCount) {
+    let counts = (
+        (*rep).uses.load(Ordering::Acquire),
+        (*rep).weaks.load(Ordering::Acquire),
+    );
+    CALLS.with(|c| c.borrow_mut().push((name, counts.0, counts.1)));
+}
+
+unsafe extern "fastcall" fn destroy(rep: *mut RefCount) {
+    record("_Destroy", rep)
+}
+
+unsafe extern "fastcall" fn delete_this(rep: *mut RefCount) {
+    record("_Delete_this", rep)
+}
+
+static VTABLE: [FnRefCount; 2] = [destroy, delete_this];
+
+// Tests may share a thread, so every block starts with a clean record.
+fn ref_count(uses: u32, weaks: u32) -> RefCount {
+    CALLS.with(|c| c.borrow_mut().clear());
+    RefCount {
+        vtable: &VTABLE,
+        uses: AtomicU32::new(uses),
+        weaks: AtomicU32::new(weaks),
+    }
+}
+
+fn calls() -> Vec<(&'static str, u32, u32)> {
+    CALLS.with(|c| c.borrow().clone())
+}
+
+#[repr(C)]
+struct RawPtr<T> {
+    ptr: *mut T,
+    rep: *mut RefCount,
+}
+
+fn shared_ptr<T>(ptr: &RawPtr<T>) -> &SharedPtr<T> {
+    unsafe { &*(ptr as *const RawPtr<T> as *const SharedPtr<T>) }
+}
+
+fn weak_ptr<T>(ptr: &RawPtr<T>) -> &WeakPtr<T> {
+    unsafe { &*(ptr as *const RawPtr<T> as *const WeakPtr<T>) }
+}
+
+#[test]
+fn share_and_release() {
+    let mut object = 42u32;
+    let mut rep = ref_count(1, 1);
+    let raw = RawPtr {
+        ptr: &mut object,
+        rep: &mut rep,
+    };
+    let shared = shared_ptr(&raw);
+
+    let first = shared.share().unwrap();
+    let second = first.clone();
+    assert_eq!(unsafe { *second.get() }, 42);
+    assert_eq!(second.as_ptr(), shared.as_ptr());
+    assert_eq!(shared.use_count(), 3);
+    assert_eq!(shared.weak_count(), 0);
+
+    drop((first, second));
+    assert_eq!(shared.use_count(), 1);
+    assert!(calls().is_empty());
+}
+
+#[test]
+fn destroy_before_delete() {
+    let mut object = 42u32;
+    let mut rep = ref_count(1, 1);
+    let raw = RawPtr {
+        ptr: &mut object,
+        rep: &mut rep,
+    };
+
+    let owned = shared_ptr(&raw).share().unwrap();
+    // The C++ owner lets go, leaving Rust with the last reference.
+    rep.uses.fetch_sub(1, Ordering::AcqRel);
+    assert!(calls().is_empty());
+
+    drop(owned);
+    assert_eq!(calls(), [("_Destroy", 0, 1), ("_Delete_this", 0, 0)]);
+}
+
+#[test]
+fn weak_keeps_block() {
+    let mut object = 42u32;
+    let mut rep = ref_count(1, 2);
+    let raw = RawPtr {
+        ptr: &mut object,
+        rep: &mut rep,
+    };
+
+    let weak = weak_ptr(&raw);
+    assert_eq!((weak.use_count(), weak.weak_count()), (1, 1));
+    assert!(!weak.expired());
+
+    let locked = weak.lock().unwrap();
+    assert_eq!(weak.use_count(), 2);
+    assert_eq!(unsafe { *locked.get() }, 42);
+
+    // With the C++ owner gone, dropping the last strong reference only
+    // releases its weak one, as the `std::weak_ptr` still holds another.
+    rep.uses.fetch_sub(1, Ordering::AcqRel);
+    drop(locked);
+    assert_eq!(calls(), [("_Destroy", 0, 2)]);
+    assert_eq!(rep.weaks.load(Ordering::Acquire), 1);
+    assert!(weak_ptr(&raw).expired());
+}
+
+#[test]
+fn lock_expired() {
+    let mut object = 42u32;
+    let mut rep = ref_count(0, 1);
+    let raw = RawPtr {
+        ptr: &mut object,
+        rep: &mut rep,
+    };
+
+    let weak = weak_ptr(&raw);
+    assert!(weak.expired());
+    assert_eq!(weak.weak_count(), 1);
+    assert!(weak.lock().is_none());
+
+    // The count is never resurrected from zero.
+    assert_eq!(rep.uses.load(Ordering::Acquire), 0);
+    assert!(calls().is_empty());
+}
+
+#[test]
+fn weak_count() {
+    let mut object = 42u32;
+    let mut rep = ref_count(2, 4);
+    let raw = RawPtr {
+        ptr: &mut object,
+        rep: &mut rep,
+    };
+
+    // The strong references collectively hold one weak reference.
+    assert_eq!(shared_ptr(&raw).weak_count(), 3);
+    assert_eq!(weak_ptr(&raw).weak_count(), 3);
+
+    rep.uses.store(0, Ordering::Release);
+    assert_eq!(weak_ptr(&raw).weak_count(), 4);
+}
+
+#[test]
+fn null_pointers() {
+    let mut object = 42u32;
+    let mut rep = ref_count(1, 1);
+
+    let no_block = RawPtr::<u32> {
+        ptr: &mut object,
+        rep: ptr::null_mut(),
+    };
+    assert!(shared_ptr(&no_block).share().is_none());
+    assert_eq!(shared_ptr(&no_block).use_count(), 0);
+    assert_eq!(weak_ptr(&no_block).weak_count(), 0);
+    assert!(weak_ptr(&no_block).lock().is_none());
+
+    let no_object = RawPtr::<u32> {
+        ptr: ptr::null_mut(),
+        rep: &mut rep,
+    };
+    assert!(shared_ptr(&no_object).share().is_none());
+    assert!(weak_ptr(&no_object).lock().is_none());
+    assert_eq!(rep.uses.load(Ordering::Acquire), 1);
+}
@@ -0,0 +1,109 @@
+use std::{ffi::c_void, time::Duration};
+
+use oleaf_hook::{
+    dml::Record,
+    metrics::{self, EventMetrics, Histogram, BUCKETS},
+    replay::{self, Event},
+};
+
+#[oleaf_hook::event("HandleMetricsProbe")]
+fn handle_metrics_probe(_this: *mut c_void, _dml: *mut Record) {}
+
+#[test]
+fn histogram_buckets() {
+    let histogram = Histogram::new();
+    histogram.record(Duration::ZERO);
+    histogram.record(Duration::from_nanos(1));
+    histogram.record(Duration::from_nanos(1000));
+    histogram.record(Duration::from_nanos(1023));
+    histogram.record(Duration::from_secs(1 << 20));
+
+    let snapshot = histogram.snapshot();
+    assert_eq!(snapshot.buckets[0], 1);
+    assert_eq!(snapshot.buckets[1], 1);
+    assert_eq!(snapshot.buckets[10], 2);
+    assert_eq!(snapshot.buckets[BUCKETS - 1], 1);
+    assert_eq!(snapshot.count(), 5);
+}
+
+#[test]
+fn histogram_statistics() {
+    let histogram = Histogram::new();
+    assert_eq!(histogram.snapshot().mean(), Duration::ZERO);
+    assert_eq!(histogram.snapshot().percentile(0.99), Duration::ZERO);
+
+    for _ in 0..99 {
+        histogram.record(Duration::from_nanos(100));
+    }
+    histogram.record(Duration::from_nanos(10_100));
+
+    let snapshot = histogram.snapshot();
+    assert_eq!(snapshot.total, Duration::from_nanos(20_000));
+    assert_eq!(snapshot.mean(), Duration::from_nanos(200));
+    // Percentiles are reported as the upper bound of their bucket.
+    assert_eq!(snapshot.percentile(0.5), Duration::from_nanos(128));
+    assert_eq!(snapshot.percentile(0.99), Duration::from_nanos(128));
+    assert_eq!(snapshot.percentile(1.0), Duration::from_nanos(16_384));
+}
+
+#[test]
+fn reset_clears_counters() {
+    static METRICS: EventMetrics = EventMetrics::new("Local");
+
+    let timer = metrics::Timer::start(&METRICS);
+    drop(timer);
+    assert_eq!(METRICS.snapshot().calls, 1);
+    assert_eq!(METRICS.snapshot().handler.count(), 1);
+
+    METRICS.reset();
+    let snapshot = METRICS.snapshot();
+    assert_eq!(snapshot.name, "Local");
+    assert_eq!(snapshot.calls, 0);
+    assert_eq!(snapshot.handler.count(), 0);
+}
+
+#[test]
+fn handlers_are_registered() {
+    let probe = metrics::find(b"HandleMetricsProbe").unwrap();
+    assert_eq!(probe.name(), "HandleMetricsProbe");
+    assert!(metrics::find(b"HandleMetricsProb").is_none());
+
+    let before = probe.snapshot().calls;
+    let event = Event {
+        name: "HandleMetricsProbe".to_string(),
+        record: None,
+    };
+    unsafe { replay::replay(&event) }.unwrap();
+    assert_eq!(probe.snapshot().calls, before + 1);
+
+    assert!(metrics::snapshot()
+        .iter()
+        .any(|s| s.name == "HandleMetricsProbe"));
+}
+
+#[test]
+fn dump_table() {
+    let mut out = Vec::new();
+    metrics::dump(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+
+    let mut lines = out.lines();
+    let header: Vec<_> = lines.next().unwrap().split_whitespace().collect();
+    assert_eq!(
+        header,
+        [
+            "event", "calls", "fired", "panics", "rust", "mean", "rust", "p99", "orig", "mean",
+            "orig", "p99"
+        ]
+    );
+
+    let rows: Vec<_> = lines.collect();
+    assert!(rows[0].starts_with("SendEvent "));
+    assert!(rows
+        .iter()
+        .any(|row| row.starts_with("HandleMetricsProbe ")));
+    // All columns are aligned to the longest event name.
+    assert!(rows
+        .iter()
+        .all(|row| row.as_bytes()["HandleMetricsProbe".len()] == b' '));
+}
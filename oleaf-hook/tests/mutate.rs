@@ -0,0 +1,245 @@
+use std::{alloc::Layout, cell::Cell};
+
+use oleaf_hook::{
+    cxx::{
+        alloc::{Allocator, Global},
+        Str, WStr,
+    },
+    dml::{Field, FieldError, RecordBuf, RecordBuilder, ToOwnedError, TypeId},
+};
+
+// Forwards to `Global` while counting the live allocations.
+#[derive(Default)]
+struct Counting {
+    live: Cell<isize>,
+    total: Cell<usize>,
+}
+
+unsafe impl Allocator for Counting {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        self.live.set(self.live.get() + 1);
+        self.total.set(self.total.get() + 1);
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        self.live.set(self.live.get() - 1);
+        Global.deallocate(ptr, layout)
+    }
+}
+
+fn quest_dialog() -> RecordBuf {
+    RecordBuilder::new()
+        .uint("QuestID", 42)
+        .str("Speaker", "Gamma")
+        .wstr("Title", "Über")
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn set_numbers() {
+    let mut buf = quest_dialog();
+
+    unsafe {
+        let record = &mut *buf.as_mut_ptr();
+        record.get_mut("QuestID").unwrap().set_u32(7).unwrap();
+        assert_eq!(record.get_u32("QuestID"), Ok(7));
+
+        assert_eq!(
+            record.get_mut("QuestID").unwrap().set_i32(7),
+            Err(FieldError::TypeMismatch {
+                name: "QuestID".to_string(),
+                expected: TypeId::Int,
+                found: TypeId::UInt,
+            })
+        );
+        assert!(record.get_mut("GoalID").is_none());
+    }
+}
+
+#[test]
+fn set_str() {
+    let mut buf = quest_dialog();
+
+    // The builder's strings live on the global heap, so they may be
+    // grown and shrunk through it.
+    unsafe {
+        let record = &mut *buf.as_mut_ptr();
+        let field = record.get_mut("Speaker").unwrap();
+
+        field.set_str(b"Merle Ambrose", &Global).unwrap();
+        let speaker = record.get_str("Speaker").unwrap();
+        assert_eq!(speaker.as_bytes(), b"Merle Ambrose");
+        assert_eq!(speaker.capacity(), 15);
+
+        let long = b"Headmaster Merle Ambrose\0of Ravenwood";
+        record
+            .get_mut("Speaker")
+            .unwrap()
+            .set_str(long, &Global)
+            .unwrap();
+        let speaker = record.get_str("Speaker").unwrap();
+        assert_eq!(speaker.as_bytes(), long);
+        assert_eq!(speaker.capacity(), 47);
+
+        // Shrinking keeps the heap storage.
+        record
+            .get_mut("Speaker")
+            .unwrap()
+            .set_str(b"", &Global)
+            .unwrap();
+        let speaker = record.get_str("Speaker").unwrap();
+        assert!(speaker.is_empty());
+        assert_eq!(speaker.capacity(), 47);
+
+        assert!(matches!(
+            record.get_mut("Title").unwrap().set_str(b"", &Global),
+            Err(FieldError::TypeMismatch {
+                expected: TypeId::Str,
+                found: TypeId::WStr,
+                ..
+            })
+        ));
+    }
+    // Dropping the record frees the grown storage.
+}
+
+#[test]
+fn set_wstr() {
+    let mut buf = quest_dialog();
+
+    unsafe {
+        let record = &mut *buf.as_mut_ptr();
+
+        record
+            .get_mut("Title")
+            .unwrap()
+            .set_wstr("Ünicorn", &Global)
+            .unwrap();
+        let title = record.get_wstr("Title").unwrap();
+        assert_eq!(title.decode_utf16(), "Ünicorn");
+        assert_eq!(title.capacity(), 7);
+
+        // The emoji takes two code units.
+        record
+            .get_mut("Title")
+            .unwrap()
+            .set_wstr("The Lost 🦄", &Global)
+            .unwrap();
+        let title = record.get_wstr("Title").unwrap();
+        assert_eq!(title.decode_utf16(), "The Lost 🦄");
+        assert_eq!(title.len(), 11);
+        assert_eq!(title.capacity(), 15);
+
+        assert!(matches!(
+            record.get_mut("Speaker").unwrap().set_wstr("", &Global),
+            Err(FieldError::TypeMismatch {
+                expected: TypeId::WStr,
+                found: TypeId::Str,
+                ..
+            })
+        ));
+    }
+}
+
+#[test]
+fn str_assign_storage() {
+    let alloc = Counting::default();
+
+    let mut s = Str::new_in(b"Gamma", &alloc);
+    assert_eq!((s.as_bytes(), s.capacity()), (&b"Gamma"[..], 15));
+    assert_eq!(alloc.total.get(), 0);
+
+    unsafe {
+        // Leaving the inline buffer grows to the next multiple of 16.
+        s.assign(&[b'x'; 20], &alloc);
+        assert_eq!((s.len(), s.capacity()), (20, 31));
+        assert_eq!((alloc.live.get(), alloc.total.get()), (1, 1));
+
+        // Data that fits is copied into the existing storage.
+        s.assign(&[b'y'; 31], &alloc);
+        assert_eq!(s.as_bytes(), [b'y'; 31]);
+        assert_eq!(alloc.total.get(), 1);
+
+        // Growing by a little still grows by half of the capacity.
+        s.assign(&[b'z'; 32], &alloc);
+        assert_eq!((s.len(), s.capacity()), (32, 47));
+        assert_eq!((alloc.live.get(), alloc.total.get()), (1, 2));
+
+        s.assign(&[b'w'; 100], &alloc);
+        assert_eq!((s.len(), s.capacity()), (100, 111));
+        assert_eq!((alloc.live.get(), alloc.total.get()), (1, 3));
+
+        s.assign(b"short", &alloc);
+        assert_eq!((s.as_bytes(), s.capacity()), (&b"short"[..], 111));
+        assert_eq!(alloc.live.get(), 1);
+    }
+    // `Str` never frees its storage; it is meant for C++ code to own.
+}
+
+#[test]
+fn wstr_assign_storage() {
+    let alloc = Counting::default();
+
+    let mut s = WStr::new_in("Über", &alloc);
+    assert_eq!((s.len(), s.capacity()), (4, 7));
+    assert_eq!(alloc.total.get(), 0);
+
+    unsafe {
+        s.assign("Headmaster", &alloc);
+        assert_eq!(s.decode_utf16(), "Headmaster");
+        assert_eq!(s.capacity(), 15);
+        assert_eq!((alloc.live.get(), alloc.total.get()), (1, 1));
+
+        s.assign("Headmaster Merle Ambrose", &alloc);
+        assert_eq!((s.len(), s.capacity()), (24, 31));
+        assert_eq!((alloc.live.get(), alloc.total.get()), (1, 2));
+
+        s.assign("", &alloc);
+        assert!(s.as_utf16().is_empty());
+        assert_eq!(s.capacity(), 31);
+        assert_eq!(alloc.live.get(), 1);
+    }
+}
+
+// The offsets of the string storage pointers within a `Field`.
+const STR_STORAGE_OFFSET: usize = 0x18;
+const WSTR_STORAGE_OFFSET: usize = 0x20;
+
+#[test]
+fn null_string_storage() {
+    let mut buf = quest_dialog();
+
+    unsafe {
+        let record = &mut *buf.as_mut_ptr();
+        for (index, offset) in [(1, STR_STORAGE_OFFSET), (2, WSTR_STORAGE_OFFSET)] {
+            let field = &mut record.fields_mut()[index] as *mut Field as *mut u8;
+            *(field.add(offset) as *mut usize) = 0;
+        }
+
+        let speaker = FieldError::NullString {
+            name: "Speaker".to_string(),
+        };
+        let title = FieldError::NullString {
+            name: "Title".to_string(),
+        };
+        assert_eq!(record.get_str("Speaker").err(), Some(speaker.clone()));
+        assert_eq!(record.get_as::<String>("Title"), Err(title.clone()));
+        assert_eq!(speaker.to_string(), "string field 'Speaker' is null");
+
+        let alloc = Counting::default();
+        let field = record.get_mut("Speaker").unwrap();
+        assert_eq!(field.set_str(b"Merle", &alloc), Err(speaker));
+        let field = record.get_mut("Title").unwrap();
+        assert_eq!(field.set_wstr("Hello", &alloc), Err(title));
+        assert_eq!(alloc.total.get(), 0);
+
+        assert_eq!(
+            record.to_owned(),
+            Err(ToOwnedError::NullString {
+                field: "Speaker".to_string()
+            })
+        );
+    }
+}
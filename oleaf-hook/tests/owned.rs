@@ -0,0 +1,78 @@
+use oleaf_hook::dml::{OwnedField, OwnedRecord, OwnedValue, RecordBuilder, ToOwnedError};
+
+fn field(name: &str, value: OwnedValue) -> OwnedField {
+    OwnedField {
+        name: name.to_string(),
+        value,
+    }
+}
+
+fn quest_dialog() -> OwnedRecord {
+    OwnedRecord {
+        fields: vec![
+            field("QuestID", OwnedValue::UInt(42)),
+            field("Mob", OwnedValue::Gid(0x1122_3344_5566_7788)),
+            field("Scale", OwnedValue::Flt(1.5)),
+            field("Speaker", OwnedValue::Str(b"a\xffb".to_vec())),
+            field("Title", OwnedValue::WStr("Über".to_string())),
+        ],
+    }
+}
+
+#[test]
+fn record_to_owned() {
+    let expected = quest_dialog();
+    let buf = RecordBuilder::from_owned(&expected).build().unwrap();
+
+    let owned = unsafe { buf.record().to_owned() }.unwrap();
+    assert_eq!(owned, expected);
+}
+
+#[test]
+fn binary_str_is_kept() {
+    let buf = RecordBuilder::new()
+        .str("Speaker", b"a\xffb".to_vec())
+        .build()
+        .unwrap();
+
+    let owned = unsafe { buf.record().to_owned() }.unwrap();
+    assert_eq!(owned.fields[0].value, OwnedValue::Str(b"a\xffb".to_vec()));
+}
+
+#[test]
+fn invalid_wstr_is_rejected() {
+    let buf = RecordBuilder::new()
+        .wstr_units("Title", vec![0x41, 0xd800, 0x42])
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        unsafe { buf.record().to_owned() },
+        Err(ToOwnedError::InvalidString {
+            field: "Title".to_string()
+        })
+    );
+}
+
+#[test]
+fn invalid_name_is_rejected() {
+    let buf = RecordBuilder::new()
+        .uint(b"Quest\xffID".to_vec(), 1)
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        unsafe { buf.record().to_owned() },
+        Err(ToOwnedError::InvalidName)
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_roundtrip() {
+    let record = quest_dialog();
+
+    let json = serde_json::to_string(&record).unwrap();
+    let read: OwnedRecord = serde_json::from_str(&json).unwrap();
+    assert_eq!(read, record);
+}
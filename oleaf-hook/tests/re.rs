@@ -0,0 +1,229 @@
+use oleaf_hook::{
+    dml::{RecordBuilder, TypeId},
+    re::{AddressRanges, ByteKind, Correlated, DmlSurvey, PointerCounts, PointerKind, RegionStats},
+};
+
+fn ranges() -> AddressRanges {
+    AddressRanges::new(
+        vec![
+            0x7000..0x8000,
+            0x1000..0x2000,
+            0x1800..0x2800,
+            0x5000..0x5000,
+        ],
+        vec![0x3000..0x4000, 0x4000..0x4800],
+    )
+}
+
+#[test]
+fn normalized_ranges() {
+    let ranges = ranges();
+
+    assert_eq!(ranges.module(), [0x1000..0x2800, 0x7000..0x8000]);
+    assert_eq!(ranges.heap(), [0x3000..0x4800]);
+}
+
+#[test]
+fn classify() {
+    let ranges = ranges();
+
+    for (value, kind) in [
+        (0, PointerKind::Null),
+        (0xfff, PointerKind::Other),
+        (0x1000, PointerKind::Module),
+        (0x2000, PointerKind::Module),
+        (0x27ff, PointerKind::Module),
+        (0x2800, PointerKind::Other),
+        (0x3000, PointerKind::Heap),
+        (0x4000, PointerKind::Heap),
+        (0x4800, PointerKind::Other),
+        (0x5000, PointerKind::Other),
+        (0x7fff, PointerKind::Module),
+        (u64::MAX, PointerKind::Other),
+    ] {
+        assert_eq!(ranges.classify(value), kind, "{:#x}", value);
+    }
+
+    assert_eq!(
+        AddressRanges::default().classify(0x1000),
+        PointerKind::Other
+    );
+}
+
+#[test]
+fn byte_kinds() {
+    let mut stats = RegionStats::new(0x10, 3);
+    assert_eq!(stats.range(), 0x10..0x13);
+    assert_eq!(stats.byte(0x10), ByteKind::Unseen);
+
+    for i in 0..9u8 {
+        stats.observe(&[7, i % 3, i], &AddressRanges::default());
+    }
+
+    assert_eq!(stats.samples(), 9);
+    assert_eq!(stats.byte(0x10), ByteKind::Constant(7));
+    assert_eq!(stats.byte(0x11), ByteKind::Enum(vec![0, 1, 2]));
+    assert_eq!(stats.byte(0x12), ByteKind::Varying);
+}
+
+#[test]
+fn enum_limit() {
+    let mut stats = RegionStats::new(0, 1);
+    for i in 0..8u8 {
+        stats.observe(&[i * 2], &AddressRanges::default());
+    }
+    assert_eq!(
+        stats.byte(0),
+        ByteKind::Enum(vec![0, 2, 4, 6, 8, 10, 12, 14])
+    );
+
+    stats.observe(&[1], &AddressRanges::default());
+    assert_eq!(stats.byte(0), ByteKind::Varying);
+}
+
+#[test]
+#[should_panic(expected = "sample has the wrong length")]
+fn wrong_sample_length() {
+    RegionStats::new(0, 4).observe(&[0; 3], &AddressRanges::default());
+}
+
+#[test]
+#[should_panic(expected = "offset is out of bounds")]
+fn byte_out_of_bounds() {
+    RegionStats::new(0x10, 4).byte(0x14);
+}
+
+#[test]
+fn pointer_slots() {
+    // Only the slots at 0x8 and 0x10 are aligned and fully covered.
+    let mut stats = RegionStats::new(0x4, 0x16);
+    let ranges = ranges();
+
+    let sample = |slot0: u64, slot1: u64| {
+        let mut bytes = vec![0xaa; 4];
+        bytes.extend(slot0.to_le_bytes());
+        bytes.extend(slot1.to_le_bytes());
+        bytes.extend([0xbb; 2]);
+        bytes
+    };
+    stats.observe(&sample(0x1000, 0), &ranges);
+    stats.observe(&sample(0x3000, 0x9000), &ranges);
+    stats.observe(&sample(0, 0x9234), &ranges);
+
+    assert_eq!(
+        stats.pointers(0x8),
+        Some(PointerCounts {
+            null: 1,
+            module: 1,
+            heap: 1,
+            other: 0,
+        })
+    );
+    assert_eq!(
+        stats.pointers(0x10),
+        Some(PointerCounts {
+            null: 1,
+            module: 0,
+            heap: 0,
+            other: 2,
+        })
+    );
+    assert!(stats.pointers(0x8).unwrap().is_pointer_like());
+    assert!(!stats.pointers(0x10).unwrap().is_pointer_like());
+
+    for offset in [0x0, 0x4, 0x9, 0x18] {
+        assert_eq!(stats.pointers(offset), None, "{:#x}", offset);
+    }
+}
+
+#[test]
+fn region_display() {
+    let mut stats = RegionStats::new(0x8, 9);
+    let ranges = ranges();
+    stats.observe(&[0x00, 0x10, 0, 0, 0, 0, 0, 0, 1], &ranges);
+    stats.observe(&[0x00, 0x30, 0, 0, 0, 0, 0, 0, 2], &ranges);
+
+    assert_eq!(
+        stats.to_string(),
+        concat!(
+            "0x8..0x11, 2 samples\n",
+            "  0x0008  pointer: 0 null, 1 module, 1 heap, 0 other\n",
+            "  0x0008  const 00\n",
+            "  0x0009  enum 10 30\n",
+            "  0x000a  const 00\n",
+            "  0x000b  const 00\n",
+            "  0x000c  const 00\n",
+            "  0x000d  const 00\n",
+            "  0x000e  const 00\n",
+            "  0x000f  const 00\n",
+            "  0x0010  enum 01 02\n",
+        )
+    );
+}
+
+#[test]
+fn correlated() {
+    let mut stats = Correlated::new(0x31, 2);
+    let ranges = AddressRanges::default();
+    stats.observe(TypeId::WStr, &[1, 0], &ranges);
+    stats.observe(TypeId::Gid, &[2, 5], &ranges);
+    stats.observe(TypeId::WStr, &[1, 6], &ranges);
+
+    assert_eq!(stats.overall().samples(), 3);
+    assert_eq!(stats.overall().byte(0x31), ByteKind::Enum(vec![1, 2]));
+
+    // Keys are ordered by type, not by their names.
+    let keys: Vec<_> = stats.by_key().keys().copied().collect();
+    assert_eq!(keys, [TypeId::Gid, TypeId::WStr]);
+    assert_eq!(stats.by_key()[&TypeId::WStr].samples(), 2);
+
+    let text = stats.to_string();
+    assert!(text.ends_with("  0x0031  determined by key: GID=const 02 WSTR=const 01\n"));
+    assert!(!text.contains("0x0032  determined"));
+}
+
+#[test]
+fn correlated_without_keys() {
+    let ranges = AddressRanges::default();
+    let mut stats = Correlated::new(0x31, 2);
+    assert!(!stats.to_string().contains("determined by key"));
+
+    stats.observe(TypeId::WStr, &[1, 0], &ranges);
+    stats.observe(TypeId::WStr, &[2, 0], &ranges);
+    assert_eq!(stats.overall().byte(0x31), ByteKind::Enum(vec![1, 2]));
+    assert!(!stats.to_string().contains("determined by key"));
+}
+
+#[test]
+fn dml_survey() {
+    let quest = RecordBuilder::new()
+        .uint("QuestID", 42)
+        .wstr("Title", "Über")
+        .build()
+        .unwrap();
+    let timer = RecordBuilder::new().uint("Ticks", 1).build().unwrap();
+
+    let mut survey = DmlSurvey::new(AddressRanges::default());
+    unsafe {
+        survey.observe(quest.record());
+        survey.observe(timer.record());
+    }
+
+    let record = survey.record_region("_08").unwrap();
+    assert_eq!((record.range(), record.samples()), (0x8..0x18, 2));
+    assert_eq!(record.byte(0x8), ByteKind::Constant(0));
+
+    let field = survey.field_region("_38").unwrap();
+    assert_eq!(field.overall().range(), 0x38..0x50);
+    assert_eq!(field.overall().samples(), 3);
+    let keys: Vec<_> = field.by_key().keys().copied().collect();
+    assert_eq!(keys, [TypeId::UInt, TypeId::WStr]);
+
+    assert!(survey.field_region("_31").is_some());
+    assert!(survey.field_region("_70").is_some());
+    assert!(survey.field_region("name").is_none());
+
+    let text = survey.to_string();
+    assert!(text.starts_with("Record::_08: 0x8..0x18, 2 samples\n"));
+    assert!(text.contains("Field::_38: 0x38..0x50, 3 samples\n"));
+}
@@ -0,0 +1,162 @@
+use std::{cell::RefCell, ffi::c_void};
+
+use oleaf_hook::{
+    dml::{FieldError, FromRecord, RecordBuilder, TypeId},
+    replay::{self, CapturedField, Event, Outcome, Value},
+    DmlRecord,
+};
+
+#[derive(Debug, PartialEq, DmlRecord)]
+struct QuestDialog {
+    #[dml(rename = "QuestID")]
+    quest_id: u32,
+    #[dml(rename = "Title")]
+    title: String,
+    #[dml(rename = "Speaker")]
+    speaker: Vec<u8>,
+    #[dml(rename = "GoalID")]
+    goal_id: Option<u32>,
+    #[dml(rename = "Mob")]
+    mob: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, DmlRecord)]
+struct Unrenamed {
+    r#type: i32,
+}
+
+thread_local! {
+    static HANDLED: RefCell<Vec<u32>> = RefCell::new(Vec::new());
+}
+
+#[oleaf_hook::event("HandleTypedQuestDialog")]
+fn handle_typed_quest_dialog(_this: *mut c_void, #[record] dialog: QuestDialog) {
+    HANDLED.with(|handled| handled.borrow_mut().push(dialog.quest_id));
+    call_original!()
+}
+
+fn convert(builder: RecordBuilder) -> Result<QuestDialog, FieldError> {
+    let buf = builder.build().unwrap();
+    let converted = QuestDialog::try_from(&buf);
+    assert_eq!(unsafe { QuestDialog::from_record(buf.record()) }, converted);
+    converted
+}
+
+fn quest_dialog() -> RecordBuilder {
+    RecordBuilder::new()
+        .uint("QuestID", 42)
+        .wstr("Title", "Über")
+        .str("Speaker", b"Gamma\xff".to_vec())
+        .gid("Mob", 7)
+}
+
+#[test]
+fn derive_converts_record() {
+    assert_eq!(
+        convert(quest_dialog()),
+        Ok(QuestDialog {
+            quest_id: 42,
+            title: "Über".to_string(),
+            speaker: b"Gamma\xff".to_vec(),
+            goal_id: None,
+            mob: Some(7),
+        })
+    );
+
+    let buf = RecordBuilder::new().int("type", -1).build().unwrap();
+    assert_eq!(Unrenamed::try_from(&buf), Ok(Unrenamed { r#type: -1 }));
+}
+
+#[test]
+fn derive_reports_missing_field() {
+    let builder = RecordBuilder::new()
+        .wstr("Title", "Über")
+        .str("Speaker", b"Gamma".to_vec());
+
+    assert_eq!(
+        convert(builder),
+        Err(FieldError::Missing {
+            name: "QuestID".to_string()
+        })
+    );
+}
+
+#[test]
+fn derive_reports_type_mismatch() {
+    let builder = RecordBuilder::new()
+        .int("QuestID", 42)
+        .wstr("Title", "Über")
+        .str("Speaker", b"Gamma".to_vec());
+    assert_eq!(
+        convert(builder),
+        Err(FieldError::TypeMismatch {
+            name: "QuestID".to_string(),
+            expected: TypeId::UInt,
+            found: TypeId::Int,
+        })
+    );
+
+    // Optional fields are still type-checked when present.
+    let builder = quest_dialog().int("GoalID", 1);
+    assert_eq!(
+        convert(builder),
+        Err(FieldError::TypeMismatch {
+            name: "GoalID".to_string(),
+            expected: TypeId::UInt,
+            found: TypeId::Int,
+        })
+    );
+}
+
+fn typed_event(quest_id: Value) -> Event {
+    Event {
+        name: "HandleTypedQuestDialog".to_string(),
+        record: Some(vec![
+            CapturedField {
+                name: b"QuestID".to_vec(),
+                value: quest_id,
+            },
+            CapturedField {
+                name: b"Title".to_vec(),
+                value: Value::WStr("Title".encode_utf16().collect()),
+            },
+            CapturedField {
+                name: b"Speaker".to_vec(),
+                value: Value::Str(b"Gamma".to_vec()),
+            },
+        ]),
+    }
+}
+
+#[test]
+fn record_argument() {
+    HANDLED.with(|handled| handled.borrow_mut().clear());
+
+    let outcome = unsafe { replay::replay(&typed_event(Value::UInt(42))) }.unwrap();
+    assert_eq!(
+        outcome,
+        Outcome {
+            handled: true,
+            original_calls: 1
+        }
+    );
+    HANDLED.with(|handled| assert_eq!(*handled.borrow(), [42]));
+}
+
+#[test]
+fn record_argument_falls_back_to_original() {
+    HANDLED.with(|handled| handled.borrow_mut().clear());
+
+    // The handler body never runs, but the original function does.
+    let outcome = unsafe { replay::replay(&typed_event(Value::Int(42))) }.unwrap();
+    assert_eq!(outcome.original_calls, 1);
+
+    let event = Event {
+        name: "HandleTypedQuestDialog".to_string(),
+        record: None,
+    };
+    let outcome = unsafe { replay::replay(&event) }.unwrap();
+    assert_eq!(outcome.original_calls, 1);
+
+    HANDLED.with(|handled| assert!(handled.borrow().is_empty()));
+}
@@ -0,0 +1,125 @@
+use std::{
+    cell::RefCell,
+    ffi::c_void,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use oleaf_hook::{
+    dml::{FieldValue, Record},
+    replay::{self, CapturedField, Event, Outcome, Reader, Value, Writer},
+};
+
+static QUEST_ID: AtomicU32 = AtomicU32::new(0);
+
+thread_local! {
+    static TITLE: RefCell<String> = RefCell::new(String::new());
+}
+
+#[oleaf_hook::event("HandleQuestDialog")]
+fn handle_quest_dialog(this: *mut c_void, dml: *mut Record) {
+    for field in unsafe { (*dml).fields() } {
+        match unsafe { field.value() } {
+            Ok(FieldValue::UInt(id)) => QUEST_ID.store(id, Ordering::SeqCst),
+            Ok(FieldValue::WStr(title)) => {
+                TITLE.with(|t| *t.borrow_mut() = unsafe { title.decode_utf16() });
+            }
+            _ => (),
+        }
+    }
+
+    call_original!(this, dml)
+}
+
+// Not in the `(this, record)` shape, so neither may be replayed.
+#[oleaf_hook::event("HandleTimer")]
+fn handle_timer(this: *mut c_void, elapsed: u64) -> *mut c_void {
+    call_original!(this, elapsed)
+}
+
+#[oleaf_hook::event("HandleVolume")]
+fn handle_volume(this: *mut c_void, volume: f32) {
+    call_original!(this, volume)
+}
+
+fn quest_dialog() -> Event {
+    Event {
+        name: "HandleQuestDialog".to_string(),
+        record: Some(vec![
+            CapturedField {
+                name: b"QuestID".to_vec(),
+                value: Value::UInt(42),
+            },
+            CapturedField {
+                name: b"Title".to_vec(),
+                value: Value::WStr("A rather long quest title".encode_utf16().collect()),
+            },
+            CapturedField {
+                name: b"Speaker".to_vec(),
+                value: Value::Str(b"Gamma".to_vec()),
+            },
+        ]),
+    }
+}
+
+#[test]
+fn capture_roundtrip() {
+    let events = vec![
+        quest_dialog(),
+        Event {
+            name: "HandleSomethingElse".to_string(),
+            record: None,
+        },
+    ];
+
+    let mut writer = Writer::new(Vec::new()).unwrap();
+    for event in &events {
+        writer.write(event).unwrap();
+    }
+    let capture = writer.into_inner();
+
+    let read = Reader::new(&capture[..])
+        .unwrap()
+        .collect::<std::io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(read, events);
+}
+
+#[test]
+fn replay_registered_handler() {
+    let outcome = unsafe { replay::replay(&quest_dialog()) }.unwrap();
+
+    assert_eq!(
+        outcome,
+        Outcome {
+            handled: true,
+            original_calls: 1
+        }
+    );
+    assert_eq!(QUEST_ID.load(Ordering::SeqCst), 42);
+    TITLE.with(|t| assert_eq!(*t.borrow(), "A rather long quest title"));
+    assert!(!replay::is_active());
+}
+
+#[test]
+fn replay_unknown_event() {
+    let event = Event {
+        name: "HandleUnknown".to_string(),
+        record: None,
+    };
+    let outcome = unsafe { replay::replay(&event) }.unwrap();
+
+    assert!(!outcome.handled);
+}
+
+#[test]
+fn replay_skips_non_record_handlers() {
+    for name in ["HandleTimer", "HandleVolume"] {
+        let event = Event {
+            name: name.to_string(),
+            record: None,
+        };
+        let outcome = unsafe { replay::replay(&event) }.unwrap();
+
+        assert!(!outcome.handled, "{} was replayed", name);
+    }
+}
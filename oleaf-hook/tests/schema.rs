@@ -0,0 +1,300 @@
+use std::io;
+
+use oleaf_hook::dml::{
+    schema::{Change, Snapshot},
+    wire::FieldDef,
+    RecordBuilder, TypeId,
+};
+
+fn snapshot(kinds: &[(&str, &[(&str, TypeId)])]) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    for (kind, fields) in kinds {
+        let fields = fields
+            .iter()
+            .map(|&(name, type_id)| FieldDef::new(name, type_id))
+            .collect();
+        snapshot.insert(*kind, fields);
+    }
+    snapshot
+}
+
+fn round_trip(snapshot: &Snapshot) -> Snapshot {
+    let mut buf = Vec::new();
+    snapshot.write_to(&mut buf).unwrap();
+    Snapshot::read_from(&buf[..]).unwrap()
+}
+
+#[test]
+fn text_format() {
+    let snapshot = snapshot(&[
+        (
+            "HandleQuestDialog",
+            &[("QuestID", TypeId::UInt), ("Title", TypeId::WStr)],
+        ),
+        ("HandleTimer", &[("Ticks", TypeId::Unknown(42))]),
+    ]);
+
+    let mut buf = Vec::new();
+    snapshot.write_to(&mut buf).unwrap();
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "[HandleQuestDialog]\nQuestID UINT\nTitle WSTR\n\n[HandleTimer]\nTicks 42\n\n"
+    );
+    assert_eq!(round_trip(&snapshot), snapshot);
+}
+
+#[test]
+fn round_trip_odd_names() {
+    let snapshot = snapshot(&[
+        (
+            "Handle Quest",
+            &[
+                ("", TypeId::Int),
+                ("Trailing ", TypeId::UInt),
+                (" Leading", TypeId::Str),
+                ("Quest Title", TypeId::WStr),
+                ("\"Quoted\\\"", TypeId::Gid),
+                ("#Comment", TypeId::Flt),
+                ("[Bracket]", TypeId::Dbl),
+                ("Tab\tNew\nLine\0", TypeId::Byt),
+                ("Über\u{301}", TypeId::UByt),
+            ],
+        ),
+        ("", &[("Plain]", TypeId::UShrt)]),
+        ("Kind]", &[]),
+        (" ", &[]),
+        ("\"", &[]),
+    ]);
+
+    let mut buf = Vec::new();
+    snapshot.write_to(&mut buf).unwrap();
+    let text = String::from_utf8(buf).unwrap();
+    assert!(text.contains("[\"Handle Quest\"]\n\"\" INT\n\"Trailing \" UINT\n"));
+    assert!(text.contains("\n\"Quest Title\" WSTR\n"));
+
+    assert_eq!(round_trip(&snapshot), snapshot);
+}
+
+#[test]
+fn read_lenient() {
+    let text = "# Comment\n\n  [Kind]  \n  Two Words   INT \n";
+
+    let snapshot = Snapshot::read_from(text.as_bytes()).unwrap();
+    assert_eq!(
+        snapshot.get("Kind").unwrap(),
+        [FieldDef::new("Two Words", TypeId::Int)]
+    );
+}
+
+#[test]
+fn read_malformed() {
+    for text in [
+        "QuestID UINT\n",
+        "[Kind]\nQuestID\n",
+        "[Kind]\nQuestID QWORD\n",
+        "[Kind]\n\"QuestID UINT\n",
+        "[Kind]\n\"QuestID\"UINT\n",
+        "[Kind]\n\"Quest\\qID\" UINT\n",
+        "[Kind]\n\"Quest\\u{d800}\" UINT\n",
+        "[\"Kind]\n",
+        "[\"Kind\"x]\n",
+    ] {
+        let err = Snapshot::read_from(text.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", text);
+    }
+}
+
+#[test]
+fn observe_record() {
+    let buf = RecordBuilder::new()
+        .uint("QuestID", 42)
+        .wstr("Title", "Über")
+        .build()
+        .unwrap();
+    let other = RecordBuilder::new().int("Delta", 1).build().unwrap();
+
+    let mut snapshot = Snapshot::new();
+    unsafe {
+        snapshot.observe("HandleQuestDialog", buf.record());
+        // Only the first layout of a kind is kept.
+        snapshot.observe("HandleQuestDialog", other.record());
+    }
+
+    assert_eq!(
+        snapshot.get("HandleQuestDialog").unwrap(),
+        [
+            FieldDef::new("QuestID", TypeId::UInt),
+            FieldDef::new("Title", TypeId::WStr),
+        ]
+    );
+}
+
+const KIND: &str = "HandleQuestDialog";
+
+fn diff(old: &[(&str, TypeId)], new: &[(&str, TypeId)]) -> Vec<Change> {
+    snapshot(&[(KIND, old)]).diff(&snapshot(&[(KIND, new)]))
+}
+
+#[test]
+fn diff_unchanged() {
+    let fields = [("QuestID", TypeId::UInt), ("Title", TypeId::WStr)];
+
+    assert!(diff(&fields, &fields).is_empty());
+}
+
+#[test]
+fn diff_added_and_removed() {
+    let changes = diff(
+        &[("QuestID", TypeId::UInt), ("Title", TypeId::WStr)],
+        &[("QuestID", TypeId::UInt), ("GoalID", TypeId::Gid)],
+    );
+
+    assert_eq!(
+        changes,
+        [
+            Change::Removed {
+                kind: KIND.to_string(),
+                field: FieldDef::new("Title", TypeId::WStr),
+            },
+            Change::Added {
+                kind: KIND.to_string(),
+                field: FieldDef::new("GoalID", TypeId::Gid),
+            },
+        ]
+    );
+    assert_eq!(
+        changes[0].to_string(),
+        "HandleQuestDialog: removed Title WSTR"
+    );
+    assert_eq!(
+        changes[1].to_string(),
+        "HandleQuestDialog: added GoalID GID"
+    );
+}
+
+#[test]
+fn diff_renamed() {
+    let changes = diff(
+        &[
+            ("QuestID", TypeId::UInt),
+            ("Title", TypeId::WStr),
+            ("Delta", TypeId::Int),
+        ],
+        &[
+            ("QuestID", TypeId::UInt),
+            ("QuestTitle", TypeId::WStr),
+            ("Delta", TypeId::Int),
+        ],
+    );
+
+    assert_eq!(
+        changes,
+        [Change::Renamed {
+            kind: KIND.to_string(),
+            from: "Title".to_string(),
+            to: "QuestTitle".to_string(),
+            type_id: TypeId::WStr,
+        }]
+    );
+    assert_eq!(
+        changes[0].to_string(),
+        "HandleQuestDialog: renamed Title to QuestTitle (WSTR)"
+    );
+}
+
+#[test]
+fn diff_rename_needs_same_slot() {
+    // The new field is elsewhere, so the old one was removed instead.
+    let changes = diff(
+        &[("Title", TypeId::WStr), ("QuestID", TypeId::UInt)],
+        &[("QuestID", TypeId::UInt), ("QuestTitle", TypeId::WStr)],
+    );
+
+    assert_eq!(
+        changes,
+        [
+            Change::Removed {
+                kind: KIND.to_string(),
+                field: FieldDef::new("Title", TypeId::WStr),
+            },
+            Change::Added {
+                kind: KIND.to_string(),
+                field: FieldDef::new("QuestTitle", TypeId::WStr),
+            },
+        ]
+    );
+}
+
+#[test]
+fn diff_retyped() {
+    let changes = diff(
+        &[("QuestID", TypeId::UInt), ("Title", TypeId::WStr)],
+        &[("QuestID", TypeId::Gid), ("Title", TypeId::WStr)],
+    );
+
+    assert_eq!(
+        changes,
+        [Change::Retyped {
+            kind: KIND.to_string(),
+            name: "QuestID".to_string(),
+            from: TypeId::UInt,
+            to: TypeId::Gid,
+        }]
+    );
+    assert_eq!(
+        changes[0].to_string(),
+        "HandleQuestDialog: retyped QuestID from UINT to GID"
+    );
+}
+
+#[test]
+fn diff_moved() {
+    let changes = diff(
+        &[
+            ("QuestID", TypeId::UInt),
+            ("Title", TypeId::WStr),
+            ("Delta", TypeId::Int),
+        ],
+        &[
+            ("Delta", TypeId::Int),
+            ("QuestID", TypeId::UInt),
+            ("Title", TypeId::WStr),
+        ],
+    );
+
+    // Only the field that broke the order of the others moved.
+    assert_eq!(
+        changes,
+        [Change::Moved {
+            kind: KIND.to_string(),
+            name: "Delta".to_string(),
+            from: 2,
+            to: 0,
+        }]
+    );
+    assert_eq!(
+        changes[0].to_string(),
+        "HandleQuestDialog: moved Delta from #2 to #0"
+    );
+}
+
+#[test]
+fn diff_kinds() {
+    let old = snapshot(&[("HandleA", &[]), ("HandleB", &[])]);
+    let new = snapshot(&[("HandleB", &[]), ("HandleC", &[])]);
+
+    let changes = old.diff(&new);
+    assert_eq!(
+        changes,
+        [
+            Change::KindRemoved {
+                kind: "HandleA".to_string()
+            },
+            Change::KindAdded {
+                kind: "HandleC".to_string()
+            },
+        ]
+    );
+    assert_eq!(changes[0].to_string(), "HandleA: record kind is gone");
+    assert_eq!(changes[1].to_string(), "HandleC: new record kind");
+}
@@ -0,0 +1,137 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use oleaf_hook::trace::{Config, Entry, Format, Recorder, BINARY_MAGIC, BINARY_VERSION};
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("oleaf-trace-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn entry(name: &[u8]) -> Entry<'_> {
+    Entry {
+        timestamp: Duration::from_micros(1_500),
+        thread_id: 7,
+        dispatcher: 0xdead_beef,
+        name,
+        handled: true,
+    }
+}
+
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    PathBuf::from(format!("{}.{}", path.display(), n))
+}
+
+#[test]
+fn binary_entry_layout() {
+    let mut buf = Vec::new();
+    entry(b"Ping").write_to(&mut buf, Format::Binary).unwrap();
+
+    let mut expected = Vec::new();
+    expected.extend_from_slice(&1_500u64.to_le_bytes());
+    expected.extend_from_slice(&7u32.to_le_bytes());
+    expected.extend_from_slice(&0xdead_beefu64.to_le_bytes());
+    expected.push(1);
+    expected.extend_from_slice(&4u16.to_le_bytes());
+    expected.extend_from_slice(b"Ping");
+    assert_eq!(buf, expected);
+}
+
+#[test]
+fn json_lines_escaping() {
+    let mut buf = Vec::new();
+    entry(b"a\"b\\c\nd\te\x01\xff")
+        .write_to(&mut buf, Format::JsonLines)
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "{\"ts\":1500,\"tid\":7,\"dispatcher\":\"0xdeadbeef\",\
+         \"name\":\"a\\\"b\\\\c\\nd\\te\\u0001\u{fffd}\",\"handled\":true}\n"
+    );
+}
+
+#[test]
+fn prefix_filter() {
+    let config = Config {
+        filter: vec!["HandleQuest*".to_string(), "Ping".to_string()],
+        ..Config::default()
+    };
+
+    assert!(config.accepts(b"HandleQuestDialog"));
+    assert!(config.accepts(b"HandleQuest"));
+    assert!(config.accepts(b"Ping"));
+    assert!(!config.accepts(b"PingPong"));
+    assert!(!config.accepts(b"HandleQues"));
+
+    assert!(Config::default().accepts(b"Anything"));
+}
+
+#[test]
+fn rotation_keeps_max_files() {
+    let dir = scratch_dir("rotation");
+    let path = dir.join("events.trace");
+
+    let mut single = Vec::new();
+    entry(b"Ping")
+        .write_to(&mut single, Format::Binary)
+        .unwrap();
+    let header = BINARY_MAGIC.len() as u64 + 1;
+
+    // Every file holds exactly two entries.
+    let mut recorder = Recorder::open(Config {
+        path: path.clone(),
+        format: Format::Binary,
+        max_file_size: header + 2 * single.len() as u64,
+        max_files: 2,
+        filter: Vec::new(),
+    })
+    .unwrap();
+    for _ in 0..7 {
+        recorder.write(&entry(b"Ping")).unwrap();
+    }
+    recorder.flush().unwrap();
+
+    let current = fs::read(&path).unwrap();
+    assert_eq!(&current[..4], BINARY_MAGIC);
+    assert_eq!(current[4], BINARY_VERSION);
+    assert_eq!(current.len() as u64, header + single.len() as u64);
+
+    for n in 1..=2 {
+        let len = fs::metadata(rotated(&path, n)).unwrap().len();
+        assert_eq!(len, header + 2 * single.len() as u64);
+    }
+    assert!(!rotated(&path, 3).exists());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn rotation_without_backups() {
+    let dir = scratch_dir("no-backups");
+    let path = dir.join("events.jsonl");
+
+    let mut recorder = Recorder::open(Config {
+        path: path.clone(),
+        format: Format::JsonLines,
+        max_file_size: 1,
+        max_files: 0,
+        filter: Vec::new(),
+    })
+    .unwrap();
+    recorder.write(&entry(b"First")).unwrap();
+    recorder.write(&entry(b"Second")).unwrap();
+    recorder.flush().unwrap();
+
+    let contents = fs::read_to_string(&path).unwrap();
+    assert_eq!(contents.lines().count(), 1);
+    assert!(contents.contains("\"name\":\"Second\""));
+    assert!(!rotated(&path, 1).exists());
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,191 @@
+use std::io;
+
+use oleaf_hook::dml::{
+    wire::{self, FieldDef, WireType},
+    Field, FieldError, FieldValue, FromField, OwnedField, OwnedRecord, OwnedValue, RecordBuf,
+    RecordBuilder, ToOwnedError, TypeId,
+};
+
+// The offset of the raw type ID within a `Field`.
+const TYPE_ID_OFFSET: usize = 0x30;
+
+fn owned() -> OwnedRecord {
+    let field = |name: &str, value| OwnedField {
+        name: name.to_string(),
+        value,
+    };
+
+    OwnedRecord {
+        fields: vec![
+            field(
+                "Payload",
+                OwnedValue::Blob(b"\x00\x01\xffdata\x00".to_vec()),
+            ),
+            field("Delta", OwnedValue::Long(-0x0123_4567_89ab_cdef)),
+            field("Total", OwnedValue::ULong(u64::MAX)),
+        ],
+    }
+}
+
+fn set_raw_type(buf: &mut RecordBuf, index: usize, raw: u8) {
+    unsafe {
+        let field = &mut (*buf.as_mut_ptr()).fields_mut()[index] as *mut Field as *mut u8;
+        *field.add(TYPE_ID_OFFSET) = raw;
+    }
+}
+
+#[test]
+fn raw_round_trip() {
+    for raw in 0..=u8::MAX {
+        let type_id = TypeId::from_raw(raw);
+        assert_eq!(type_id.to_raw(), Some(raw));
+        assert_eq!(
+            matches!(type_id, TypeId::Unknown(_)),
+            !(1..=10).contains(&raw)
+        );
+    }
+
+    for type_id in [TypeId::Blob, TypeId::Long, TypeId::ULong] {
+        assert_eq!(type_id.to_raw(), None);
+    }
+}
+
+#[test]
+fn name_round_trip() {
+    let types = (1..=10)
+        .map(TypeId::from_raw)
+        .chain([TypeId::Blob, TypeId::Long, TypeId::ULong]);
+    for type_id in types {
+        let name = type_id.name().unwrap();
+        assert_eq!(TypeId::from_name(name), Some(type_id));
+        assert_eq!(type_id.to_string(), name);
+    }
+
+    assert_eq!(TypeId::from_name("BLOB"), Some(TypeId::Blob));
+    assert_eq!(TypeId::from_name("LONG"), Some(TypeId::Long));
+    assert_eq!(TypeId::from_name("ULONG"), Some(TypeId::ULong));
+    assert_eq!(TypeId::from_name("QWORD"), None);
+    assert_eq!(TypeId::Unknown(200).name(), None);
+    assert_eq!(TypeId::Unknown(200).to_string(), "UNKNOWN(200)");
+}
+
+#[test]
+fn unknown_type() {
+    let mut buf = RecordBuilder::new()
+        .int("Count", 1)
+        .gid("Total", 7)
+        .build()
+        .unwrap();
+
+    // Raw IDs past `WStr` are not read from guessed storage.
+    for raw in [11, 12, 13, 200] {
+        set_raw_type(&mut buf, 1, raw);
+        let record = buf.record();
+
+        let field = &unsafe { record.fields() }[1];
+        assert_eq!(field.type_id(), TypeId::Unknown(raw));
+
+        let expected = FieldError::UnknownType {
+            name: "Total".to_string(),
+            type_id: raw,
+        };
+        assert_eq!(unsafe { field.value() }.err(), Some(expected.clone()));
+        assert_eq!(unsafe { record.get_u64("Total") }, Err(expected.clone()));
+        assert_eq!(unsafe { record.get_as::<u64>("Total") }, Err(expected));
+        assert_eq!(
+            unsafe { record.to_owned() },
+            Err(ToOwnedError::UnknownType {
+                field: "Total".to_string()
+            })
+        );
+    }
+
+    assert_eq!(
+        FieldError::UnknownType {
+            name: "Total".to_string(),
+            type_id: 200,
+        }
+        .to_string(),
+        "field 'Total' has unknown type ID 200"
+    );
+}
+
+#[test]
+fn build_without_raw_id() {
+    for field in owned().fields {
+        let err = RecordBuilder::new()
+            .int("Count", 1)
+            .value(field.name.as_str(), &field.value)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "field '{}' has a type whose raw ID is not known",
+                field.name
+            )
+        );
+    }
+}
+
+#[test]
+fn from_wide_values() {
+    let buf = RecordBuilder::new()
+        .str("Payload", b"\x00\x01\xffdata\x00".to_vec())
+        .build()
+        .unwrap();
+    let payload = unsafe { buf.record().get_str("Payload") }.unwrap();
+
+    unsafe {
+        assert_eq!(u64::from_value(FieldValue::ULong(u64::MAX)), Some(u64::MAX));
+        assert_eq!(u64::from_value(FieldValue::Gid(7)), Some(7));
+        assert_eq!(u64::from_value(FieldValue::Long(7)), None);
+        assert_eq!(i64::from_value(FieldValue::Long(i64::MIN)), Some(i64::MIN));
+        assert_eq!(i64::from_value(FieldValue::ULong(7)), None);
+        assert_eq!(
+            Vec::<u8>::from_value(FieldValue::Blob(payload)),
+            Some(b"\x00\x01\xffdata\x00".to_vec())
+        );
+    }
+}
+
+#[test]
+fn wide_value_formatting() {
+    let buf = RecordBuilder::new()
+        .str("Payload", b"\x00\x01\xffdata\x00".to_vec())
+        .build()
+        .unwrap();
+    let payload = unsafe { buf.record().get_str("Payload") }.unwrap();
+
+    let value = FieldValue::ULong(u64::MAX);
+    assert_eq!(format!("{:?}", value), format!("ULong({})", u64::MAX));
+    assert_eq!(FieldValue::Long(-1).to_string(), "-1");
+    assert_eq!(FieldValue::Blob(payload).to_string(), "0001ff6461746100");
+}
+
+#[test]
+fn wire_wide_types() {
+    let schema = [
+        FieldDef::new("Payload", TypeId::Blob),
+        FieldDef::new("Delta", TypeId::Long),
+        FieldDef::new("Total", TypeId::ULong),
+    ];
+
+    let mut data = Vec::new();
+    wire::write_record(&mut data, &schema, &owned()).unwrap();
+    assert_eq!(data.len(), (2 + 8) + 8 + 8);
+    assert_eq!(&data[..3], [0x08, 0x00, 0x00]);
+    assert_eq!(wire::decode(&data, &schema).unwrap(), owned());
+
+    let mut out = Vec::new();
+    7u64.write(&mut out, "Total", TypeId::ULong).unwrap();
+    7u64.write(&mut out, "GlobalID", TypeId::Gid).unwrap();
+    let mut rest = &out[..];
+    assert_eq!(u64::read(&mut rest, "Total", TypeId::ULong).unwrap(), 7);
+    assert_eq!(u64::read(&mut rest, "GlobalID", TypeId::Gid).unwrap(), 7);
+
+    let err = i64::read(&mut &data[..], "Payload", TypeId::Blob).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,92 @@
+use std::{cell::Cell, ptr};
+
+use oleaf_hook::unwind;
+
+#[test]
+fn handler_result_is_returned() {
+    let originals = Cell::new(0);
+    let ret = unwind::contain(
+        "Test",
+        || 7u32,
+        || {
+            originals.set(originals.get() + 1);
+            0
+        },
+        || unreachable!(),
+    );
+
+    assert_eq!(ret, 7);
+    assert_eq!(originals.get(), 0);
+}
+
+#[test]
+fn original_runs_after_panic() {
+    let ret = unwind::contain("Test", || -> u32 { panic!("handler failed") }, || 42, || ());
+
+    assert_eq!(ret, 42);
+}
+
+#[test]
+fn original_is_not_called_twice() {
+    let originals = Cell::new(0);
+    let original = || {
+        originals.set(originals.get() + 1);
+        0x1000 as *mut u8
+    };
+
+    let ret = unwind::contain(
+        "Test",
+        || {
+            let ret = original();
+            unwind::mark_original_called();
+            if !ret.is_null() {
+                panic!("handler failed after calling the original");
+            }
+            ret
+        },
+        original,
+        || (),
+    );
+
+    assert_eq!(ret, ptr::null_mut());
+    assert_eq!(originals.get(), 1);
+}
+
+#[test]
+fn nested_frames_are_independent() {
+    let ret = unwind::contain(
+        "Outer",
+        || -> u32 {
+            unwind::mark_original_called();
+            // The inner hook has not called its original yet.
+            let inner = unwind::contain("Inner", || -> u32 { panic!("inner") }, || 1, || ());
+            assert_eq!(inner, 1);
+            panic!("outer")
+        },
+        || 2,
+        || (),
+    );
+
+    assert_eq!(ret, 0);
+}
+
+#[test]
+fn disable_on_panic() {
+    let disabled = Cell::new(0);
+    let run = || {
+        unwind::contain(
+            "Test",
+            || panic!("handler failed"),
+            || (),
+            || disabled.set(disabled.get() + 1),
+        )
+    };
+
+    run();
+    assert_eq!(disabled.get(), 0);
+
+    unwind::set_disable_on_panic(true);
+    run();
+    unwind::set_disable_on_panic(false);
+    assert_eq!(disabled.get(), 1);
+}
@@ -0,0 +1,153 @@
+use std::{alloc::Layout, cell::Cell, os::raw::c_void, rc::Rc};
+
+use oleaf_hook::cxx::{
+    alloc::{Allocator, GameHeap, Global},
+    VectorBuf,
+};
+
+extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+}
+
+thread_local! {
+    static MALLOC_CALLS: Cell<usize> = Cell::new(0);
+    static FREE_CALLS: Cell<usize> = Cell::new(0);
+}
+
+// Stand-ins for the client's `malloc` and `free` that count their calls.
+unsafe extern "C" fn mock_malloc(size: usize) -> *mut c_void {
+    MALLOC_CALLS.with(|c| c.set(c.get() + 1));
+    malloc(size)
+}
+
+unsafe extern "C" fn mock_free(ptr: *mut c_void) {
+    FREE_CALLS.with(|c| c.set(c.get() + 1));
+    free(ptr)
+}
+
+fn calls() -> (usize, usize) {
+    (MALLOC_CALLS.with(Cell::get), FREE_CALLS.with(Cell::get))
+}
+
+// Forwards to `Global` while counting the live allocations.
+#[derive(Default)]
+struct Counting {
+    live: Cell<isize>,
+}
+
+unsafe impl Allocator for Counting {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        self.live.set(self.live.get() + 1);
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        self.live.set(self.live.get() - 1);
+        Global.deallocate(ptr, layout)
+    }
+}
+
+unsafe impl Allocator for &Counting {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        (**self).allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        (**self).deallocate(ptr, layout)
+    }
+}
+
+// Counts how often any of its clones were dropped.
+#[derive(Clone, Debug)]
+struct Tracked(u32, Rc<Cell<usize>>);
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.1.set(self.1.get() + 1);
+    }
+}
+
+fn tracked(drops: &Rc<Cell<usize>>) -> Vec<Tracked> {
+    (0..3).map(|i| Tracked(i, drops.clone())).collect()
+}
+
+#[test]
+fn drop_frees_once() {
+    let drops = Rc::new(Cell::new(0));
+    let alloc = Counting::default();
+
+    let vector = VectorBuf::from_vec_in(tracked(&drops), &alloc);
+    assert_eq!((drops.get(), alloc.live.get()), (0, 1));
+    assert_eq!(vector.iter().map(|t| t.0).collect::<Vec<_>>(), [0, 1, 2]);
+
+    drop(vector);
+    assert_eq!((drops.get(), alloc.live.get()), (3, 0));
+}
+
+#[test]
+fn empty_does_not_allocate() {
+    let alloc = Counting::default();
+
+    let vector = VectorBuf::<Tracked, _>::from_vec_in(Vec::new(), &alloc);
+    assert!(vector.as_vector().as_ptr().is_null());
+    drop(vector);
+    assert_eq!(alloc.live.get(), 0);
+}
+
+#[test]
+fn into_vec_moves_elements() {
+    let drops = Rc::new(Cell::new(0));
+    let alloc = Counting::default();
+
+    let vec = VectorBuf::from_vec_in(tracked(&drops), &alloc).into_vec();
+    // The storage is gone, but the elements were moved out of it.
+    assert_eq!((drops.get(), alloc.live.get()), (0, 0));
+    assert_eq!(vec.iter().map(|t| t.0).collect::<Vec<_>>(), [0, 1, 2]);
+
+    drop(vec);
+    assert_eq!(drops.get(), 3);
+}
+
+#[test]
+fn into_iter_drops_remaining() {
+    let drops = Rc::new(Cell::new(0));
+    let alloc = Counting::default();
+
+    let mut iter = VectorBuf::from_vec_in(tracked(&drops), &alloc).into_iter();
+    assert_eq!(iter.next().map(|t| t.0), Some(0));
+    assert_eq!((drops.get(), alloc.live.get()), (1, 0));
+
+    drop(iter);
+    assert_eq!(drops.get(), 3);
+}
+
+#[test]
+fn into_raw_keeps_storage() {
+    let drops = Rc::new(Cell::new(0));
+    let heap = unsafe { GameHeap::new(mock_malloc, mock_free) };
+
+    let vector = VectorBuf::from_vec_in(tracked(&drops), heap);
+    assert_eq!(calls(), (1, 0));
+
+    let mut raw = vector.into_raw();
+    assert_eq!(calls(), (1, 0));
+    assert_eq!(drops.get(), 0);
+    assert_eq!((raw.len(), raw.capacity()), (3, 3));
+    assert_eq!(
+        unsafe { raw.as_slice() }
+            .iter()
+            .map(|t| t.0)
+            .collect::<Vec<_>>(),
+        [0, 1, 2]
+    );
+
+    // Clean up like the client would.
+    unsafe {
+        std::ptr::drop_in_place(raw.as_mut_slice());
+        let layout = Layout::array::<Tracked>(raw.capacity()).unwrap();
+        heap.deallocate(raw.as_ptr() as *mut u8, layout);
+    }
+    assert_eq!(calls(), (1, 1));
+    assert_eq!(drops.get(), 3);
+}
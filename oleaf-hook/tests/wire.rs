@@ -0,0 +1,173 @@
+use std::io;
+
+use oleaf_hook::dml::{
+    wire::{self, FieldDef, WireType},
+    OwnedField, OwnedRecord, OwnedValue, TypeId,
+};
+
+fn schema() -> Vec<FieldDef> {
+    vec![
+        FieldDef::new("GlobalID", TypeId::Gid),
+        FieldDef::new("Delta", TypeId::Int),
+        FieldDef::new("QuestID", TypeId::UInt),
+        FieldDef::new("Scale", TypeId::Flt),
+        FieldDef::new("Offset", TypeId::Byt),
+        FieldDef::new("Flags", TypeId::UByt),
+        FieldDef::new("Port", TypeId::UShrt),
+        FieldDef::new("Time", TypeId::Dbl),
+        FieldDef::new("Speaker", TypeId::Str),
+        FieldDef::new("Title", TypeId::WStr),
+    ]
+}
+
+fn field(name: &str, value: OwnedValue) -> OwnedField {
+    OwnedField {
+        name: name.to_string(),
+        value,
+    }
+}
+
+fn record() -> OwnedRecord {
+    OwnedRecord {
+        fields: vec![
+            field("GlobalID", OwnedValue::Gid(0x0123_4567_89ab_cdef)),
+            field("Delta", OwnedValue::Int(-2)),
+            field("QuestID", OwnedValue::UInt(42)),
+            field("Scale", OwnedValue::Flt(1.5)),
+            field("Offset", OwnedValue::Byt(-1)),
+            field("Flags", OwnedValue::UByt(0x80)),
+            field("Port", OwnedValue::UShrt(12000)),
+            field("Time", OwnedValue::Dbl(-0.25)),
+            field("Speaker", OwnedValue::Str(b"Gamma".to_vec())),
+            field("Title", OwnedValue::WStr("Über".to_string())),
+        ],
+    }
+}
+
+#[rustfmt::skip]
+const ENCODED: &[u8] = &[
+    0xef, 0xcd, 0xab, 0x89, 0x67, 0x45, 0x23, 0x01,
+    0xfe, 0xff, 0xff, 0xff,
+    0x2a, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xc0, 0x3f,
+    0xff,
+    0x80,
+    0xe0, 0x2e,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xd0, 0xbf,
+    0x05, 0x00, b'G', b'a', b'm', b'm', b'a',
+    0x04, 0x00, 0xdc, 0x00, b'b', 0x00, b'e', 0x00, b'r', 0x00,
+];
+
+#[test]
+fn encode_layout() {
+    assert_eq!(wire::encode(&record(), &schema()).unwrap(), ENCODED);
+}
+
+#[test]
+fn decode_layout() {
+    assert_eq!(wire::decode(ENCODED, &schema()).unwrap(), record());
+}
+
+#[test]
+fn encode_uses_schema_order() {
+    let mut shuffled = record();
+    shuffled.fields.reverse();
+    shuffled.fields.push(field("Unused", OwnedValue::Int(0)));
+
+    assert_eq!(wire::encode(&shuffled, &schema()).unwrap(), ENCODED);
+}
+
+#[test]
+fn read_consecutive_records() {
+    let schema = [FieldDef::new("QuestID", TypeId::UInt)];
+    let mut data = &[1, 0, 0, 0, 2, 0, 0, 0][..];
+
+    let first = wire::read_record(&mut data, &schema).unwrap();
+    let second = wire::read_record(&mut data, &schema).unwrap();
+    assert_eq!(first.field("QuestID").unwrap().value, OwnedValue::UInt(1));
+    assert_eq!(second.field("QuestID").unwrap().value, OwnedValue::UInt(2));
+    assert!(data.is_empty());
+}
+
+#[test]
+fn decode_truncated() {
+    for len in 0..ENCODED.len() {
+        let err = wire::decode(&ENCODED[..len], &schema()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
+
+#[test]
+fn decode_trailing_bytes() {
+    let mut data = ENCODED.to_vec();
+    data.push(0);
+
+    let err = wire::decode(&data, &schema()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn decode_binary_str() {
+    let schema = [FieldDef::new("Speaker", TypeId::Str)];
+    let record = wire::decode(&[0x02, 0x00, 0xff, 0x00], &schema).unwrap();
+    assert_eq!(
+        record.fields,
+        [field("Speaker", OwnedValue::Str(vec![0xff, 0x00]))]
+    );
+    assert_eq!(
+        wire::encode(&record, &schema).unwrap(),
+        [0x02, 0x00, 0xff, 0x00]
+    );
+
+    let bytes = <Vec<u8> as WireType>::read(&mut &[0x01, 0x00, 0xff][..], "Speaker", TypeId::Str);
+    assert_eq!(bytes.unwrap(), [0xff]);
+
+    let err = String::read(&mut &[0x01, 0x00, 0xff][..], "Speaker", TypeId::Str).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn decode_invalid_strings() {
+    // An unpaired surrogate.
+    let schema = [FieldDef::new("Title", TypeId::WStr)];
+    let err = wire::decode(&[0x01, 0x00, 0x00, 0xd8], &schema).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn encode_missing_field() {
+    let record = OwnedRecord::default();
+
+    let err = wire::encode(&record, &schema()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn encode_type_mismatch() {
+    let schema = [FieldDef::new("QuestID", TypeId::UInt)];
+    let record = OwnedRecord {
+        fields: vec![field("QuestID", OwnedValue::Int(42))],
+    };
+
+    let err = wire::encode(&record, &schema).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn encode_string_too_long() {
+    let schema = [FieldDef::new("Speaker", TypeId::Str)];
+    let record = OwnedRecord {
+        fields: vec![field("Speaker", OwnedValue::Str(vec![b'x'; 0x10000]))],
+    };
+
+    let err = wire::encode(&record, &schema).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn unsupported_type() {
+    let schema = [FieldDef::new("Unknown", TypeId::Unknown(42))];
+
+    let err = wire::decode(&[], &schema).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,263 @@
+use std::{error::Error, ffi::c_void, mem, ptr, thread, time::Duration};
+
+use oleaf_hook::{
+    cxx::alloc::{self, GameHeap},
+    dml::schema::{self, Snapshot},
+    event, unload,
+};
+use windows::Win32::{
+    Foundation::{BOOL, HANDLE, HINSTANCE},
+    System::{
+        Console,
+        LibraryLoader::{DisableThreadLibraryCalls, FreeLibraryAndExitThread},
+        SystemServices::{DLL_PROCESS_ATTACH, DLL_PROCESS_DETACH},
+        Threading::{CreateThread, GetCurrentProcessId, THREAD_CREATE_RUN_IMMEDIATELY},
+    },
+    UI::{
+        Input::KeyboardAndMouse::{GetAsyncKeyState, VK_END},
+        WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId},
+    },
+};
+
+const SEND_EVENT_SIG: &str = "40 ?? 56 57 41 ?? 41 ?? 41 ?? 41 ?? 48 81 ?? ?? ?? ?? ?? ?? c7 ?? ?? ?? ?? ?? ?? ?? ?? 89 ?? ?? ?? ?? ?? ?? 48 8b ?? ?? ?? ?? ?? 48 33 ?? ?? 89 ?? ?? ?? ?? ?? ?? 4d 8b ?? ?? 89";
+const EVENT_HANDLER_GETTER_SIG: &str = "41 56 48 83 EC ?? 48 C7 44 24 20 FE FF FF FF 48 89 5C 24 ?? 48 89 6C 24 ?? 48 89 74 24 ?? 48 89 7C 24 ?? 48 8B FA 4C 8B C9";
+// `_malloc_base` and `_free_base` of a statically linked UCRT.
+const MALLOC_SIG: &str = "40 53 48 83 EC 20 48 8B D9 48 83 F9 E0 77 ??";
+const FREE_SIG: &str = "48 85 C9 74 ?? 53 48 83 EC 20 4C 8B C1 33 D2 48 8B 0D ?? ?? ?? ?? FF 15";
+
+unsafe fn initialize_detours() -> Result<(), Box<dyn Error>> {
+    let cur_mod = oleaf_hook::Module::pe().ok_or("Failed to find module")?;
+
+    let send_event_target: event::FnSendEvent =
+        std::mem::transmute(cur_mod.find_signature(SEND_EVENT_SIG)?);
+    let event_handler_getter: event::FnGetEventHandler =
+        std::mem::transmute(cur_mod.find_signature(EVENT_HANDLER_GETTER_SIG)?);
+
+    println!(
+        "EventHandler getter found at: {:x}",
+        event_handler_getter as usize
+    );
+    event::initialize_event_handler_getter(event_handler_getter);
+
+    // Not finding the game heap only rules out handing objects over to
+    // C++ code, so we carry on without it.
+    match GameHeap::from_imports(&cur_mod)
+        .or_else(|_| GameHeap::from_signatures(&cur_mod, MALLOC_SIG, FREE_SIG))
+    {
+        Ok(heap) => {
+            println!("Game heap found: {:?}", heap);
+            alloc::initialize_game_heap(heap);
+        }
+        Err(e) => println!("Game heap unavailable: {}", e),
+    }
+
+    event::SendEventHook
+        .initialize(send_event_target, event::send_event_detour)?
+        .enable()?;
+
+    println!("Hooked SendEvent\n");
+
+    Ok(())
+}
+
+// How often the bootstrap thread polls for an unload request.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+// How long we wait for threads to leave the detours before giving up.
+const UNLOAD_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Reads lines typed into the console without blocking.
+struct ConsoleInput {
+    handle: HANDLE,
+    line: String,
+}
+
+impl ConsoleInput {
+    unsafe fn new() -> Self {
+        Self {
+            handle: Console::GetStdHandle(Console::STD_INPUT_HANDLE),
+            line: String::new(),
+        }
+    }
+
+    // Consumes all pending key events and returns a line once it was
+    // completed with the return key.
+    unsafe fn poll_line(&mut self) -> Option<String> {
+        loop {
+            let mut pending = 0;
+            if !Console::GetNumberOfConsoleInputEvents(self.handle, &mut pending).as_bool()
+                || pending == 0
+            {
+                return None;
+            }
+
+            let mut record: Console::INPUT_RECORD = mem::zeroed();
+            let mut read = 0;
+            if !Console::ReadConsoleInputW(self.handle, &mut record, 1, &mut read).as_bool()
+                || read == 0
+            {
+                return None;
+            }
+            if record.EventType as u32 != Console::KEY_EVENT {
+                continue;
+            }
+
+            // The event type tells us which union member is active.
+            let key = record.Event.KeyEvent;
+            if !key.bKeyDown.as_bool() {
+                continue;
+            }
+
+            match char::from_u32(key.uChar.UnicodeChar as u32) {
+                Some('\r') => {
+                    println!();
+                    return Some(mem::take(&mut self.line));
+                }
+                Some('\u{8}') => {
+                    if self.line.pop().is_some() {
+                        print!("\u{8} \u{8}");
+                    }
+                }
+                Some(c) if !c.is_control() => {
+                    print!("{}", c);
+                    self.line.push(c);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Handles the `schema` console commands for recording DML record layouts
+// and comparing them across game versions.
+fn schema_command(args: &[&str]) {
+    match args {
+        ["start"] => {
+            schema::start();
+            println!("Recording DML schema...");
+        }
+        ["save", path] => match schema::stop().save(path) {
+            Ok(()) => println!("Saved DML schema to {}", path),
+            Err(e) => println!("Failed to save DML schema: {}", e),
+        },
+        ["diff", old, new] => match (Snapshot::load(old), Snapshot::load(new)) {
+            (Ok(old), Ok(new)) => {
+                let changes = old.diff(&new);
+                for change in &changes {
+                    println!("{}", change);
+                }
+                println!("{} changes in DML schema", changes.len());
+            }
+            (Err(e), _) | (_, Err(e)) => println!("Failed to load DML schema: {}", e),
+        },
+        _ => println!("Usage: schema start | schema save <path> | schema diff <old> <new>"),
+    }
+}
+
+// Checks if the window in the foreground belongs to the game.
+unsafe fn is_foreground() -> bool {
+    let mut pid = 0;
+    GetWindowThreadProcessId(GetForegroundWindow(), &mut pid);
+    pid == GetCurrentProcessId()
+}
+
+// Blocks until an unload is requested through the `END` hotkey, the
+// `unload` console command or `oleaf_hook::unload::request`.
+unsafe fn wait_for_unload() {
+    let mut input = ConsoleInput::new();
+
+    loop {
+        // The key state is system-wide, so only honour it while the game
+        // window is focused. The most significant bit is set while the
+        // key is held down.
+        if is_foreground() && GetAsyncKeyState(VK_END.0 as i32) < 0 {
+            unload::request();
+        }
+        if let Some(line) = input.poll_line() {
+            match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["unload"] => unload::request(),
+                ["schema", args @ ..] => schema_command(args),
+                [] => {}
+                _ => println!("Unknown command: {}", line.trim()),
+            }
+        }
+
+        if unload::is_requested() {
+            return;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[inline(never)]
+unsafe extern "system" fn bootstrap_oleaf(module: *mut c_void) -> u32 {
+    let module = HINSTANCE(module as isize);
+
+    if let Err(e) = initialize_detours() {
+        println!("Failed to initialize oleaf: {}", e);
+        return 1;
+    }
+
+    println!("Press END in the game window or enter `unload` to unload oleaf.");
+    wait_for_unload();
+
+    println!("Unloading oleaf...");
+    if !unload::shutdown(UNLOAD_TIMEOUT) {
+        // Freeing the library now would pull the code out from under a
+        // thread which is still executing it.
+        println!("Threads did not leave the hooks in time, staying loaded");
+        return 1;
+    }
+
+    Console::FreeConsole();
+    FreeLibraryAndExitThread(module, 0)
+}
+
+unsafe fn main(
+    module: HINSTANCE,
+    call_reason: u32,
+    reserved: *const (),
+) -> Result<(), Box<dyn Error>> {
+    match call_reason {
+        DLL_PROCESS_ATTACH => {
+            DisableThreadLibraryCalls(module).ok()?;
+            Console::AllocConsole().ok()?;
+
+            // Bootstrap the functionality in a separate thread.
+            CreateThread(
+                ptr::null(),
+                0,
+                Some(bootstrap_oleaf),
+                module.0 as *const c_void,
+                THREAD_CREATE_RUN_IMMEDIATELY,
+                ptr::null_mut(),
+            );
+
+            Ok(())
+        }
+        DLL_PROCESS_DETACH => {
+            // When the library is freed without going through the unload
+            // sequence, at least make sure no patch keeps jumping into
+            // unmapped memory. We hold the loader lock here, so waiting
+            // for other threads is not an option. If the process is
+            // terminating, all other threads are gone already.
+            if reserved.is_null() && !unload::is_shut_down() {
+                event::unhook_all();
+            }
+
+            Console::FreeConsole().ok()?;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Entrypoint to the oleaf application.
+#[allow(clippy::missing_safety_doc, non_snake_case)]
+#[no_mangle]
+pub unsafe extern "system" fn DllMain(
+    module: HINSTANCE,
+    call_reason: u32,
+    reserved: *const (),
+) -> BOOL {
+    main(module, call_reason, reserved).is_ok().into()
+}